@@ -0,0 +1,282 @@
+// Distraction alerts, read from alerts.json in the data directory. Each rule puts a budget on a
+// category, or on all time with a negative productivity weight when no category is given:
+//
+//     [
+//         { "category": "Social Media", "continuous": "15m" },
+//         { "category": "Entertainment", "daily": "1h", "command": "~/bin/lock-screen" },
+//         { "continuous": "45m", "message": "Time to get back to it" }
+//     ]
+//
+// A continuous budget fires once per unbroken stretch in the category, a daily budget once per
+// day. Alerts show a desktop notification (or run the notify hook) and `command`, if any, with
+// the details in $PT_ALERT_TARGET, $PT_ALERT_KIND, $PT_ALERT_SPENT (seconds) and $PT_ALERT_APP.
+// Every alert is logged for `report alerts`.
+
+use crate::data::duration::{format_short, HumanDuration};
+use crate::data::{BudgetKind, DailySummary, DistractionAlert};
+use crate::data::timezone::DisplayZone;
+use crate::db::{self, Database};
+use crate::notify::Notifier;
+use chrono::{DateTime, Local, NaiveDate};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    category: Option<String>,
+    continuous: Option<String>,
+    daily: Option<String>,
+    message: Option<String>,
+    command: Option<String>,
+    #[serde(default = "default_notify")]
+    notify: bool,
+}
+
+fn default_notify() -> bool {
+    true
+}
+
+#[derive(Debug)]
+pub struct AlertRule {
+    // None watches all time weighted below zero
+    pub category: Option<String>,
+    pub kind: BudgetKind,
+    pub budget: Duration,
+    pub message: Option<String>,
+    pub command: Option<String>,
+    pub notify: bool,
+}
+
+impl AlertRule {
+    pub fn target(&self) -> String {
+        self.category.clone().unwrap_or_else(|| "unproductive time".to_string())
+    }
+
+    fn matches(&self, category: &str, weight: i8) -> bool {
+        match &self.category {
+            Some(watched) => watched.eq_ignore_ascii_case(category),
+            None => weight < 0,
+        }
+    }
+}
+
+pub fn alerts_path() -> PathBuf {
+    db::data_dir().join("alerts.json")
+}
+
+// No file means no alerts
+pub fn load_rules() -> Result<Vec<AlertRule>, Box<dyn Error>> {
+    let path = alerts_path();
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = std::fs::read_to_string(&path)?;
+    let raw: Vec<RawRule> = serde_json::from_str(&contents)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    let mut rules = Vec::with_capacity(raw.len());
+    for (i, rule) in raw.into_iter().enumerate() {
+        let invalid = |message: String| format!("{}: rule {}: {}", path.display(), i + 1, message);
+        let (kind, budget) = match (rule.continuous, rule.daily) {
+            (Some(budget), None) => (BudgetKind::Continuous, budget),
+            (None, Some(budget)) => (BudgetKind::Daily, budget),
+            _ => return Err(invalid("needs exactly one of \"continuous\" or \"daily\"".to_string()).into()),
+        };
+        let budget: HumanDuration = budget.parse().map_err(invalid)?;
+        if budget.0.is_zero() {
+            return Err(invalid("budget must be longer than zero".to_string()).into());
+        }
+        rules.push(AlertRule {
+            category: rule.category,
+            kind,
+            budget: budget.0,
+            message: rule.message,
+            command: rule.command,
+            notify: rule.notify,
+        });
+    }
+    Ok(rules)
+}
+
+// Tracks how long the current stretch has lasted and which rules already fired
+pub struct AlertMonitor {
+    rules: Vec<AlertRule>,
+    notifier: Notifier,
+    // When each rule's current unbroken stretch began
+    stretch_started: HashMap<usize, DateTime<Local>>,
+    fired_continuous: Vec<usize>,
+    fired_daily: HashMap<usize, NaiveDate>,
+}
+
+impl AlertMonitor {
+    // Falls back to no alerts, with a warning, like the categorization rules
+    pub fn load_or_warn() -> Self {
+        let rules = load_rules().unwrap_or_else(|e| {
+            eprintln!("Ignoring alert rules: {}", e);
+            Vec::new()
+        });
+        Self {
+            rules,
+            notifier: Notifier::new(None),
+            stretch_started: HashMap::new(),
+            fired_continuous: Vec::new(),
+            fired_daily: HashMap::new(),
+        }
+    }
+
+    // Pausing or tracking manually breaks every stretch
+    pub fn reset(&mut self) {
+        self.stretch_started.clear();
+        self.fired_continuous.clear();
+    }
+
+    // Called on every sample with what is in front now. `unsaved_since` is the start of the span
+    // not yet in the database, which counts towards daily budgets.
+    pub fn check(&mut self, db: &Database, app: &str, category: &str, weight: i8, unsaved_since: DateTime<Local>) -> Result<Vec<DistractionAlert>, Box<dyn Error>> {
+        let now = Local::now();
+        let today = now.date_naive();
+        let mut raised = Vec::new();
+        // Read at most once per sample, and only while a daily budget is still open
+        let mut saved_today: Option<Vec<DailySummary>> = None;
+
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(category, weight) {
+                self.stretch_started.remove(&index);
+                self.fired_continuous.retain(|i| *i != index);
+                continue;
+            }
+
+            let spent = match rule.kind {
+                BudgetKind::Continuous => {
+                    if self.fired_continuous.contains(&index) {
+                        continue;
+                    }
+                    let started = *self.stretch_started.entry(index).or_insert(unsaved_since);
+                    (now - started).to_std().unwrap_or_default()
+                },
+                BudgetKind::Daily => {
+                    if self.fired_daily.get(&index) == Some(&today) {
+                        continue;
+                    }
+                    let unsaved = (now - unsaved_since.max(DisplayZone::Local.start_of_day(today))).to_std().unwrap_or_default();
+                    let rows = match &mut saved_today {
+                        Some(rows) => rows,
+                        None => saved_today.insert(db.get_daily_summaries(today, today, None)?),
+                    };
+                    time_today(rows, rule) + unsaved
+                },
+            };
+            if spent < rule.budget {
+                continue;
+            }
+
+            match rule.kind {
+                BudgetKind::Continuous => self.fired_continuous.push(index),
+                BudgetKind::Daily => { self.fired_daily.insert(index, today); },
+            }
+            let mut alert = DistractionAlert {
+                id: None,
+                target: rule.target(),
+                kind: rule.kind,
+                budget: rule.budget,
+                spent,
+                app: app.to_string(),
+                triggered_at: now,
+            };
+            alert.id = Some(db.save_alert(&alert)?);
+            deliver(&self.notifier, rule, &alert);
+            raised.push(alert);
+        }
+        Ok(raised)
+    }
+}
+
+// Saved time since midnight counting against a daily budget, from today's rollup rows
+fn time_today(rows: &[DailySummary], rule: &AlertRule) -> Duration {
+    let seconds: u64 = rows.iter()
+        .filter(|r| r.tag.is_none())
+        .map(|r| match &rule.category {
+            Some(watched) if watched.eq_ignore_ascii_case(&r.category) => r.seconds,
+            Some(_) => 0,
+            None => r.unproductive_seconds,
+        })
+        .sum();
+    Duration::from_secs(seconds)
+}
+
+fn deliver(notifier: &Notifier, rule: &AlertRule, alert: &DistractionAlert) {
+    let title = match alert.kind {
+        BudgetKind::Continuous => format!("{} of {} in a row", format_short(alert.spent), alert.target),
+        BudgetKind::Daily => format!("{} of {} today", format_short(alert.spent), alert.target),
+    };
+    let body = rule.message.clone()
+        .unwrap_or_else(|| format!("Over your {} {} budget", format_short(alert.budget), alert.kind.as_str()));
+    println!("Alert: {} - {}", title, body);
+
+    if rule.notify && !notifier.send(&title, &body) {
+        eprintln!("Could not show a desktop notification for the alert");
+    }
+    // Not waited for; tokio reaps the child once it exits
+    if let Some(command) = &rule.command {
+        let spawned = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("PT_ALERT_TARGET", &alert.target)
+            .env("PT_ALERT_KIND", alert.kind.as_str())
+            .env("PT_ALERT_SPENT", alert.spent.as_secs().to_string())
+            .env("PT_ALERT_APP", &alert.app)
+            .stdin(Stdio::null())
+            .spawn();
+        if let Err(e) = spawned {
+            eprintln!("Failed to run alert command: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(category: &str, tag: Option<&str>, seconds: u64, unproductive_seconds: u64) -> DailySummary {
+        DailySummary {
+            date: NaiveDate::from_ymd_opt(2026, 3, 2).unwrap(),
+            category: category.to_string(),
+            app: "Safari".to_string(),
+            tag: tag.map(|t| t.to_string()),
+            seconds,
+            activities: 1,
+            weighted_seconds: -(unproductive_seconds as i64),
+            productive_seconds: seconds - unproductive_seconds,
+            unproductive_seconds,
+        }
+    }
+
+    fn rule(category: Option<&str>) -> AlertRule {
+        AlertRule {
+            category: category.map(|c| c.to_string()),
+            kind: BudgetKind::Daily,
+            budget: Duration::from_secs(3600),
+            message: None,
+            command: None,
+            notify: false,
+        }
+    }
+
+    #[test]
+    fn daily_time_comes_from_untagged_rollup_rows() {
+        let rows = [
+            row("Social Media", None, 600, 600),
+            row("Social Media", Some("automatic"), 600, 600),
+            row("Development", None, 1200, 0),
+            row("Entertainment", None, 300, 300),
+        ];
+        assert_eq!(time_today(&rows, &rule(Some("social media"))), Duration::from_secs(600));
+        assert_eq!(time_today(&rows, &rule(None)), Duration::from_secs(900));
+    }
+}
@@ -0,0 +1,517 @@
+use crate::data::timezone::DisplayZone;
+use crate::data::{Activity, CalendarEvent};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, NaiveDateTime, Utc, Weekday};
+use std::error::Error;
+use std::io::Write;
+
+// RFC 5545 recommends folding content lines longer than 75 octets
+const MAX_LINE_OCTETS: usize = 75;
+
+pub fn write_activities<W: Write>(activities: &[Activity], mut out: W) -> Result<(), Box<dyn Error>> {
+    let stamp = format_utc(&Local::now());
+
+    write_line(&mut out, "BEGIN:VCALENDAR")?;
+    write_line(&mut out, "VERSION:2.0")?;
+    write_line(&mut out, "PRODID:-//productivity_tracker//activities//EN")?;
+    write_line(&mut out, "CALSCALE:GREGORIAN")?;
+
+    for activity in activities {
+        // Activities still running have no block to draw yet
+        let end = match activity.end_time {
+            Some(end) => end,
+            None => continue,
+        };
+
+        let uid = match (&activity.id, &activity.source) {
+            (Some(id), _) => format!("activity-{}@productivity_tracker", id),
+            (None, Some(source)) => format!("{}@productivity_tracker", source),
+            (None, None) => format!("activity-{}@productivity_tracker", activity.start_time.timestamp()),
+        };
+
+        write_line(&mut out, "BEGIN:VEVENT")?;
+        write_line(&mut out, &format!("UID:{}", escape(&uid)))?;
+        write_line(&mut out, &format!("DTSTAMP:{}", stamp))?;
+        write_line(&mut out, &format!("DTSTART:{}", format_utc(&activity.start_time)))?;
+        write_line(&mut out, &format!("DTEND:{}", format_utc(&end)))?;
+        write_line(&mut out, &format!("SUMMARY:{}", escape(&activity.name)))?;
+        write_line(&mut out, &format!("CATEGORIES:{}", escape(&activity.category)))?;
+
+        let mut description = Vec::new();
+        if !activity.tags.is_empty() {
+            description.push(format!("Tags: {}", activity.tags.join(", ")));
+        }
+        description.push(format!("Weight: {:+}", activity.weight));
+        if let Some(notes) = &activity.notes {
+            description.push(notes.clone());
+        }
+        write_line(&mut out, &format!("DESCRIPTION:{}", escape(&description.join("\n"))))?;
+        // Tracked time is informational, it should not block the calendar
+        write_line(&mut out, "TRANSP:TRANSPARENT")?;
+        write_line(&mut out, "END:VEVENT")?;
+    }
+
+    write_line(&mut out, "END:VCALENDAR")?;
+    Ok(())
+}
+
+// Events from a calendar file. All-day events carry no meeting time and are skipped. Daily and
+// weekly RRULEs are expanded up to a year ahead; other rules contribute their first occurrence
+// only and are counted in `unexpanded`.
+pub struct ParsedCalendar {
+    pub events: Vec<CalendarEvent>,
+    pub skipped_all_day: usize,
+    pub unexpanded: usize,
+    // TZID values that are not IANA names; their times were read as local time
+    pub unknown_zones: Vec<String>,
+}
+
+// How far ahead open-ended recurrences are expanded
+const RECURRENCE_HORIZON_DAYS: i64 = 366;
+
+pub fn parse_calendar(contents: &str) -> Result<ParsedCalendar, String> {
+    let mut parsed = ParsedCalendar { events: Vec::new(), skipped_all_day: 0, unexpanded: 0, unknown_zones: Vec::new() };
+    // Single occurrences moved or edited in their own VEVENT (RECURRENCE-ID). They replace the
+    // occurrence the rule generates wherever they appear in the file.
+    let mut overrides: Vec<CalendarEvent> = Vec::new();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for line in unfold(contents) {
+        let (name, value) = match line.split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        match (name.to_uppercase().as_str(), value.trim().to_uppercase().as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = current.take() {
+                    match events_from_properties(&properties, &mut parsed.unknown_zones)? {
+                        Some(Occurrences { events, unexpanded, replaces_occurrence }) => {
+                            if unexpanded {
+                                parsed.unexpanded += 1;
+                            }
+                            if replaces_occurrence {
+                                overrides.extend(events);
+                            } else {
+                                parsed.events.extend(events);
+                            }
+                        },
+                        None => parsed.skipped_all_day += 1,
+                    }
+                }
+            },
+            _ => {
+                if let Some(properties) = current.as_mut() {
+                    properties.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+
+    parsed.events.retain(|event| !overrides.iter().any(|o| o.uid == event.uid));
+    parsed.events.extend(overrides);
+    Ok(parsed)
+}
+
+struct Occurrences {
+    events: Vec<CalendarEvent>,
+    // The event has a rule that is not expanded, so only its first occurrence is included
+    unexpanded: bool,
+    // The event stands in for one occurrence of a rule and carries that occurrence's UID
+    replaces_occurrence: bool,
+}
+
+// A DTSTART or DTEND as written: wall-clock time and the zone it is in
+#[derive(Clone, Copy)]
+struct Stamp {
+    naive: NaiveDateTime,
+    zone: DisplayZone,
+}
+
+impl Stamp {
+    fn resolve(&self) -> DateTime<Local> {
+        self.zone.resolve(self.naive)
+    }
+}
+
+fn events_from_properties(properties: &[(String, String)], unknown_zones: &mut Vec<String>) -> Result<Option<Occurrences>, String> {
+    let find = |key: &str| {
+        properties.iter().find(|(name, _)| {
+            let base = name.split(';').next().unwrap_or_default();
+            base.eq_ignore_ascii_case(key)
+        })
+    };
+
+    let (start_name, start_value) = find("DTSTART").ok_or("VEVENT without DTSTART")?;
+    let start_stamp = match parse_datetime(start_name, start_value, unknown_zones)? {
+        Some(start) => start,
+        None => return Ok(None),
+    };
+    let start = start_stamp.resolve();
+    let end = match find("DTEND") {
+        Some((name, value)) => parse_datetime(name, value, unknown_zones)?.map(|e| e.resolve()).unwrap_or(start),
+        None => match find("DURATION") {
+            Some((_, value)) => start.checked_add_signed(parse_duration(value)?)
+                .ok_or_else(|| format!("duration '{}' is too long", value.trim()))?,
+            None => start,
+        },
+    };
+
+    let summary = find("SUMMARY").map(|(_, v)| unescape(v)).unwrap_or_else(|| "Untitled event".to_string());
+    let uid = find("UID")
+        .map(|(_, v)| v.trim().to_string())
+        .unwrap_or_else(|| format!("{}-{}", start.timestamp(), summary));
+    let location = find("LOCATION").map(|(_, v)| unescape(v)).filter(|l| !l.is_empty());
+    let length = end - start;
+    let event = |uid: String, start_time: DateTime<Local>| -> Result<CalendarEvent, String> {
+        Ok(CalendarEvent {
+            uid,
+            summary: summary.clone(),
+            start_time,
+            end_time: start_time.checked_add_signed(length).ok_or_else(|| format!("event '{}' ends too late", summary))?,
+            location: location.clone(),
+        })
+    };
+
+    if let Some((name, value)) = find("RECURRENCE-ID") {
+        let Some(original) = parse_datetime(name, value, unknown_zones)? else {
+            return Ok(None);
+        };
+        let uid = occurrence_uid(&uid, &original.resolve());
+        return Ok(Some(Occurrences { events: vec![event(uid, start)?], unexpanded: false, replaces_occurrence: true }));
+    }
+
+    let Some((_, rule)) = find("RRULE") else {
+        return Ok(Some(Occurrences { events: vec![event(uid, start)?], unexpanded: false, replaces_occurrence: false }));
+    };
+    let Some(rule) = Rule::parse(rule, start_stamp.zone) else {
+        return Ok(Some(Occurrences { events: vec![event(uid, start)?], unexpanded: true, replaces_occurrence: false }));
+    };
+
+    let mut excluded = Vec::new();
+    for (name, value) in properties.iter().filter(|(name, _)| name.split(';').next().unwrap_or_default().eq_ignore_ascii_case("EXDATE")) {
+        for value in value.split(',') {
+            if let Some(stamp) = parse_datetime(name, value, unknown_zones)? {
+                excluded.push(stamp.resolve());
+            }
+        }
+    }
+    let horizon = Local::now() + chrono::Duration::days(RECURRENCE_HORIZON_DAYS);
+    let events = rule.occurrences(start_stamp, horizon)
+        .into_iter()
+        .filter(|time| !excluded.contains(time))
+        .map(|time| event(occurrence_uid(&uid, &time), time))
+        .collect::<Result<_, _>>()?;
+    Ok(Some(Occurrences { events, unexpanded: false, replaces_occurrence: false }))
+}
+
+// Each occurrence is stored as an event of its own, keyed by when the rule put it
+fn occurrence_uid(uid: &str, start: &DateTime<Local>) -> String {
+    format!("{}/{}", uid, format_utc(start))
+}
+
+// The supported part of RFC 5545 recurrence rules: FREQ=DAILY or WEEKLY, with INTERVAL, COUNT,
+// UNTIL and, for weekly rules, BYDAY without ordinals
+struct Rule {
+    weekly: bool,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<DateTime<Local>>,
+    by_day: Vec<Weekday>,
+}
+
+impl Rule {
+    fn parse(value: &str, zone: DisplayZone) -> Option<Rule> {
+        let mut rule = Rule { weekly: false, interval: 1, count: None, until: None, by_day: Vec::new() };
+        let mut frequency = None;
+        for part in value.trim().split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=')?;
+            match key.to_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.to_uppercase()),
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => rule.until = Some(parse_until(value, zone)?),
+                "BYDAY" => {
+                    rule.by_day = value.split(',').map(|day| match day.to_uppercase().as_str() {
+                        "MO" => Some(Weekday::Mon),
+                        "TU" => Some(Weekday::Tue),
+                        "WE" => Some(Weekday::Wed),
+                        "TH" => Some(Weekday::Thu),
+                        "FR" => Some(Weekday::Fri),
+                        "SA" => Some(Weekday::Sat),
+                        "SU" => Some(Weekday::Sun),
+                        _ => None,
+                    }).collect::<Option<_>>()?;
+                },
+                // Only changes which week a day falls in for intervals above one; weeks start on Monday
+                "WKST" => {},
+                _ => return None,
+            }
+        }
+        rule.weekly = match frequency?.as_str() {
+            "DAILY" if rule.by_day.is_empty() => false,
+            "WEEKLY" => true,
+            _ => return None,
+        };
+        Some(rule)
+    }
+
+    // Start times up to UNTIL, COUNT or `horizon`, whichever comes first, at the same wall-clock
+    // time in the event's zone so they stay put across DST changes. A step past the last
+    // representable date ends the rule as well.
+    fn occurrences(&self, start: Stamp, horizon: DateTime<Local>) -> Vec<DateTime<Local>> {
+        let first_day = start.naive.date();
+        let mut days: Vec<u32> = if self.weekly && !self.by_day.is_empty() {
+            self.by_day.iter().map(|d| d.num_days_from_monday()).collect()
+        } else {
+            vec![first_day.weekday().num_days_from_monday()]
+        };
+        days.sort_unstable();
+        days.dedup();
+        let week_start = first_day.checked_sub_days(Days::new(first_day.weekday().num_days_from_monday() as u64));
+
+        let mut times = Vec::new();
+        for step in 0u64.. {
+            let offset = step.checked_mul(self.interval as u64);
+            let candidates: Option<Vec<NaiveDate>> = if self.weekly {
+                let week = week_start.zip(offset).and_then(|(week, o)| week.checked_add_days(Days::new(o.checked_mul(7)?)));
+                week.and_then(|week| days.iter().map(|d| week.checked_add_days(Days::new(*d as u64))).collect())
+            } else {
+                offset.and_then(|o| first_day.checked_add_days(Days::new(o))).map(|day| vec![day])
+            };
+            let Some(candidates) = candidates else {
+                return times;
+            };
+            for day in candidates.into_iter().filter(|day| *day >= first_day) {
+                let time = Stamp { naive: day.and_time(start.naive.time()), zone: start.zone }.resolve();
+                let finished = self.count.is_some_and(|count| times.len() >= count)
+                    || self.until.is_some_and(|until| time > until)
+                    || time > horizon;
+                if finished {
+                    return times;
+                }
+                times.push(time);
+            }
+        }
+        times
+    }
+}
+
+// UNTIL is a UTC time, or a date meaning up to the end of that day
+fn parse_until(value: &str, zone: DisplayZone) -> Option<DateTime<Local>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some(zone.resolve(date.and_hms_opt(23, 59, 59)?));
+    }
+    let naive = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    Some(if value.ends_with('Z') { naive.and_utc().with_timezone(&Local) } else { zone.resolve(naive) })
+}
+
+// Handles UTC ("...Z"), floating local times, TZID parameters and all-day dates (returned as
+// None). A TZID that is not an IANA name is noted in `unknown_zones` and read as local time.
+fn parse_datetime(name: &str, value: &str, unknown_zones: &mut Vec<String>) -> Result<Option<Stamp>, String> {
+    let value = value.trim();
+    let params: Vec<&str> = name.split(';').skip(1).collect();
+
+    if params.iter().any(|p| p.eq_ignore_ascii_case("VALUE=DATE")) || (value.len() == 8 && NaiveDate::parse_from_str(value, "%Y%m%d").is_ok()) {
+        return Ok(None);
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map_err(|_| format!("invalid date-time '{}'", value))?;
+        return Ok(Some(Stamp { naive, zone: DisplayZone::Utc }));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map_err(|_| format!("invalid date-time '{}'", value))?;
+    let zone = match params.iter().find_map(|p| p.strip_prefix("TZID=")).map(|tzid| tzid.trim_matches('"')) {
+        Some(tzid) => tzid.parse::<DisplayZone>().unwrap_or_else(|_| {
+            if !unknown_zones.iter().any(|z| z == tzid) {
+                unknown_zones.push(tzid.to_string());
+            }
+            DisplayZone::Local
+        }),
+        None => DisplayZone::Local,
+    };
+    Ok(Some(Stamp { naive, zone }))
+}
+
+// Subset of RFC 5545 durations used by calendar apps: PT1H30M, PT45M, P1D
+fn parse_duration(value: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("invalid duration '{}'", value);
+    let body = value.trim().strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut total = chrono::Duration::zero();
+    let mut number = String::new();
+    for c in body.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {},
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                let part = match c {
+                    'W' => chrono::Duration::try_weeks(n),
+                    'D' => chrono::Duration::try_days(n),
+                    'H' => chrono::Duration::try_hours(n),
+                    'M' => chrono::Duration::try_minutes(n),
+                    _ => chrono::Duration::try_seconds(n),
+                };
+                total = part.and_then(|part| total.checked_add(&part))
+                    .ok_or_else(|| format!("duration '{}' is too long", value.trim()))?;
+            },
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(total)
+}
+
+fn format_utc(time: &DateTime<Local>) -> String {
+    time.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(other) => result.push(other),
+                None => {},
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+// Continuation lines start with a space or tab
+fn unfold(contents: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in contents.lines() {
+        let raw = raw.trim_end_matches('\r');
+        if let Some(rest) = raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        lines.push(raw.to_string());
+    }
+    lines
+}
+
+fn write_line<W: Write>(out: &mut W, line: &str) -> std::io::Result<()> {
+    let mut current = 0;
+    let mut first = true;
+
+    while current < line.len() {
+        let limit = if first { MAX_LINE_OCTETS } else { MAX_LINE_OCTETS - 1 };
+        let mut end = (current + limit).min(line.len());
+        // Never split inside a UTF-8 sequence
+        while !line.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        if !first {
+            out.write_all(b" ")?;
+        }
+        out.write_all(&line.as_bytes()[current..end])?;
+        out.write_all(b"\r\n")?;
+
+        current = end;
+        first = false;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(events: &[&str]) -> String {
+        let events: Vec<String> = events.iter().map(|e| format!("BEGIN:VEVENT\r\n{}\r\nEND:VEVENT", e.trim().replace('\n', "\r\n"))).collect();
+        format!("BEGIN:VCALENDAR\r\n{}\r\nEND:VCALENDAR\r\n", events.join("\r\n"))
+    }
+
+    fn berlin(events: &[CalendarEvent]) -> Vec<String> {
+        let zone: DisplayZone = "Europe/Berlin".parse().unwrap();
+        events.iter().map(|e| zone.naive_local(&e.start_time).format("%a %Y-%m-%d %H:%M").to_string()).collect()
+    }
+
+    #[test]
+    fn expands_weekly_rules_on_the_same_wall_clock_time() {
+        let parsed = parse_calendar(&calendar(&["
+UID:standup
+SUMMARY:Standup
+DTSTART;TZID=Europe/Berlin:20260323T093000
+DTEND;TZID=Europe/Berlin:20260323T094500
+RRULE:FREQ=WEEKLY;BYDAY=MO,TH;COUNT=4"])).unwrap();
+        assert_eq!(berlin(&parsed.events), [
+            "Mon 2026-03-23 09:30", "Thu 2026-03-26 09:30", "Mon 2026-03-30 09:30", "Thu 2026-04-02 09:30",
+        ]);
+        assert!(parsed.events.iter().all(|e| e.end_time - e.start_time == chrono::Duration::minutes(15)));
+        assert_eq!(parsed.unexpanded, 0);
+    }
+
+    #[test]
+    fn daily_rules_stop_at_until_and_skip_exdates_and_overrides() {
+        let parsed = parse_calendar(&calendar(&["
+UID:sync
+RECURRENCE-ID;TZID=Europe/Berlin:20260303T100000
+SUMMARY:Sync (moved)
+DTSTART;TZID=Europe/Berlin:20260303T150000
+DTEND;TZID=Europe/Berlin:20260303T153000", "
+UID:sync
+SUMMARY:Sync
+DTSTART;TZID=Europe/Berlin:20260301T100000
+DURATION:PT30M
+RRULE:FREQ=DAILY;UNTIL=20260304
+EXDATE;TZID=Europe/Berlin:20260302T100000"])).unwrap();
+        let mut starts = berlin(&parsed.events);
+        starts.sort();
+        assert_eq!(starts, ["Sun 2026-03-01 10:00", "Tue 2026-03-03 15:00", "Wed 2026-03-04 10:00"]);
+    }
+
+    #[test]
+    fn reports_unsupported_rules_and_unknown_zones() {
+        let parsed = parse_calendar(&calendar(&["
+UID:review
+DTSTART;TZID=Pacific Standard Time:20260301T100000
+RRULE:FREQ=MONTHLY;BYDAY=1MO"])).unwrap();
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.unexpanded, 1);
+        assert_eq!(parsed.unknown_zones, ["Pacific Standard Time"]);
+    }
+    #[test]
+    fn rejects_durations_and_steps_out_of_range_without_panicking() {
+        for duration in ["P99999999999999W", "PT9223372036854775807S", "P100000000D"] {
+            let error = parse_calendar(&calendar(&[&format!("
+UID:long
+DTSTART:20260301T100000Z
+DURATION:{}", duration)])).err().unwrap();
+            assert!(error.contains("too long"), "{}", error);
+        }
+
+        let parsed = parse_calendar(&calendar(&["
+UID:rare
+DTSTART:20260301T100000Z
+DURATION:PT1H
+RRULE:FREQ=WEEKLY;INTERVAL=4294967295", "
+UID:rarer
+DTSTART:20260301T100000Z
+DURATION:PT1H
+RRULE:FREQ=DAILY;INTERVAL=4294967295"])).unwrap();
+        assert_eq!(parsed.events.len(), 2);
+    }
+}
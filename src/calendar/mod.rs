@@ -0,0 +1,138 @@
+pub mod ics;
+
+use crate::data::{Activity, ActivityFilter, CalendarEvent};
+use crate::db::Database;
+use chrono::{DateTime, Local};
+use crate::privacy::Redactor;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Default)]
+pub struct CalendarImportSummary {
+    pub stored: usize,
+    pub created: usize,
+    pub annotated: usize,
+    pub skipped_all_day: usize,
+    pub before_since: usize,
+    pub since: Option<DateTime<Local>>,
+    pub unexpanded: usize,
+    pub unknown_zones: Vec<String>,
+}
+
+// Apps whose time is best described by the meeting on the calendar
+pub fn is_meeting_app(app_name: &str) -> bool {
+    let app_lower = app_name.to_lowercase();
+    app_lower.contains("zoom") ||
+        app_lower.contains("teams") ||
+        app_lower.contains("webex") ||
+        app_lower.contains("facetime") ||
+        app_lower.contains("meet.google.com")
+}
+
+// Stores the events so the daemon can name meetings as they happen, and reconciles past events
+// since `since` (default: the first tracked activity) with tracked time: meeting-app activities
+// inside an event are renamed after it, and events with no tracked time at all get an activity
+// of their own. Events from before tracking began are only stored, so importing years of history
+// does not fill them with meetings.
+pub fn import_calendar(db: &Database, path: &Path, since: Option<DateTime<Local>>) -> Result<CalendarImportSummary, Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    let parsed = ics::parse_calendar(&contents)?;
+    let redactor = Redactor::load()?;
+    let now = Local::now();
+    let since = match since {
+        Some(since) => Some(since),
+        None => db.first_activity_start()?,
+    };
+
+    let mut summary = CalendarImportSummary {
+        skipped_all_day: parsed.skipped_all_day,
+        unexpanded: parsed.unexpanded,
+        unknown_zones: parsed.unknown_zones,
+        since,
+        ..Default::default()
+    };
+
+    for mut event in parsed.events {
+        // Meeting titles end up as activity names, so they are masked like window titles
+        event.summary = redactor.text(&event.summary);
+        event.location = event.location.map(|l| redactor.text(&l));
+        db.save_calendar_event(&event)?;
+        summary.stored += 1;
+
+        if event.end_time > now || event.end_time <= event.start_time {
+            continue;
+        }
+        if since.is_none_or(|since| event.start_time < since) {
+            summary.before_since += 1;
+            continue;
+        }
+
+        let overlapping = db.get_activities(&ActivityFilter {
+            from: Some(event.start_time - chrono::Duration::days(1)),
+            to: Some(event.end_time),
+            ..Default::default()
+        })?;
+        // An activity still running counts as running until now
+        let overlapping: Vec<Activity> = overlapping.into_iter()
+            .filter(|a| a.end_time.unwrap_or(now) > event.start_time)
+            .collect();
+
+        let source = format!("calendar:{}", event.uid);
+        if overlapping.is_empty() {
+            if !db.source_exists(&source)? {
+                db.save_activity(&meeting_activity(&event, source))?;
+                summary.created += 1;
+            }
+            continue;
+        }
+
+        for mut activity in overlapping {
+            // The daemon names the running one itself and would overwrite the change
+            if activity.end_time.is_some() && is_meeting_app(&activity.name) {
+                annotate(&mut activity, &event);
+                db.update_activity(&activity)?;
+                summary.annotated += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+// Name of the meeting running at the moment, if a meeting app is in front during a calendar event
+pub fn current_meeting_name(db: &Database, app_name: &str) -> Option<String> {
+    if !is_meeting_app(app_name) {
+        return None;
+    }
+    db.get_calendar_event_at(&chrono::Local::now())
+        .ok()
+        .flatten()
+        .map(|event| event.summary)
+}
+
+fn annotate(activity: &mut Activity, event: &CalendarEvent) {
+    let app = std::mem::replace(&mut activity.name, event.summary.clone());
+    if !activity.tags.iter().any(|t| t == "meeting") {
+        activity.tags.push("meeting".to_string());
+    }
+    if activity.notes.is_none() {
+        activity.notes = Some(format!("Calendar event via {}", app));
+    }
+}
+
+fn meeting_activity(event: &CalendarEvent, source: String) -> Activity {
+    let mut activity = Activity::new(
+        event.summary.clone(),
+        "Communication".to_string(),
+        vec!["meeting".to_string(), "calendar".to_string()],
+        false,
+    );
+    // Meetings are work but not focused work, as when the daemon records them
+    activity.set_weight(0);
+    activity.start_time = event.start_time;
+    activity.end_time = Some(event.end_time);
+    activity.duration = Some(event.end_time.signed_duration_since(event.start_time).to_std().unwrap_or_default());
+    activity.notes = event.location.clone();
+    activity.source = Some(source);
+    activity
+}
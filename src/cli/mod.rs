@@ -3,7 +3,29 @@ use colored::*;
 use crate::tracker::Tracker;
 use crate::monitor::AppMonitor;
 use crate::db::Database;
-use rusqlite::Result;
+use crate::data::{weight_from_flag, Activity, ActivityFilter, DailySummary, Goal, GoalDirection, GoalPeriod, GoalTarget};
+use crate::data::duration::{format_short, HumanDuration};
+use crate::data::timezone::{DayBoundary, DisplayZone};
+use crate::export::{self, Column, DurationFormat, ExportFormat, ExportOptions};
+use crate::import::{self, ColumnMapping, ImportFormat, ImportOptions};
+use crate::calendar;
+use crate::reports::{self, html, GroupBy, ReportFormat};
+use crate::reports::commits::{self, CommitOptions};
+use crate::reports::compare::{self, Period};
+use crate::monitor::git;
+use crate::server::{self, Bind};
+use crate::ipc::{self, ControlServer, Request, Response};
+use crate::service::{self, InstanceLock};
+use crate::shell::{self, ShellEvent, ShellKind, ShellPhase};
+use crate::focus::{self, FocusOptions};
+use crate::alerts;
+use crate::dashboard;
+use crate::timeline;
+use crate::privacy::{self, Redactor};
+use crate::goals::{self, DayMark};
+use chrono::{Datelike, NaiveDate};
+use std::error::Error;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "productivity_tracker")]
@@ -28,6 +50,10 @@ enum Commands {
         
         #[arg(short, long, help = "Mark as productive", default_value = "true")]
         productive: bool,
+        
+        #[arg(short, long, allow_negative_numbers = true, value_parser = clap::value_parser!(i8).range(-2..=2),
+            help = "Productivity weight from -2 (distraction) to 2 (deep work), instead of --productive")]
+        weight: Option<i8>,
     },
     
     #[command(about = "Stop tracking the current activity")]
@@ -36,45 +62,496 @@ enum Commands {
     #[command(about = "Show the current activity")]
     Current,
     
+    #[command(about = "Pause automatic tracking in the running daemon (also: SIGUSR1)")]
+    Pause {
+        #[arg(long = "for", help = "Snooze for a while, e.g. 30m or 1h, then resume automatically")]
+        duration: Option<HumanDuration>,
+    },
+    
+    #[command(about = "Resume automatic tracking in the running daemon (also: SIGUSR2)")]
+    Resume,
+    
+    #[command(about = "Show the state of the running daemon")]
+    Status,
+    
+    #[command(about = "Work in timed focus blocks with breaks, with a notification at the end of each")]
+    Focus {
+        #[command(subcommand)]
+        action: FocusAction,
+    },
+    
+    #[command(about = "Add, list or remove daily and weekly time goals")]
+    Goal {
+        #[command(subcommand)]
+        action: GoalAction,
+    },
+    
+    #[command(about = "Show progress towards goals for today and this week, with streaks")]
+    Goals {
+        #[arg(long, help = "Show progress as of this day (YYYY-MM-DD), defaults to today")]
+        date: Option<NaiveDate>,
+    },
+    
+    #[command(about = "Show a day as hourly bars or contiguous blocks, with pauses and idle time marked")]
+    Timeline {
+        #[arg(long, help = "Day to show (YYYY-MM-DD), defaults to today")]
+        date: Option<NaiveDate>,
+        
+        #[arg(short, long, help = "List contiguous blocks instead of one bar per hour")]
+        blocks: bool,
+    },
+    
+    #[command(about = "Open a live terminal dashboard of today's or this week's tracking")]
+    Dashboard {
+        #[arg(short, long, help = "Number of recent activities to list", default_value = "12")]
+        rows: usize,
+        
+        #[arg(short, long, help = "Open in the week view instead of today")]
+        week: bool,
+    },
+    
     #[command(about = "Run in background mode to automatically track application usage")]
     Daemon {
         #[arg(short, long, help = "Sampling interval in seconds", default_value = "5")]
         interval: u64,
     },
+    
+    #[command(about = "Print shell hooks that report terminal commands to the tracker")]
+    ShellHook {
+        #[arg(value_enum, help = "Shell to print the hooks for")]
+        shell: ShellKind,
+    },
+    
+    // Called by the shell hooks, not meant to be typed
+    #[command(hide = true)]
+    ShellEvent {
+        #[arg(value_enum)]
+        phase: ShellPhase,
+        
+        #[arg(long)]
+        pid: u32,
+        
+        #[arg(long, help = "Unix time the command started")]
+        started: i64,
+        
+        #[arg(long)]
+        status: Option<i32>,
+        
+        #[arg(long)]
+        cwd: String,
+        
+        #[arg(long, help = "Read the command line from stdin, where other users cannot see it")]
+        stdin: bool,
+        
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    
+    #[command(about = "Run the daemon as a systemd user service or launchd agent")]
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+    
+    #[command(about = "Export recorded activities as CSV, JSON, NDJSON or iCalendar")]
+    Export {
+        #[arg(short, long, value_enum, help = "Output format", default_value = "csv")]
+        format: ExportFormat,
+        
+        #[arg(long, help = "First day to include (YYYY-MM-DD)")]
+        from: Option<NaiveDate>,
+        
+        #[arg(long, help = "Last day to include (YYYY-MM-DD)")]
+        to: Option<NaiveDate>,
+        
+        #[arg(short, long, help = "Only export activities in this category")]
+        category: Option<String>,
+        
+        #[arg(short, long, help = "Only export activities with any of these tags (comma separated)")]
+        tags: Option<String>,
+        
+        #[arg(long, value_enum, value_delimiter = ',', help = "Columns to include (comma separated, default: all)")]
+        columns: Vec<Column>,
+        
+        #[arg(long, help = "Time zone for timestamps: local, utc, +HH:MM or an IANA name", default_value = "local")]
+        tz: DisplayZone,
+        
+        #[arg(long, value_enum, help = "Duration format", default_value = "seconds")]
+        durations: DurationFormat,
+        
+        #[arg(short, long, help = "Write to a file instead of stdout")]
+        out: Option<PathBuf>,
+    },
+    
+    #[command(about = "Import activities from Toggl, Clockify, ActivityWatch or a generic CSV file")]
+    Import {
+        #[arg(value_enum, help = "Format of the file to import")]
+        format: ImportFormat,
+        
+        #[arg(help = "Path to the exported file")]
+        file: PathBuf,
+        
+        #[arg(short, long, help = "Column mapping for generic CSV, e.g. name=Task,start=Begin,end=Finish,category=Project")]
+        map: Option<ColumnMapping>,
+        
+        #[arg(long, help = "chrono format string for dates or timestamps that are not auto-detected")]
+        date_format: Option<String>,
+        
+        #[arg(long, help = "Time zone the file's wall-clock times are in", default_value = "local")]
+        tz: DisplayZone,
+        
+        #[arg(long, help = "Only show what would be imported")]
+        dry_run: bool,
+    },
+    
+    #[command(about = "Serve a local HTTP JSON API for activities and status")]
+    Serve {
+        #[arg(short, long, help = "Loopback address to listen on", default_value = "127.0.0.1:8787")]
+        addr: std::net::SocketAddr,
+        
+        #[arg(short, long, help = "Listen on this Unix domain socket instead of TCP")]
+        socket: Option<PathBuf>,
+        
+        #[arg(long, help = "API token (default: generated and stored in ~/.productivity_tracker/api_token)")]
+        token: Option<String>,
+    },
+    
+    #[command(about = "Import meetings from an iCalendar (.ics) file")]
+    ImportCalendar {
+        #[arg(help = "Path to the .ics file")]
+        file: PathBuf,
+        
+        #[arg(long, help = "Reconcile past events from this day on (YYYY-MM-DD, default: the first tracked activity)")]
+        since: Option<NaiveDate>,
+    },
+    
+    #[command(about = "Recompute the daily summaries used by long-range reports from all activities")]
+    RebuildSummaries,
+    
+    #[command(about = "Check stored activity names and notes, window titles, URLs and commands against the privacy settings")]
+    Privacy {
+        #[command(subcommand)]
+        action: PrivacyAction,
+    },
+    
+    #[command(about = "Show where tracked time went, by category, app, window title, editor project or git repository")]
+    Report {
+        #[command(subcommand)]
+        view: Option<ReportView>,
+        
+        #[arg(long, help = "First day to include (YYYY-MM-DD), defaults to today")]
+        from: Option<NaiveDate>,
+        
+        #[arg(long, help = "Last day to include (YYYY-MM-DD), defaults to the first day")]
+        to: Option<NaiveDate>,
+        
+        #[arg(short, long, value_enum, help = "What to group time by", default_value = "category")]
+        by: GroupBy,
+        
+        #[arg(short, long, help = "Only include activities in this category")]
+        category: Option<String>,
+        
+        #[arg(short = 'n', long, help = "Number of rows to show", default_value = "20")]
+        limit: usize,
+        
+        #[arg(long, value_enum, help = "Output format; html writes a self-contained page with charts", default_value = "text")]
+        format: ReportFormat,
+        
+        #[arg(short, long, help = "Write the report to this file instead of standard output")]
+        out: Option<PathBuf>,
+        
+        #[arg(long, global = true, help = "Time zone days are counted in, e.g. Europe/Berlin (default: the zone each activity was recorded in)")]
+        tz: Option<DisplayZone>,
+        
+        #[arg(long, global = true, help = "Hour (0-23) a day starts at, so late nights count towards the day before (default: $PRODUCTIVITY_TRACKER_DAY_START or 0)")]
+        day_start: Option<u32>,
+    },
+}
+
+// Routes a request through the daemon's control socket.
+// Returns None when no daemon is running so the caller can use the database directly.
+fn ask_daemon(request: Request) -> Result<Option<Response>, Box<dyn Error>> {
+    match ipc::send(&request) {
+        None => Ok(None),
+        Some(Ok(Response::Error { message })) => Err(format!("daemon: {}", message).into()),
+        Some(Ok(response)) => Ok(Some(response)),
+        Some(Err(e)) => Err(format!("could not talk to the daemon: {}", e).into()),
+    }
+}
+
+fn print_daemon_reply(response: Option<Response>) {
+    match response {
+        Some(Response::Done { message }) => println!("{}", message.green()),
+        Some(_) => println!("{}", "Unexpected reply from the daemon".yellow()),
+        None => println!("{}", "No daemon is running".yellow()),
+    }
+}
+
+// Reporting days asked for with --tz or --day-start. None means the rollup's days, which are
+// the calendar days in the zone each activity was recorded in.
+fn report_days(tz: Option<DisplayZone>, day_start: Option<u32>) -> Result<Option<DayBoundary>, Box<dyn Error>> {
+    let boundary = DayBoundary::new(tz.unwrap_or(DisplayZone::Local), day_start)?;
+    Ok((tz.is_some() || boundary.start_hour != 0).then_some(boundary))
+}
+
+// Activities overlapping the reporting days from `from` to `to`; cut them with reports::split_by_day
+fn load_days(db: &Database, boundary: &DayBoundary, from: NaiveDate, to: NaiveDate, category: Option<String>) -> Result<Vec<Activity>, Box<dyn Error>> {
+    Ok(db.get_activities(&ActivityFilter {
+        from: Some(boundary.start_of(from)),
+        to: Some(boundary.start_of(to.succ_opt().unwrap_or(to))),
+        category,
+        overlapping: true,
+        ..Default::default()
+    })?)
+}
+
+// Daily summaries from the rollup, or worked out from the activities when days are counted differently
+fn load_summaries(db: &Database, days: Option<&DayBoundary>, from: NaiveDate, to: NaiveDate, category: Option<&str>) -> Result<Vec<DailySummary>, Box<dyn Error>> {
+    match days {
+        None => Ok(db.get_daily_summaries(from, to, category)?),
+        Some(boundary) => {
+            let activities = load_days(db, boundary, from, to, category.map(str::to_string))?;
+            Ok(reports::day_summaries(&activities, boundary, from, to))
+        },
+    }
+}
+
+fn parse_tags(tags: Option<String>) -> Vec<String> {
+    tags.unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[derive(Subcommand)]
+enum ReportView {
+    #[command(about = "Estimate the tracked time behind each commit in a local git repository")]
+    Commits {
+        #[arg(long, help = "Path inside the repository", default_value = ".")]
+        repo: PathBuf,
+        
+        #[arg(long, help = "First day to include (YYYY-MM-DD), defaults to a week ago")]
+        from: Option<NaiveDate>,
+        
+        #[arg(long, help = "Last day to include (YYYY-MM-DD), defaults to today")]
+        to: Option<NaiveDate>,
+        
+        #[arg(long, help = "Longest stretch before a commit that counts towards it, e.g. 4h", default_value = "4h")]
+        window: HumanDuration,
+        
+        #[arg(long, help = "Only commits by this email (default: the repository's user.email)")]
+        author: Option<String>,
+        
+        #[arg(long, help = "Include commits by every author", conflicts_with = "author")]
+        all_authors: bool,
+        
+        #[arg(long, help = "Print the report as JSON")]
+        json: bool,
+    },
+    
+    #[command(about = "Show the daily productivity score (-100 to 100) and its trend")]
+    Score {
+        #[arg(long, help = "First day to include (YYYY-MM-DD), defaults to two weeks ago")]
+        from: Option<NaiveDate>,
+        
+        #[arg(long, help = "Last day to include (YYYY-MM-DD), defaults to today")]
+        to: Option<NaiveDate>,
+        
+        #[arg(long, help = "Print the scores as JSON")]
+        json: bool,
+    },
+    
+    #[command(about = "Show productive time by hour of day and weekday, with the best and worst hours")]
+    Heatmap {
+        #[arg(short, long, help = "Number of weeks to look back over, ending today", default_value = "8")]
+        weeks: u32,
+        
+        #[arg(long, help = "Print one row per weekday and hour as CSV", conflicts_with = "json")]
+        csv: bool,
+        
+        #[arg(long, help = "Print the heatmap as JSON")]
+        json: bool,
+    },
+    
+    #[command(about = "Compare time per category and tag between two periods, e.g. this week and last week")]
+    Compare {
+        #[arg(long = "this", help = "Period to look at: today, yesterday, week, last-week, month, last-month or YYYY-MM-DD..YYYY-MM-DD", default_value = "week")]
+        this: Period,
+        
+        #[arg(long, help = "Period to compare against, defaults to the one just before")]
+        vs: Option<Period>,
+        
+        #[arg(short = 'n', long, help = "Number of categories and tags to show", default_value = "10")]
+        limit: usize,
+        
+        #[arg(long, help = "Print the comparison as JSON")]
+        json: bool,
+    },
+    
+    #[command(about = "Show how often distraction alerts from alerts.json fired")]
+    Alerts {
+        #[arg(long, help = "First day to include (YYYY-MM-DD), defaults to a week ago")]
+        from: Option<NaiveDate>,
+        
+        #[arg(long, help = "Last day to include (YYYY-MM-DD), defaults to today")]
+        to: Option<NaiveDate>,
+        
+        #[arg(long, help = "List every alert, not just the totals")]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum FocusAction {
+    #[command(about = "Start a focus session in the foreground (Ctrl+C stops it)")]
+    Start {
+        #[arg(short, long, help = "Length of each work block, e.g. 25m", default_value = "25m")]
+        length: HumanDuration,
+        
+        #[arg(short, long = "break", help = "Length of the break after each work block", default_value = "5m")]
+        break_length: HumanDuration,
+        
+        #[arg(short = 'n', long, help = "Number of work blocks", default_value = "4")]
+        cycles: u32,
+        
+        #[arg(long, help = "Name of the tracked activity", default_value = "Focus")]
+        name: String,
+        
+        #[arg(short, long, help = "Category of the tracked activity", default_value = "Development")]
+        category: String,
+        
+        #[arg(long, help = "Command to run when no desktop notification can be shown ($PT_NOTIFY_TITLE, $PT_NOTIFY_BODY)")]
+        notify_command: Option<String>,
+    },
+    
+    #[command(about = "List recent focus sessions")]
+    History {
+        #[arg(short = 'n', long, help = "Number of sessions to show", default_value = "10")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum GoalAction {
+    #[command(about = "Add a goal, e.g. --category Development --at-least 4h --per weekday")]
+    Add {
+        #[arg(short, long, help = "Count time in this category", required_unless_present = "tag", conflicts_with = "tag")]
+        category: Option<String>,
+        
+        #[arg(short, long, help = "Count time in activities with this tag")]
+        tag: Option<String>,
+        
+        #[arg(long, help = "Time to reach, e.g. 4h", required_unless_present = "at_most", conflicts_with = "at_most")]
+        at_least: Option<HumanDuration>,
+        
+        #[arg(long, help = "Time not to go over, e.g. 45m; the daemon notifies when it is crossed")]
+        at_most: Option<HumanDuration>,
+        
+        #[arg(long, value_enum, help = "Period the time is counted over", default_value = "day")]
+        per: GoalPeriod,
+    },
+    
+    #[command(about = "List goals")]
+    List,
+    
+    #[command(about = "Remove a goal")]
+    Remove {
+        #[arg(help = "Goal id, as shown by goal list")]
+        id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum PrivacyAction {
+    #[command(about = "List stored values that look sensitive, such as emails, tokens or search terms")]
+    Audit {
+        #[arg(short = 'n', long, help = "Number of examples to show per kind", default_value = "5")]
+        limit: usize,
+        
+        #[arg(long, help = "Print every finding as JSON")]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServiceAction {
+    #[command(about = "Register the daemon to start now and at every login")]
+    Install {
+        #[arg(short, long, help = "Sampling interval in seconds", default_value = "5")]
+        interval: u64,
+        
+        #[arg(long, help = "Print the unit file instead of installing it")]
+        print: bool,
+    },
+    
+    #[command(about = "Stop the service and remove it")]
+    Uninstall,
+    
+    #[command(about = "Show whether the service is installed and running")]
+    Status,
 }
 
-pub async fn run() -> Result<()> {
+pub async fn run() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
     
     match cli.command {
-        Commands::Start { name, category, tags, productive } => {
-            let mut tracker = Tracker::new()?;
-            let tags_vec = tags
-                .unwrap_or_default()
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .collect();
-            
-            tracker.start_activity(name.clone(), category.clone(), tags_vec, productive)?;
+        Commands::Start { name, category, tags, productive, weight } => {
+            let weight = weight.unwrap_or_else(|| weight_from_flag(productive));
+            ipc::start_activity(name.clone(), category.clone(), parse_tags(tags), weight)?;
             println!("{} {} in category {}", "Started".green(), name, category);
         },
         Commands::Stop => {
-            let mut tracker = Tracker::new()?;
-            tracker.stop_activity()?;
+            ipc::stop_activity()?;
             println!("{}", "Activity stopped".green());
         },
         Commands::Current => {
-            let tracker = Tracker::new()?;
-            if let Some(activity) = tracker.get_current_activity() {
+            let current = match ask_daemon(Request::Current)? {
+                Some(Response::Activity { activity }) => activity,
+                _ => Tracker::new()?.get_current_activity().cloned(),
+            };
+            if let Some(activity) = current {
                 println!("{}: {} (Category: {})", "Current activity".green(), activity.name, activity.category);
             } else {
                 println!("{}", "No activity is currently being tracked".yellow());
             }
         },
+        Commands::Pause { duration } => {
+            if duration.is_some_and(|d| d.0.as_secs() == 0) {
+                return Err("--for needs a length of at least a second; leave it out to pause until resumed".into());
+            }
+            let request = Request::Pause { duration_secs: duration.map(|d| d.0.as_secs()) };
+            print_daemon_reply(ask_daemon(request)?);
+        },
+        Commands::Resume => print_daemon_reply(ask_daemon(Request::Resume)?),
+        Commands::Status => {
+            match ask_daemon(Request::Status)? {
+                Some(Response::Status { status }) => {
+                    println!("{} (pid {}, running since {})", "Daemon is running".green(), status.pid, status.running_since.format("%Y-%m-%d %H:%M"));
+                    match (status.paused, status.paused_until) {
+                        (true, Some(until)) => println!("Automatic tracking: {} until {}", "snoozed".yellow(), until.format("%H:%M")),
+                        (true, None) => println!("Automatic tracking: {}", "paused".yellow()),
+                        (false, _) => println!("Automatic tracking: {}", "active".green()),
+                    }
+                    println!("Sampling every {} seconds", status.sampling_interval_secs);
+                    if !status.foreground_app.is_empty() {
+                        println!("Foreground app: {}", status.foreground_app);
+                    }
+                    if let Some(activity) = status.manual_activity {
+                        println!("Manual activity: {} (Category: {})", activity.name, activity.category);
+                    }
+                },
+                Some(_) => println!("{}", "Unexpected reply from the daemon".yellow()),
+                None => println!("{}", "No daemon is running".yellow()),
+            }
+        },
         Commands::Daemon { interval } => {
             println!("{}", "Starting background tracking daemon...".green());
             println!("Press Ctrl+C to stop");
             
+            // Two daemons would record every sample twice
+            let _instance = InstanceLock::acquire()?;
+            
             // Initialize database
             let db = Database::new()?;
             
@@ -87,17 +564,40 @@ pub async fn run() -> Result<()> {
                 println!("Sampling interval set to {} seconds", interval);
             }
             
-            // Set up Ctrl+C handler
-            let ctrl_c = tokio::signal::ctrl_c();
+            // Save whatever a crashed daemon was sampling when it went down
+            if let Some(recovered) = monitor.recover_interrupted_span()? {
+                println!("Recovered {} from {} to {} left open by the previous run",
+                    recovered.name,
+                    recovered.start_time.format("%H:%M:%S"),
+                    recovered.end_time.map(|t| t.format("%H:%M:%S").to_string()).unwrap_or_default());
+            }
+            
+            // Listen for start/stop/pause requests from the CLI
+            let control = ControlServer::new(monitor.handle(), monitor.sampling_interval)?;
+            let control_socket = tokio::spawn(async move {
+                if let Err(e) = control.listen().await {
+                    eprintln!("Control socket stopped: {}", e);
+                }
+            });
+            
+            let pause_handle = monitor.handle();
+            tokio::spawn(async move {
+                if let Err(e) = ipc::handle_pause_signals(pause_handle).await {
+                    eprintln!("Pause signals unavailable: {}", e);
+                }
+            });
+            
+            // Set up SIGINT, SIGTERM and SIGHUP handlers
+            let shutdown = ipc::shutdown_signal()?;
             
             // Start monitoring task
+            service::notify::ready();
             let monitoring = monitor.start_monitoring();
             
-            // Wait for either Ctrl+C or monitoring to complete
+            // Wait for either a termination signal or monitoring to complete
             tokio::select! {
-                _ = ctrl_c => {
-                    println!("Received termination signal");
-                    monitor.stop_monitoring();
+                signal = shutdown => {
+                    println!("Received {}, shutting down", signal);
                 },
                 result = monitoring => {
                     if let Err(e) = result {
@@ -106,7 +606,780 @@ pub async fn run() -> Result<()> {
                 }
             }
             
+            service::notify::stopping();
+            control_socket.abort();
+            ipc::remove_socket();
+            monitor.shutdown()?;
             println!("Background tracking stopped");
+        },
+        Commands::Focus { action: FocusAction::Start { length, break_length, cycles, name, category, notify_command } } => {
+            if cycles == 0 || length.0.is_zero() {
+                return Err("a focus session needs at least one work block of non-zero length".into());
+            }
+            let session = focus::run(FocusOptions {
+                name,
+                category,
+                work_length: length.0,
+                break_length: break_length.0,
+                cycles,
+                notify_command,
+            }).await?;
+            
+            println!("{} {} of {} blocks, {} interruptions", "Session saved:".green(), session.completed_cycles, session.planned_cycles, session.interruptions);
+            if !session.interrupted_by.is_empty() {
+                println!("Interrupted by: {}", session.interrupted_by.join(", "));
+            }
+        },
+        Commands::Focus { action: FocusAction::History { limit } } => {
+            let db = Database::new()?;
+            let sessions = db.get_focus_sessions(limit)?;
+            if sessions.is_empty() {
+                println!("{}", "No focus sessions recorded yet".yellow());
+            }
+            for session in sessions {
+                let outcome = if session.completed { "completed".green() } else { "stopped".yellow() };
+                println!("{}  {:<20} {}/{} x {}  {:<9}  {} interruptions{}",
+                    session.start_time.format("%Y-%m-%d %H:%M"),
+                    session.name,
+                    session.completed_cycles,
+                    session.planned_cycles,
+                    format_short(session.work_length),
+                    outcome,
+                    session.interruptions,
+                    if session.interrupted_by.is_empty() { String::new() } else { format!(" ({})", session.interrupted_by.join(", ")) });
+            }
+        },
+        Commands::Goal { action: GoalAction::Add { category, tag, at_least, at_most, per } } => {
+            let target = match (category, tag) {
+                (Some(category), _) => GoalTarget::Category(category),
+                (None, Some(tag)) => GoalTarget::Tag(tag),
+                (None, None) => return Err("a goal needs a --category or a --tag".into()),
+            };
+            let (direction, amount) = match (at_least, at_most) {
+                (Some(amount), _) => (GoalDirection::AtLeast, amount),
+                (None, Some(amount)) => (GoalDirection::AtMost, amount),
+                (None, None) => return Err("a goal needs --at-least or --at-most".into()),
+            };
+            let mut goal = Goal {
+                id: None,
+                target,
+                direction,
+                amount: amount.0,
+                period: per,
+                created_at: chrono::Local::now(),
+            };
+            let db = Database::new()?;
+            goal.id = Some(db.save_goal(&goal)?);
+            println!("{} goal {}: {}", "Added".green(), goal.id.unwrap_or_default(), goals::describe(&goal));
+        },
+        Commands::Goal { action: GoalAction::List } => {
+            let db = Database::new()?;
+            let all_goals = db.get_goals()?;
+            if all_goals.is_empty() {
+                println!("{}", "No goals set".yellow());
+            }
+            for goal in all_goals {
+                println!("{:>3}  {}  (since {})", goal.id.unwrap_or_default(), goals::describe(&goal), goal.created_at.format("%Y-%m-%d"));
+            }
+        },
+        Commands::Goal { action: GoalAction::Remove { id } } => {
+            let db = Database::new()?;
+            if db.delete_goal(id)? {
+                println!("{} goal {}", "Removed".green(), id);
+            } else {
+                return Err(format!("no goal with id {}", id).into());
+            }
+        },
+        Commands::Goals { date } => {
+            let today = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let db = Database::new()?;
+            let all_goals = db.get_goals()?;
+            if all_goals.is_empty() {
+                println!("{}", "No goals set; add one with goal add".yellow());
+                return Ok(());
+            }
+            
+            println!("{}", format!("Goals on {}", today.format("%a %Y-%m-%d")).bold());
+            for progress in goals::load_progress(&db, &all_goals, today)? {
+                let goal = &progress.goal;
+                let share = progress.spent.as_secs_f64() / goal.amount.as_secs_f64().max(1.0);
+                let filled = ((share.min(1.0)) * 20.0).round() as usize;
+                let bar = format!("{}{}", "█".repeat(filled), "░".repeat(20 - filled));
+                let bar = match (goal.direction, progress.met) {
+                    (_, true) => bar.green(),
+                    (GoalDirection::AtLeast, false) => bar.yellow(),
+                    (GoalDirection::AtMost, false) => bar.red(),
+                };
+                let period = match goal.period {
+                    GoalPeriod::Week => "this week",
+                    GoalPeriod::Weekday if today.weekday().num_days_from_monday() >= 5 => "today (not a weekday)",
+                    _ => "today",
+                };
+                println!();
+                println!("{}", progress.description.bold());
+                println!("  {} {:>8} of {} {} ({:.0}%)", bar, format_short(progress.spent), format_short(goal.amount), period, share * 100.0);
+                
+                if !progress.week.is_empty() {
+                    let days: Vec<String> = progress.week.iter()
+                        .map(|(day, mark)| {
+                            let label = day.format("%a").to_string();
+                            match mark {
+                                DayMark::Met => format!("{} ✓", label).green().to_string(),
+                                DayMark::Missed => format!("{} ✗", label).red().to_string(),
+                                DayMark::InProgress => format!("{} …", label).yellow().to_string(),
+                                DayMark::Skipped => format!("{} ·", label).dimmed().to_string(),
+                            }
+                        })
+                        .collect();
+                    println!("  This week: {}", days.join("  "));
+                }
+                let unit = match (goal.period, progress.streak) {
+                    (GoalPeriod::Week, 1) => "week",
+                    (GoalPeriod::Week, _) => "weeks",
+                    (_, 1) => "day",
+                    (_, _) => "days",
+                };
+                println!("  Streak: {} {}", progress.streak, unit);
+            }
+        },
+        Commands::Timeline { date, blocks } => {
+            let day = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let from = DisplayZone::Local.start_of_day(day);
+            let to = DisplayZone::Local.start_of_day(day + chrono::Duration::days(1));
+            let db = Database::new()?;
+            // Starting a day early picks up activities that ran past midnight
+            let activities = db.get_activities(&ActivityFilter {
+                from: Some(from - chrono::Duration::days(1)),
+                to: Some(to),
+                ..Default::default()
+            })?;
+            let gaps = db.get_gaps(&from, &to)?;
+            
+            let day_blocks = timeline::build(&activities, &gaps, from, to);
+            println!("{}", format!("Timeline for {}", day.format("%a %Y-%m-%d")).bold());
+            if day_blocks.is_empty() {
+                println!("{}", "Nothing tracked on this day".yellow());
+                return Ok(());
+            }
+            if blocks {
+                timeline::print_blocks(&day_blocks);
+            } else {
+                timeline::print_hours(&day_blocks);
+            }
+            println!();
+            timeline::print_totals(&day_blocks);
+        },
+        Commands::Dashboard { rows, week } => {
+            let view = if week { dashboard::View::Week } else { dashboard::View::Day };
+            dashboard::run(view, rows)?;
+        },
+        Commands::ShellHook { shell } => {
+            print!("{}", shell::hook_script(shell)?);
+        },
+        Commands::ShellEvent { phase, pid, started, status, cwd, stdin, command } => {
+            let command = if stdin {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                command.join(" ")
+            };
+            if command.trim().is_empty() {
+                return Ok(());
+            }
+            let start_time = chrono::DateTime::from_timestamp(started, 0)
+                .map(|t| t.with_timezone(&chrono::Local))
+                .unwrap_or_else(chrono::Local::now);
+            let event = ShellEvent {
+                phase,
+                shell_pid: pid,
+                command: shell::mask_secrets(command.trim()),
+                cwd,
+                start_time,
+                end_time: Some(chrono::Local::now()).filter(|_| phase == ShellPhase::End),
+                exit_status: status.filter(|_| phase == ShellPhase::End),
+            };
+            
+            // Without a daemon only finished commands are kept; a start has nobody to tell
+            match ipc::send(&Request::ShellEvent { event: event.clone() }) {
+                Some(Ok(_)) => {},
+                Some(Err(_)) | None if phase == ShellPhase::End => shell::append_to_spool(&event)?,
+                Some(Err(_)) | None => {},
+            }
+        },
+        Commands::Service { action } => match action {
+            ServiceAction::Install { interval, print: true } => {
+                print!("{}", service::Manager::current().render(interval)?);
+            },
+            ServiceAction::Install { interval, print: false } => {
+                let path = service::install(interval)?;
+                println!("{} {}", "Installed and started".green(), path.display());
+            },
+            ServiceAction::Uninstall => {
+                if service::uninstall()? {
+                    println!("{}", "Service stopped and removed".green());
+                } else {
+                    println!("{}", "The service is not installed".yellow());
+                }
+            },
+            ServiceAction::Status => {
+                let status = service::status();
+                if status.installed {
+                    println!("{} service: {}", status.manager.name(), status.unit_path.display());
+                } else {
+                    println!("{} service: {}", status.manager.name(), "not installed".yellow());
+                }
+                if let Some(state) = status.state {
+                    println!("State: {}", state);
+                }
+                match status.daemon_pid {
+                    Some(pid) => println!("{} (pid {})", "Daemon is running".green(), pid),
+                    None => println!("{}", "No daemon is running".yellow()),
+                }
+            },
+        },
+        Commands::Export { format, from, to, category, tags, columns, tz, durations, out } => {
+            let db = Database::new()?;
+            let filter = ActivityFilter {
+                from: from.map(|d| tz.start_of_day(d)),
+                to: to.and_then(|d| d.succ_opt()).map(|d| tz.start_of_day(d)),
+                category,
+                tags: parse_tags(tags),
+                ..Default::default()
+            };
+            let activities = db.get_activities(&filter)?;
+            
+            let options = ExportOptions { format, columns, zone: tz, durations };
+            match out {
+                Some(path) => {
+                    let file = std::fs::File::create(&path)?;
+                    export::export_activities(&activities, &options, std::io::BufWriter::new(file))?;
+                    eprintln!("{} {} activities to {}", "Exported".green(), activities.len(), path.display());
+                },
+                None => export::export_activities(&activities, &options, std::io::stdout().lock())?,
+            }
+        },
+        Commands::Import { format, file, map, date_format, tz, dry_run } => {
+            let db = Database::new()?;
+            let options = ImportOptions { format, mapping: map, date_format, zone: tz, dry_run };
+            let summary = import::import_file(&db, &file, &options)?;
+            
+            if dry_run {
+                println!("{}", "Dry run, nothing was written".yellow());
+            }
+            summary.print(dry_run);
+        },
+        Commands::Serve { addr, socket, token } => {
+            let token = match token {
+                Some(token) => token,
+                None => server::load_or_create_token()?,
+            };
+            let bind = match socket {
+                Some(path) => Bind::Unix(path),
+                None => Bind::Tcp(addr),
+            };
+            
+            println!("{}", "Starting API server...".green());
+            println!("Send the token as 'Authorization: Bearer <token>' (token file: ~/.productivity_tracker/api_token)");
+            server::serve(bind, token).await?;
+        },
+        Commands::Report { view: Some(ReportView::Commits { repo, from, to, window, author, all_authors, json }), .. } => {
+            let to = to.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let from = from.unwrap_or(to - chrono::Duration::days(6));
+            let options = CommitOptions {
+                from: DisplayZone::Local.start_of_day(from),
+                to: DisplayZone::Local.start_of_day(to.succ_opt().unwrap_or(to)),
+                max_window: window.0,
+                author: if all_authors { None } else { author.or_else(|| commits::local_author(&repo)) },
+            };
+            
+            // Work before the first commit in range can still count towards it, and work after
+            // the last one shows it was not left untracked
+            let db = Database::new()?;
+            let window = chrono::Duration::from_std(window.0).unwrap_or_default();
+            let activities = db.get_activities(&ActivityFilter {
+                from: Some(options.from - window - chrono::Duration::days(1)),
+                to: Some(options.to + window),
+                ..Default::default()
+            })?;
+            let report = commits::commit_report(&repo, &activities, &options)?;
+            
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            
+            println!("{}", format!("Commits in {} from {} to {}", git::display_repo(&report.repo), from, to).bold());
+            if report.commits.is_empty() {
+                println!("{}", "No commits in range".yellow());
+            }
+            for commit in &report.commits {
+                let tracked = if commit.tracked_seconds == 0 {
+                    format!("{:>8}", "-").red()
+                } else {
+                    format!("{:>8}", format_short(std::time::Duration::from_secs(commit.tracked_seconds))).normal()
+                };
+                println!("{}  {}  {:<16} {}  {}",
+                    commit.time.format("%Y-%m-%d %H:%M"),
+                    commit.hash.chars().take(7).collect::<String>().yellow(),
+                    commit.branch,
+                    tracked,
+                    commit.subject);
+            }
+            
+            if !report.branches.is_empty() {
+                println!();
+                println!("{}", "By branch".bold());
+                for branch in &report.branches {
+                    println!("{:<24} {:>3} commits {:>8} estimated {:>8} tracked on branch",
+                        branch.branch,
+                        branch.commits,
+                        format_short(std::time::Duration::from_secs(branch.commit_seconds)),
+                        format_short(std::time::Duration::from_secs(branch.tracked_seconds)));
+                }
+            }
+            
+            println!();
+            println!("Development time in range: {}, of which {} led up to a commit",
+                format_short(std::time::Duration::from_secs(report.development_seconds)),
+                format_short(std::time::Duration::from_secs(report.attributed_seconds)));
+            let untracked = report.untracked().count();
+            if untracked > 0 {
+                println!("{} {} commits have no tracked time around them", "Note:".yellow(), untracked);
+            }
+        },
+        Commands::Report { view: Some(ReportView::Heatmap { weeks, csv, json }), tz, day_start, .. } => {
+            if weeks == 0 {
+                return Err("--weeks must be at least 1".into());
+            }
+            let boundary = report_days(tz, day_start)?.unwrap_or_default();
+            let to = boundary.date_of(&chrono::Local::now());
+            let from = to.checked_sub_days(chrono::Days::new(weeks as u64 * 7 - 1))
+                .ok_or_else(|| format!("--weeks {} reaches back before the earliest supported date", weeks))?;
+            let db = Database::new()?;
+            let activities = reports::split_by_day(&load_days(&db, &boundary, from, to, None)?, &boundary, from, to);
+            let cells = reports::weekday_hours(&activities, &boundary);
+            let ranking = reports::rank_hours(&cells, 3);
+            const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+            // Every weekday occurs `weeks` times in the window, so this is the average for one of them
+            let minutes = |seconds: u64| seconds as f64 / 60.0 / weeks as f64;
+            
+            if csv {
+                let mut writer = csv::Writer::from_writer(std::io::stdout());
+                writer.write_record(["weekday", "hour", "tracked_minutes", "productive_minutes"])?;
+                for (day, hours) in cells.iter().enumerate() {
+                    for (hour, cell) in hours.iter().enumerate() {
+                        writer.write_record([
+                            WEEKDAYS[day].to_string(),
+                            hour.to_string(),
+                            format!("{:.1}", minutes(cell.tracked_seconds)),
+                            format!("{:.1}", minutes(cell.productive_seconds)),
+                        ])?;
+                    }
+                }
+                writer.flush()?;
+                return Ok(());
+            }
+            if json {
+                let rows: Vec<serde_json::Value> = cells.iter().enumerate()
+                    .flat_map(|(day, hours)| hours.iter().enumerate().map(move |(hour, cell)| (day, hour, *cell)))
+                    .map(|(day, hour, cell)| serde_json::json!({
+                        "weekday": WEEKDAYS[day],
+                        "hour": hour,
+                        "tracked_minutes": (minutes(cell.tracked_seconds) * 10.0).round() / 10.0,
+                        "productive_minutes": (minutes(cell.productive_seconds) * 10.0).round() / 10.0,
+                    }))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+                    "from": from,
+                    "to": to,
+                    "weeks": weeks,
+                    "cells": rows,
+                    "most_productive_hours": ranking.most_productive,
+                    "least_productive_hours": ranking.least_productive,
+                }))?);
+                return Ok(());
+            }
+            
+            println!("{}", format!("Productive minutes per hour, averaged over {} weeks ({} to {})", weeks, from, to).bold());
+            let busiest = cells.iter().flatten().map(|c| c.productive_seconds).max().unwrap_or_default();
+            if busiest == 0 {
+                println!("{}", "No productive time tracked".yellow());
+                return Ok(());
+            }
+            let header: String = (0..24).map(|h| if h % 3 == 0 { format!("{:<3}", h) } else { "   ".to_string() }).collect();
+            println!("     {}", header.dimmed());
+            for (day, hours) in cells.iter().enumerate() {
+                let row: String = hours.iter().map(|cell| {
+                    let level = (cell.productive_seconds * 4).div_ceil(busiest) as usize;
+                    let shade = [" ·", "░░", "▒▒", "▓▓", "██"][level.min(4)];
+                    format!("{} ", if level == 0 { shade.dimmed() } else { shade.green() })
+                }).collect();
+                println!("{}  {}", WEEKDAYS[day], row);
+            }
+            let markers: String = (0..24u32).map(|h| {
+                if ranking.most_productive.contains(&h) {
+                    format!("{} ", "▲▲".green())
+                } else if ranking.least_productive.contains(&h) {
+                    format!("{} ", "▼▼".red())
+                } else {
+                    "   ".to_string()
+                }
+            }).collect();
+            println!("     {}", markers);
+            
+            let describe = |hours: &[u32]| -> String {
+                hours.iter().map(|h| {
+                    let productive: u64 = cells.iter().map(|day| day[*h as usize].productive_seconds).sum();
+                    let tracked: u64 = cells.iter().map(|day| day[*h as usize].tracked_seconds).sum();
+                    format!("{:02}:00 ({:.0}% productive)", h, productive as f64 / tracked.max(1) as f64 * 100.0)
+                }).collect::<Vec<_>>().join(", ")
+            };
+            println!();
+            println!("{} {}", "Most productive hours:".green(), describe(&ranking.most_productive));
+            if !ranking.least_productive.is_empty() {
+                println!("{} {}", "Least productive hours:".red(), describe(&ranking.least_productive));
+            }
+            let best_day = cells.iter().enumerate()
+                .max_by_key(|(_, hours)| hours.iter().map(|c| c.productive_seconds).sum::<u64>())
+                .map(|(day, _)| WEEKDAYS[day])
+                .unwrap_or_default();
+            println!("Most productive weekday: {}", best_day);
+        },
+        Commands::Report { view: Some(ReportView::Compare { this, vs, limit, json }), tz, day_start, .. } => {
+            let days = report_days(tz, day_start)?;
+            let today = days.unwrap_or_default().date_of(&chrono::Local::now());
+            let vs = vs.unwrap_or_else(|| this.previous());
+            let (this_range, vs_range) = compare::resolve_pair(this, vs, today);
+            let db = Database::new()?;
+            let load = |(from, to): (NaiveDate, NaiveDate)| load_summaries(&db, days.as_ref(), from, to, None);
+            let comparison = compare::compare(today, this.to_string(), this_range, &load(this_range)?, vs.to_string(), vs_range, &load(vs_range)?);
+            
+            if json {
+                println!("{}", serde_json::to_string_pretty(&comparison)?);
+                return Ok(());
+            }
+            
+            let range = |span: &compare::Span| {
+                let so_far = if span.partial { ", so far" } else { "" };
+                if span.from == span.to {
+                    format!("{} ({}{})", span.label, span.from, so_far)
+                } else {
+                    format!("{} ({} to {}{})", span.label, span.from, span.to, so_far)
+                }
+            };
+            println!("{}", format!("{} vs {}", range(&comparison.this), range(&comparison.vs)).bold());
+            if comparison.this.tracked_seconds == 0 && comparison.vs.tracked_seconds == 0 {
+                println!("{}", "Nothing tracked in either period".yellow());
+                return Ok(());
+            }
+            
+            let seconds = |s: u64| format_short(std::time::Duration::from_secs(s));
+            // More time reads as good news unless the time is mostly weighted as a distraction
+            let change = |change: i64, percent: Option<f64>, weight: f64| -> String {
+                let amount = format!("{}{}", if change < 0 { "-" } else { "+" }, seconds(change.unsigned_abs()));
+                let text = match percent {
+                    Some(percent) => format!("{} ({:+.0}%)", amount, percent),
+                    None => format!("{} (new)", amount),
+                };
+                let arrow = match change {
+                    c if c > 0 => "▲",
+                    c if c < 0 => "▼",
+                    _ => "=",
+                };
+                let text = format!("{} {}", arrow, text);
+                let good = (change as f64) * if weight < 0.0 { -1.0 } else { 1.0 };
+                if change == 0 || weight == 0.0 {
+                    text.normal().to_string()
+                } else if good > 0.0 {
+                    text.green().to_string()
+                } else {
+                    text.red().to_string()
+                }
+            };
+            let ratio_change = comparison.productive_ratio_change;
+            let ratio_text = format!("{} {:+.0} pts", if ratio_change >= 0.0 { "▲" } else { "▼" }, ratio_change);
+            println!("{:<24} {:>8} → {:<8} {}", "Tracked",
+                seconds(comparison.vs.tracked_seconds), seconds(comparison.this.tracked_seconds),
+                change(comparison.this.tracked_seconds as i64 - comparison.vs.tracked_seconds as i64,
+                    (comparison.vs.tracked_seconds > 0).then(|| (comparison.this.tracked_seconds as f64 / comparison.vs.tracked_seconds as f64 - 1.0) * 100.0), 0.0));
+            println!("{:<24} {:>8} → {:<8} {}", "Productive share",
+                format!("{:.0}%", comparison.vs.productive_ratio * 100.0), format!("{:.0}%", comparison.this.productive_ratio * 100.0),
+                if ratio_change.abs() < 0.5 { ratio_text.normal() } else if ratio_change > 0.0 { ratio_text.green() } else { ratio_text.red() });
+            
+            for (title, deltas) in [("Categories", &comparison.categories), ("Tags", &comparison.tags)] {
+                if deltas.is_empty() {
+                    continue;
+                }
+                println!();
+                println!("{}", title.bold());
+                for delta in deltas.iter().take(limit) {
+                    let key: String = if delta.key.chars().count() > 24 {
+                        delta.key.chars().take(23).chain(std::iter::once('…')).collect()
+                    } else {
+                        delta.key.clone()
+                    };
+                    println!("{:<24} {:>8} → {:<8} {}", key, seconds(delta.vs_seconds), seconds(delta.this_seconds),
+                        change(delta.change_seconds, delta.change_percent, delta.mean_weight));
+                }
+                if deltas.len() > limit {
+                    println!("… and {} more", deltas.len() - limit);
+                }
+            }
+            
+            for (title, activities) in [("New activities", &comparison.new_activities), ("Disappeared activities", &comparison.disappeared_activities)] {
+                if activities.is_empty() {
+                    continue;
+                }
+                let names: Vec<String> = activities.iter()
+                    .take(limit)
+                    .map(|a| format!("{} ({})", a.key, seconds(a.seconds)))
+                    .collect();
+                let more = if activities.len() > limit { format!(", … and {} more", activities.len() - limit) } else { String::new() };
+                println!();
+                println!("{}: {}{}", title.bold(), names.join(", "), more);
+            }
+        },
+        Commands::Report { view: Some(ReportView::Score { from, to, json }), tz, day_start, .. } => {
+            let days = report_days(tz, day_start)?;
+            let to = to.unwrap_or_else(|| days.unwrap_or_default().date_of(&chrono::Local::now()));
+            let from = from.unwrap_or(to - chrono::Duration::days(13));
+            let db = Database::new()?;
+            let rows = load_summaries(&db, days.as_ref(), from, to, None)?;
+            let scores = reports::summary_scores(&rows, from, to);
+            let trend = reports::score_trend(&scores);
+            let overall = {
+                let totals = rows.iter().filter(|r| r.tag.is_none());
+                let (weighted, seconds) = totals.fold((0, 0), |(w, s), r| (w + r.weighted_seconds, s + r.seconds));
+                reports::weighted_score(weighted, seconds)
+            };
+            
+            if json {
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+                    "days": scores,
+                    "overall": overall,
+                    "trend_per_day": trend,
+                }))?);
+                return Ok(());
+            }
+            
+            println!("{}", format!("Productivity score from {} to {}", from, to).bold());
+            for day in &scores {
+                if day.tracked_seconds == 0 {
+                    println!("{}  {:>5}", day.date.format("%a %Y-%m-%d"), "-".dimmed());
+                    continue;
+                }
+                // Bars grow left of the axis for negative scores and right of it for positive ones
+                let width = (day.score.abs() / 100.0 * 20.0).round() as usize;
+                let (left, right) = if day.score < 0.0 {
+                    (format!("{:>20}", "█".repeat(width)).red(), "".normal())
+                } else {
+                    (" ".repeat(20).normal(), "█".repeat(width).green())
+                };
+                println!("{}  {:>+5.0} {}|{} {}",
+                    day.date.format("%a %Y-%m-%d"),
+                    day.score,
+                    left,
+                    right,
+                    format_short(std::time::Duration::from_secs(day.tracked_seconds)).dimmed());
+            }
+            
+            println!();
+            println!("Overall: {:+.0}", overall);
+            match trend {
+                Some(slope) if slope > 0.5 => println!("Trend: {} ({:+.1} points per day)", "improving".green(), slope),
+                Some(slope) if slope < -0.5 => println!("Trend: {} ({:+.1} points per day)", "declining".red(), slope),
+                Some(slope) => println!("Trend: steady ({:+.1} points per day)", slope),
+                None => println!("Trend: not enough days with tracked time"),
+            }
+        },
+        Commands::Report { view: Some(ReportView::Alerts { from, to, all }), .. } => {
+            let to = to.unwrap_or_else(|| chrono::Local::now().date_naive());
+            let from = from.unwrap_or(to - chrono::Duration::days(6));
+            let db = Database::new()?;
+            let alerts_in_range = db.get_alerts(
+                &DisplayZone::Local.start_of_day(from),
+                &DisplayZone::Local.start_of_day(to.succ_opt().unwrap_or(to)),
+            )?;
+            
+            println!("{}", format!("Distraction alerts from {} to {}", from, to).bold());
+            if alerts_in_range.is_empty() {
+                println!("{}", "No alerts".green());
+                if !alerts::alerts_path().exists() {
+                    println!("No alert rules are set up; add them to {}", alerts::alerts_path().display());
+                }
+                return Ok(());
+            }
+            
+            for count in reports::alert_counts(&alerts_in_range) {
+                println!("{:<30} {:>4} alerts   {}", count.target, count.count, count.apps.join(", "));
+            }
+            
+            println!();
+            println!("{}", "By day".bold());
+            let mut day = from;
+            while day <= to {
+                let count = alerts_in_range.iter().filter(|a| a.triggered_at.date_naive() == day).count();
+                println!("{} {:>4} {}", day.format("%a %Y-%m-%d"), count, "█".repeat(count).red());
+                day = match day.succ_opt() {
+                    Some(next) => next,
+                    None => break,
+                };
+            }
+            
+            if all {
+                println!();
+                for alert in &alerts_in_range {
+                    println!("{}  {:<24} {:<10} {} over a {} budget, in {}",
+                        alert.triggered_at.format("%Y-%m-%d %H:%M"),
+                        alert.target,
+                        alert.kind.as_str(),
+                        format_short(alert.spent),
+                        format_short(alert.budget),
+                        alert.app);
+                }
+            }
+        },
+        Commands::Report { view: None, from, to, by, category, limit, format, out, tz, day_start } => {
+            let days = report_days(tz, day_start)?;
+            let boundary = days.unwrap_or_default();
+            let from = from.unwrap_or_else(|| boundary.date_of(&chrono::Local::now()));
+            let to = to.unwrap_or(from);
+            if to < from {
+                return Err("--to must not be before --from".into());
+            }
+            let db = Database::new()?;
+            // Time crossing into or out of the range only counts for the part inside it
+            let load_activities = |category: Option<String>| -> Result<Vec<Activity>, Box<dyn Error>> {
+                Ok(reports::split_by_day(&load_days(&db, &boundary, from, to, category)?, &boundary, from, to))
+            };
+            
+            if format == ReportFormat::Html {
+                let page = html::render(&load_activities(category)?, &boundary, from, to);
+                match out {
+                    Some(path) => {
+                        std::fs::write(&path, page)?;
+                        println!("{} {}", "Wrote report to".green(), path.display());
+                    },
+                    None => print!("{}", page),
+                }
+                return Ok(());
+            }
+            if out.is_some() {
+                return Err("--out is only supported with --format html".into());
+            }
+            
+            // Totals by category or name come from the rollup; finer groupings need every activity,
+            // cut on the days the rollup counts them on unless --tz or --day-start ask for others
+            let rows = match by {
+                GroupBy::Category | GroupBy::Name => Some(load_summaries(&db, days.as_ref(), from, to, category.as_deref())?),
+                _ => None,
+            };
+            let totals = match rows.as_deref().and_then(|rows| reports::summary_totals(rows, by)) {
+                Some(totals) => totals,
+                None if days.is_some() => reports::group_totals(&load_activities(category)?, by),
+                None => {
+                    let around = load_days(
+                        &db,
+                        &boundary,
+                        from.checked_sub_days(chrono::Days::new(2)).unwrap_or(from),
+                        to.checked_add_days(chrono::Days::new(2)).unwrap_or(to),
+                        category,
+                    )?;
+                    reports::group_totals(&reports::split_by_recorded_day(&around, from, to), by)
+                },
+            };
+            let total: u64 = totals.iter().map(|t| t.seconds).sum();
+            if from == to {
+                println!("{}", format!("Tracked time on {}", from).bold());
+            } else {
+                println!("{}", format!("Tracked time from {} to {}", from, to).bold());
+            }
+            if total == 0 {
+                println!("{}", "Nothing tracked".yellow());
+                return Ok(());
+            }
+            
+            for group in totals.iter().take(limit) {
+                let share = group.seconds as f64 / total as f64;
+                let key: String = if group.key.chars().count() > 40 {
+                    group.key.chars().take(39).chain(std::iter::once('…')).collect()
+                } else {
+                    group.key.clone()
+                };
+                println!("{:<40} {:>8} {:>4.0}% {}",
+                    key,
+                    format_short(std::time::Duration::from_secs(group.seconds)),
+                    share * 100.0,
+                    "█".repeat((share * 30.0).round() as usize).cyan());
+            }
+            if totals.len() > limit {
+                println!("… and {} more", totals.len() - limit);
+            }
+            println!("{:<40} {:>8}", "Total".bold(), format_short(std::time::Duration::from_secs(total)));
+        },
+        Commands::RebuildSummaries => {
+            let rows = Database::new()?.rebuild_daily_summaries()?;
+            println!("{} daily summaries with {} rows", "Rebuilt".green(), rows);
+        },
+        Commands::Privacy { action: PrivacyAction::Audit { limit, json } } => {
+            let db = Database::new()?;
+            let findings = privacy::audit(&db.get_stored_texts()?, &Redactor::load()?);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&findings)?);
+                return Ok(());
+            }
+            
+            if findings.is_empty() {
+                println!("{}", "Nothing sensitive-looking found in stored names, notes, titles, URLs or commands".green());
+                return Ok(());
+            }
+            // Grouped by where and what, most findings first
+            let mut groups: Vec<(&str, &str, Vec<&privacy::Finding>)> = Vec::new();
+            for finding in &findings {
+                match groups.iter_mut().find(|(location, kind, _)| *location == finding.location && *kind == finding.kind) {
+                    Some((_, _, group)) => group.push(finding),
+                    None => groups.push((&finding.location, finding.kind, vec![finding])),
+                }
+            }
+            groups.sort_by(|a, b| b.2.len().cmp(&a.2.len()).then(a.0.cmp(b.0)).then(a.1.cmp(b.1)));
+            for (location, kind, group) in &groups {
+                println!("{} {} in {}", format!("{:>5}", group.len()).yellow(), kind.bold(), location);
+                for finding in group.iter().take(limit) {
+                    println!("      {:>6}  {}", format!("#{}", finding.id).dimmed(), finding.excerpt);
+                }
+                if group.len() > limit {
+                    println!("      … and {} more", group.len() - limit);
+                }
+            }
+            println!();
+            let path = privacy::privacy_path();
+            if path.exists() {
+                println!("{} findings. New samples are redacted according to {}", findings.len(), path.display());
+            } else {
+                println!("{} findings. New URLs lose their query string; add {} to mask titles or keep only domains", findings.len(), path.display());
+            }
+        },
+        Commands::ImportCalendar { file, since } => {
+            let db = Database::new()?;
+            let summary = calendar::import_calendar(&db, &file, since.map(|d| DisplayZone::Local.start_of_day(d)))?;
+            
+            println!("{} {} calendar events", "Stored".green(), summary.stored);
+            println!("Renamed {} meeting activities after their event", summary.annotated);
+            println!("Created {} activities for meetings without tracked time", summary.created);
+            if summary.skipped_all_day > 0 {
+                println!("Skipped {} all-day events", summary.skipped_all_day);
+            }
+            if summary.before_since > 0 {
+                match summary.since {
+                    Some(since) => println!("Left {} past events before {} alone (see --since)", summary.before_since, since.format("%Y-%m-%d")),
+                    None => println!("Left {} past events alone, as nothing has been tracked yet (see --since)", summary.before_since),
+                }
+            }
+            if summary.unexpanded > 0 {
+                println!("{} {} recurring events repeat in a way that is not supported and only use their first occurrence", "Note:".yellow(), summary.unexpanded);
+            }
+            for zone in &summary.unknown_zones {
+                eprintln!("{} unknown time zone '{}', its times were read as local time", "Warning:".yellow(), zone);
+            }
         }
     }
     
@@ -0,0 +1,476 @@
+// Full-screen terminal dashboard. Everything on screen is read back from the database every
+// couple of seconds, so it follows the daemon, the CLI and the HTTP API alike. Starting and
+// stopping go through the daemon when one runs, the same way the CLI does.
+
+use crate::data::{weight_from_flag, Activity, ActivityFilter};
+use crate::data::duration::format_short;
+use crate::data::timezone::DisplayZone;
+use crate::db::Database;
+use crate::goals::{self, GoalProgress};
+use crate::ipc::{self, Request, Response};
+use crate::reports::{self, GroupBy};
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, Timelike};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+// How often the database is read again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    Day,
+    Week,
+}
+
+enum Prompt {
+    // "name @category #tag"
+    Start(String),
+    // Comma separated tags for the selected row
+    Retag(String),
+}
+
+struct Dashboard {
+    db: Database,
+    view: View,
+    rows: usize,
+    // Everything since Monday; the day view filters it down
+    activities: Vec<Activity>,
+    current: Option<Activity>,
+    // What the daemon sees in front, None when no daemon runs
+    foreground: Option<String>,
+    goals: Vec<GoalProgress>,
+    table: TableState,
+    prompt: Option<Prompt>,
+    message: Option<String>,
+    refreshed: Option<Instant>,
+}
+
+pub fn run(view: View, rows: usize) -> Result<(), Box<dyn Error>> {
+    let mut dashboard = Dashboard {
+        db: Database::new()?,
+        view,
+        rows,
+        activities: Vec::new(),
+        current: None,
+        foreground: None,
+        goals: Vec::new(),
+        table: TableState::default().with_selected(Some(0)),
+        prompt: None,
+        message: None,
+        refreshed: None,
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = dashboard.event_loop(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Dashboard {
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Box<dyn Error>> {
+        loop {
+            if self.refreshed.map(|t| t.elapsed() >= REFRESH_INTERVAL).unwrap_or(true) {
+                self.refresh()?;
+            }
+            terminal.draw(|frame| self.draw(frame))?;
+
+            // Redraw at least once a second so the elapsed time keeps ticking
+            if !event::poll(Duration::from_secs(1))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+                    return Ok(());
+                }
+                if self.prompt.is_some() {
+                    self.handle_prompt_key(key.code);
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('s') => self.prompt = Some(Prompt::Start(String::new())),
+                    KeyCode::Char('x') => {
+                        self.message = Some(match ipc::stop_activity() {
+                            Ok(Some(activity)) => format!("Stopped {}", activity.name),
+                            Ok(None) => "Nothing to stop".to_string(),
+                            Err(e) => format!("Error: {}", e),
+                        });
+                        self.refreshed = None;
+                    },
+                    KeyCode::Char('t') => {
+                        if let Some(activity) = self.selected() {
+                            self.prompt = Some(Prompt::Retag(activity.tags.join(", ")));
+                        }
+                    },
+                    KeyCode::Char('d') => self.switch_view(View::Day),
+                    KeyCode::Char('w') => self.switch_view(View::Week),
+                    KeyCode::Tab => self.switch_view(if self.view == View::Day { View::Week } else { View::Day }),
+                    KeyCode::Char('r') => self.refreshed = None,
+                    KeyCode::Down | KeyCode::Char('j') => self.table.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => self.table.select_previous(),
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    fn handle_prompt_key(&mut self, code: KeyCode) {
+        let text = match &mut self.prompt {
+            Some(Prompt::Start(text)) | Some(Prompt::Retag(text)) => text,
+            None => return,
+        };
+        match code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => { text.pop(); },
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                let result = match self.prompt.take() {
+                    Some(Prompt::Start(text)) => start_from_prompt(&text),
+                    Some(Prompt::Retag(text)) => self.retag_selected(&text),
+                    None => Ok(String::new()),
+                };
+                self.message = Some(result.unwrap_or_else(|e| format!("Error: {}", e)));
+                self.refreshed = None;
+            },
+            _ => {},
+        }
+    }
+
+    fn switch_view(&mut self, view: View) {
+        self.view = view;
+        self.table.select(Some(0));
+    }
+
+    fn refresh(&mut self) -> Result<(), Box<dyn Error>> {
+        let today = Local::now().date_naive();
+        self.activities = self.db.get_activities(&ActivityFilter {
+            from: Some(DisplayZone::Local.start_of_day(goals::week_start(today))),
+            ..Default::default()
+        })?;
+        self.current = self.db.get_open_activity()?;
+        self.foreground = match ipc::send(&Request::Status) {
+            Some(Ok(Response::Status { status })) => Some(status.foreground_app),
+            _ => None,
+        };
+        self.goals = goals::load_progress(&self.db, &self.db.get_goals()?, today)?;
+        self.refreshed = Some(Instant::now());
+        Ok(())
+    }
+
+    // Activities in the current view, newest first
+    fn visible(&self) -> Vec<&Activity> {
+        let today = Local::now().date_naive();
+        let mut visible: Vec<&Activity> = self.activities.iter()
+            .filter(|a| self.view == View::Week || a.start_time.date_naive() == today)
+            .collect();
+        visible.reverse();
+        visible
+    }
+
+    fn selected(&self) -> Option<Activity> {
+        let index = self.table.selected()?;
+        self.visible().into_iter().take(self.rows).nth(index).cloned()
+    }
+
+    fn retag_selected(&self, text: &str) -> Result<String, Box<dyn Error>> {
+        let mut activity = self.selected().ok_or("no activity selected")?;
+        activity.tags = text.split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        self.db.update_activity(&activity)?;
+        Ok(format!("Retagged {}", activity.name))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, middle, recent, footer] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(10),
+            Constraint::Length(self.rows as u16 + 3),
+            Constraint::Length(1),
+        ]).areas(frame.area());
+        let [timeline, side] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(middle);
+        let [categories, goal_area] = Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(side);
+
+        self.draw_header(frame, header);
+        match self.view {
+            View::Day => self.draw_day_timeline(frame, timeline),
+            View::Week => self.draw_week_timeline(frame, timeline),
+        }
+        self.draw_categories(frame, categories);
+        self.draw_goals(frame, goal_area);
+        self.draw_recent(frame, recent);
+        self.draw_footer(frame, footer);
+    }
+
+    fn draw_header(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = match &self.current {
+            Some(activity) => {
+                let elapsed = (Local::now() - activity.start_time).to_std().unwrap_or_default();
+                vec![
+                    Span::styled("● ", Style::default().fg(Color::Green)),
+                    Span::styled(activity.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(format!(" ({})  ", activity.category)),
+                    Span::styled(clock(elapsed), Style::default().fg(Color::Cyan)),
+                ]
+            },
+            None => vec![Span::styled("No activity running", Style::default().fg(Color::DarkGray))],
+        };
+        spans.push(Span::raw("    "));
+        spans.push(match &self.foreground {
+            Some(app) if !app.is_empty() => Span::raw(format!("Daemon sees: {}", app)),
+            Some(_) => Span::raw("Daemon running"),
+            None => Span::styled("No daemon running", Style::default().fg(Color::Yellow)),
+        });
+
+        let title = match self.view {
+            View::Day => " Today ",
+            View::Week => " This week ",
+        };
+        let block = Block::default().borders(Borders::ALL).title(title);
+        frame.render_widget(Paragraph::new(Line::from(spans)).block(block), area);
+    }
+
+    // One line per hour, twelve five-minute cells each, coloured by the weight of what filled them
+    fn draw_day_timeline(&self, frame: &mut Frame, area: Rect) {
+        let now = Local::now();
+        let today = now.date_naive();
+        let mut slots: Vec<Option<(i8, i64)>> = vec![None; 24 * 12];
+        for activity in self.activities.iter().filter(|a| a.start_time.date_naive() == today) {
+            let end = activity.end_time.unwrap_or(now);
+            for (slot, seconds) in slot_overlaps(activity.start_time, end, today) {
+                // The activity covering most of a slot decides its colour
+                if slots[slot].map(|(_, longest)| seconds > longest).unwrap_or(true) {
+                    slots[slot] = Some((activity.weight, seconds));
+                }
+            }
+        }
+
+        let first_hour = slots.iter().position(|s| s.is_some()).map(|i| i / 12).unwrap_or(now.hour() as usize);
+        let last_hour = now.hour() as usize;
+        let visible_hours = (area.height.saturating_sub(2) as usize).max(1);
+        let start_hour = first_hour.max((last_hour + 1).saturating_sub(visible_hours));
+
+        let lines: Vec<Line> = (start_hour..=last_hour.max(start_hour))
+            .map(|hour| {
+                let mut spans = vec![Span::raw(format!("{:02}:00 ", hour))];
+                for slot in &slots[hour * 12..hour * 12 + 12] {
+                    spans.push(match slot {
+                        Some((weight, _)) => Span::styled("██", Style::default().fg(weight_color(*weight))),
+                        None => Span::styled("··", Style::default().fg(Color::DarkGray)),
+                    });
+                }
+                Line::from(spans)
+            })
+            .collect();
+        let block = Block::default().borders(Borders::ALL).title(" Timeline ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    // One bar per day, split into productive, neutral and distracting time
+    fn draw_week_timeline(&self, frame: &mut Frame, area: Rect) {
+        let today = Local::now().date_naive();
+        let monday = goals::week_start(today);
+        let days: Vec<(NaiveDate, [u64; 3])> = (0..7)
+            .map(|i| monday + ChronoDuration::days(i))
+            .map(|date| {
+                let mut split = [0u64; 3];
+                for activity in self.activities.iter().filter(|a| a.start_time.date_naive() == date) {
+                    let bucket = match activity.weight {
+                        w if w > 0 => 0,
+                        0 => 1,
+                        _ => 2,
+                    };
+                    split[bucket] += seconds_of(activity);
+                }
+                (date, split)
+            })
+            .collect();
+        let longest = days.iter().map(|(_, split)| split.iter().sum::<u64>()).max().unwrap_or(0).max(1);
+        let width = area.width.saturating_sub(22) as u64;
+
+        let lines: Vec<Line> = days.iter()
+            .map(|(date, split)| {
+                let total: u64 = split.iter().sum();
+                let mut spans = vec![Span::styled(
+                    format!("{} ", date.format("%a %d")),
+                    if *date == today { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() },
+                )];
+                for (seconds, color) in split.iter().zip([Color::Green, Color::Gray, Color::Red]) {
+                    let cells = (seconds * width / longest) as usize;
+                    spans.push(Span::styled("█".repeat(cells), Style::default().fg(color)));
+                }
+                spans.push(Span::raw(format!(" {}", format_short(Duration::from_secs(total)))));
+                Line::from(spans)
+            })
+            .collect();
+        let block = Block::default().borders(Borders::ALL).title(" Week ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_categories(&self, frame: &mut Frame, area: Rect) {
+        let visible: Vec<Activity> = self.visible().into_iter().cloned().collect();
+        let totals = reports::group_totals(&visible, GroupBy::Category);
+        let total: u64 = totals.iter().map(|t| t.seconds).sum::<u64>().max(1);
+        let bar_width = area.width.saturating_sub(32) as u64;
+
+        let lines: Vec<Line> = totals.iter()
+            .take(area.height.saturating_sub(2) as usize)
+            .map(|group| Line::from(vec![
+                Span::raw(format!("{:<18.18} {:>8} ", group.key, format_short(Duration::from_secs(group.seconds)))),
+                Span::styled("█".repeat((group.seconds * bar_width / total) as usize), Style::default().fg(Color::Cyan)),
+            ]))
+            .collect();
+        let block = Block::default().borders(Borders::ALL).title(" Categories ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_goals(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = if self.goals.is_empty() {
+            vec![Line::styled("No goals set (goal add)", Style::default().fg(Color::DarkGray))]
+        } else {
+            self.goals.iter()
+                .map(|progress| {
+                    let share = progress.spent.as_secs_f64() / progress.goal.amount.as_secs_f64().max(1.0);
+                    let filled = (share.min(1.0) * 10.0).round() as usize;
+                    let color = if progress.met { Color::Green } else { Color::Yellow };
+                    Line::from(vec![
+                        Span::styled(format!("{}{} ", "█".repeat(filled), "░".repeat(10 - filled)), Style::default().fg(color)),
+                        Span::raw(format!("{:>4.0}% {}", share * 100.0, progress.description)),
+                    ])
+                })
+                .collect()
+        };
+        let block = Block::default().borders(Borders::ALL).title(" Goals ");
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_recent(&mut self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self.visible().into_iter()
+            .take(self.rows)
+            .map(|activity| {
+                let time = match self.view {
+                    View::Day => activity.start_time.format("%H:%M").to_string(),
+                    View::Week => activity.start_time.format("%a %H:%M").to_string(),
+                };
+                let duration = match activity.end_time {
+                    Some(_) => format_short(Duration::from_secs(seconds_of(activity))),
+                    None => "running".to_string(),
+                };
+                Row::new(vec![
+                    time,
+                    activity.name.clone(),
+                    activity.category.clone(),
+                    duration,
+                    format!("{:+}", activity.weight),
+                    activity.tags.join(", "),
+                ]).style(Style::default().fg(weight_color(activity.weight)))
+            })
+            .collect();
+        if self.table.selected().map(|i| i >= rows.len()).unwrap_or(false) {
+            self.table.select(Some(rows.len().saturating_sub(1)));
+        }
+
+        let table = Table::new(rows, [
+            Constraint::Length(10),
+            Constraint::Percentage(30),
+            Constraint::Percentage(18),
+            Constraint::Length(8),
+            Constraint::Length(3),
+            Constraint::Percentage(30),
+        ])
+        .header(Row::new(vec!["Start", "Activity", "Category", "Time", "W", "Tags"]).style(Style::default().add_modifier(Modifier::BOLD)))
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).title(" Recent "));
+        frame.render_stateful_widget(table, area, &mut self.table);
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = match &self.prompt {
+            Some(Prompt::Start(text)) => Line::from(format!("Start (name @category #tag): {}▏", text)),
+            Some(Prompt::Retag(text)) => Line::from(format!("Tags (comma separated): {}▏", text)),
+            None => {
+                let help = "q quit  s start  x stop  t retag  d/w day/week  ↑↓ select";
+                match &self.message {
+                    Some(message) => Line::from(vec![
+                        Span::styled(message.clone(), Style::default().fg(Color::Yellow)),
+                        Span::raw(format!("   {}", help)),
+                    ]),
+                    None => Line::styled(help, Style::default().fg(Color::DarkGray)),
+                }
+            },
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+// "Write report @Docs #client/acme" starts "Write report" in Docs tagged client/acme.
+// Without a category, "Other" is used.
+fn start_from_prompt(text: &str) -> Result<String, Box<dyn Error>> {
+    let mut name = Vec::new();
+    let mut category = None;
+    let mut tags = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(c) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            category = Some(c.to_string());
+        } else if let Some(t) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+            tags.push(t.to_string());
+        } else {
+            name.push(word);
+        }
+    }
+    if name.is_empty() {
+        return Err("the activity needs a name".into());
+    }
+    let name = name.join(" ");
+    let category = category.unwrap_or_else(|| "Other".to_string());
+    ipc::start_activity(name.clone(), category.clone(), tags, weight_from_flag(true))?;
+    Ok(format!("Started {} in {}", name, category))
+}
+
+fn seconds_of(activity: &Activity) -> u64 {
+    let end = activity.end_time.unwrap_or_else(Local::now);
+    (end - activity.start_time).num_seconds().max(0) as u64
+}
+
+// Seconds of [start, end) falling in each five-minute slot of `day`
+fn slot_overlaps(start: DateTime<Local>, end: DateTime<Local>, day: NaiveDate) -> Vec<(usize, i64)> {
+    let midnight = DisplayZone::Local.start_of_day(day);
+    let from = (start - midnight).num_seconds().max(0);
+    let to = (end - midnight).num_seconds().min(24 * 3600);
+    let mut overlaps = Vec::new();
+    let mut slot = (from / 300) as usize;
+    while (slot as i64) * 300 < to && slot < 24 * 12 {
+        let slot_start = slot as i64 * 300;
+        let seconds = to.min(slot_start + 300) - from.max(slot_start);
+        if seconds > 0 {
+            overlaps.push((slot, seconds));
+        }
+        slot += 1;
+    }
+    overlaps
+}
+
+fn weight_color(weight: i8) -> Color {
+    match weight {
+        2 => Color::Green,
+        1 => Color::LightGreen,
+        0 => Color::Gray,
+        -1 => Color::LightRed,
+        _ => Color::Red,
+    }
+}
+
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+// A duration written the way people type it on the command line: "90s", "25m", "1h30m", "2h".
+// A bare number is read as minutes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumanDuration(pub Duration);
+
+impl FromStr for HumanDuration {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().to_lowercase();
+        if trimmed.is_empty() {
+            return Err("empty duration".to_string());
+        }
+        let too_long = || format!("duration '{}' is too long", s);
+        if let Ok(minutes) = trimmed.parse::<u64>() {
+            return minutes.checked_mul(60)
+                .map(|secs| HumanDuration(Duration::from_secs(secs)))
+                .ok_or_else(too_long);
+        }
+        
+        let mut total = 0u64;
+        let mut number = String::new();
+        for c in trimmed.chars() {
+            match c {
+                '0'..='9' => number.push(c),
+                'h' | 'm' | 's' | 'd' => {
+                    let value: u64 = number.parse()
+                        .map_err(|_| format!("invalid duration '{}'", s))?;
+                    number.clear();
+                    let unit = match c {
+                        'd' => 86400,
+                        'h' => 3600,
+                        'm' => 60,
+                        _ => 1,
+                    };
+                    total = value.checked_mul(unit)
+                        .and_then(|secs| total.checked_add(secs))
+                        .ok_or_else(too_long)?;
+                },
+                ' ' => {},
+                _ => return Err(format!("invalid duration '{}', expected something like 30m or 1h30m", s)),
+            }
+        }
+        if !number.is_empty() {
+            return Err(format!("missing unit after '{}' in '{}'", number, s));
+        }
+        Ok(HumanDuration(Duration::from_secs(total)))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_short(self.0))
+    }
+}
+
+// Compact form for terminal output, e.g. "1h 05m", "25m", "40s"
+pub fn format_short(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_typed_durations() {
+        let secs = |s: &str| s.parse::<HumanDuration>().unwrap().0.as_secs();
+        assert_eq!(secs("25"), 1500);
+        assert_eq!(secs("1h30m"), 5400);
+        assert_eq!(secs("2d 90s"), 172890);
+    }
+
+    #[test]
+    fn rejects_durations_that_overflow() {
+        for input in ["999999999999999999", "99999999999999999d", "18446744073709551615s1s"] {
+            assert!(input.parse::<HumanDuration>().unwrap_err().contains("too long"), "{}", input);
+        }
+    }
+}
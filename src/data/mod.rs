@@ -1,8 +1,26 @@
-use chrono::{DateTime, Local};
+pub mod duration;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod timezone;
+
+use chrono::{DateTime, Local, NaiveDate};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+// Productivity weights run from MIN_WEIGHT (a distraction) through 0 (neutral, e.g. email or
+// meetings) to MAX_WEIGHT (deep work)
+pub const MIN_WEIGHT: i8 = -2;
+pub const MAX_WEIGHT: i8 = 2;
+
+// What the old productive flag stands for on the weight scale. Not productive covered neutral
+// apps and meetings as well as distractions, so it is read as neutral rather than negative.
+pub fn weight_from_flag(is_productive: bool) -> i8 {
+    if is_productive { 1 } else { 0 }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "SerializedActivity")]
 pub struct Activity {
     pub id: Option<i64>,
     pub name: String,
@@ -13,6 +31,85 @@ pub struct Activity {
     pub tags: Vec<String>,
     pub notes: Option<String>,
     pub is_productive: bool,
+    // Where the record came from when it was not tracked live, e.g. "toggl:1700000000:Standup"
+    #[serde(default)]
+    pub source: Option<String>,
+    // Title of the foreground window, for automatically tracked activities
+    #[serde(default)]
+    pub window_title: Option<String>,
+    // Page address for browser activities, as left by the privacy settings
+    #[serde(default)]
+    pub url: Option<String>,
+    // Working tree root and branch the time was spent in, when it could be resolved
+    #[serde(default)]
+    pub git_repo: Option<String>,
+    #[serde(default)]
+    pub git_branch: Option<String>,
+    // Command line running in the terminal, reported by the shell hooks
+    #[serde(default)]
+    pub command: Option<String>,
+    // Productivity weight; `is_productive` is kept in step as weight > 0
+    pub weight: i8,
+    // IANA zone (or UTC offset) the activity was recorded in; the system zone when saved if unset
+    #[serde(default)]
+    pub zone: Option<String>,
+}
+
+// An activity as written by serde. JSON from before weights existed has no `weight`, which then
+// follows the productive flag like it does for old database rows.
+#[derive(Deserialize)]
+struct SerializedActivity {
+    id: Option<i64>,
+    name: String,
+    category: String,
+    start_time: DateTime<Local>,
+    end_time: Option<DateTime<Local>>,
+    duration: Option<Duration>,
+    tags: Vec<String>,
+    notes: Option<String>,
+    is_productive: bool,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    window_title: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    git_repo: Option<String>,
+    #[serde(default)]
+    git_branch: Option<String>,
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    weight: Option<i8>,
+    #[serde(default)]
+    zone: Option<String>,
+}
+
+impl From<SerializedActivity> for Activity {
+    fn from(a: SerializedActivity) -> Self {
+        Self {
+            id: a.id,
+            name: a.name,
+            category: a.category,
+            start_time: a.start_time,
+            end_time: a.end_time,
+            duration: a.duration,
+            tags: a.tags,
+            notes: a.notes,
+            is_productive: a.is_productive,
+            source: a.source,
+            window_title: a.window_title,
+            url: a.url,
+            git_repo: a.git_repo,
+            git_branch: a.git_branch,
+            command: a.command,
+            weight: a.weight
+                .map(|w| w.clamp(MIN_WEIGHT, MAX_WEIGHT))
+                .unwrap_or_else(|| weight_from_flag(a.is_productive)),
+            zone: a.zone,
+        }
+    }
 }
 
 impl Activity {
@@ -27,9 +124,22 @@ impl Activity {
             tags,
             notes: None,
             is_productive,
+            source: None,
+            window_title: None,
+            git_repo: None,
+            git_branch: None,
+            command: None,
+            weight: weight_from_flag(is_productive),
+            zone: None,
+            url: None,
         }
     }
     
+    pub fn set_weight(&mut self, weight: i8) {
+        self.weight = weight.clamp(MIN_WEIGHT, MAX_WEIGHT);
+        self.is_productive = self.weight > 0;
+    }
+    
     pub fn stop(&mut self) {
         let now = Local::now();
         self.end_time = Some(now);
@@ -37,3 +147,212 @@ impl Activity {
     }
 }
 
+// A VEVENT read from an imported calendar file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub summary: String,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub location: Option<String>,
+}
+
+// A stretch of time deliberately left unrecorded, e.g. while automatic tracking was paused
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Gap {
+    pub id: Option<i64>,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub reason: String,
+}
+
+// A command line run in a shell with the hooks from `shell-hook` installed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShellCommand {
+    pub id: Option<i64>,
+    pub command: String,
+    pub cwd: String,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub exit_status: Option<i32>,
+    pub shell_pid: u32,
+}
+
+// A timeboxed run of work blocks separated by breaks, started with `focus start`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusSession {
+    pub id: Option<i64>,
+    pub name: String,
+    pub start_time: DateTime<Local>,
+    pub end_time: DateTime<Local>,
+    pub work_length: Duration,
+    pub break_length: Duration,
+    pub planned_cycles: u32,
+    pub completed_cycles: u32,
+    // Times a distracting app or site came to the front during a work block
+    pub interruptions: u32,
+    pub interrupted_by: Vec<String>,
+    // False when the session was stopped before its last work block ended
+    pub completed: bool,
+}
+
+// How an alert rule measures time: one unbroken stretch, or everything since midnight
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetKind {
+    Continuous,
+    Daily,
+}
+
+impl BudgetKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetKind::Continuous => "continuous",
+            BudgetKind::Daily => "daily",
+        }
+    }
+}
+
+// Raised by the daemon when time in a category went over a budget from alerts.json
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DistractionAlert {
+    pub id: Option<i64>,
+    // What the rule watches, e.g. "Social Media" or "unproductive time"
+    pub target: String,
+    pub kind: BudgetKind,
+    pub budget: Duration,
+    // Time counted against the budget when the alert fired
+    pub spent: Duration,
+    // App in front at that moment
+    pub app: String,
+    pub triggered_at: DateTime<Local>,
+}
+
+// What a goal counts: time in a category or time carrying a tag
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", content = "name", rename_all = "snake_case")]
+pub enum GoalTarget {
+    Category(String),
+    Tag(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalDirection {
+    AtLeast,
+    // A limit; the daemon notifies when it is crossed
+    AtMost,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalPeriod {
+    Day,
+    // Monday to Friday, each day on its own
+    Weekday,
+    // Monday to Sunday as a whole
+    Week,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Goal {
+    pub id: Option<i64>,
+    pub target: GoalTarget,
+    pub direction: GoalDirection,
+    pub amount: Duration,
+    pub period: GoalPeriod,
+    // Days before this do not count towards streaks
+    pub created_at: DateTime<Local>,
+}
+
+// One row of the daily_summaries rollup: the activities of one day with the same category, name
+// and tag. Every activity is counted once with no tag, for totals, and once more per tag it has.
+#[derive(Debug, Serialize, Clone)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub category: String,
+    // Activity name, i.e. the app for automatically tracked time
+    pub app: String,
+    pub tag: Option<String>,
+    pub seconds: u64,
+    pub activities: usize,
+    // Sum of weight × seconds, for the productivity score
+    pub weighted_seconds: i64,
+    pub productive_seconds: u64,
+    pub unproductive_seconds: u64,
+}
+
+// Written by the daemon on every tick. A heartbeat still present at startup means the last
+// daemon did not shut down cleanly, and the span it was sampling ran at least until `time`.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    pub time: DateTime<Local>,
+    pub app: Option<String>,
+    pub browser_url: Option<String>,
+    pub window_title: Option<String>,
+    pub git_repo: Option<String>,
+    pub git_branch: Option<String>,
+    pub command: Option<String>,
+    pub span_start: Option<DateTime<Local>>,
+    // Set while automatic tracking is paused, so a restart after a crash can carry on the pause
+    pub paused_since: Option<DateTime<Local>>,
+    pub paused_until: Option<DateTime<Local>>,
+}
+
+// Criteria used when reading activities back out of the database
+#[derive(Debug, Default, Clone)]
+pub struct ActivityFilter {
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    // Match activities overlapping from..to rather than only those starting in it
+    pub overlapping: bool,
+}
+
+impl ActivityFilter {
+    // Tags are stored as JSON, so they are matched after the rows are loaded.
+    // An activity matches when it carries any of the requested tags.
+    pub fn matches_tags(&self, activity: &Activity) -> bool {
+        self.tags.is_empty() || self.tags.iter().any(|tag| activity.tags.contains(tag))
+    }
+}
+
+// An insert, update or delete of an activity, numbered in the order they were made
+#[derive(Debug, Clone)]
+pub struct ActivityChange {
+    pub seq: i64,
+    pub id: i64,
+    // None once the activity was deleted
+    pub activity: Option<Activity>,
+}
+
+// A free-text value kept in the database, for the privacy audit
+#[derive(Debug, Clone)]
+pub struct StoredText {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub id: i64,
+    // App (activity name) and category the value was recorded with, where known
+    pub app: String,
+    pub category: Option<String>,
+    pub value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activities_without_a_weight_follow_the_productive_flag() {
+        let json = |extra: &str| format!(
+            r#"{{"id": 1, "name": "Reddit", "category": "Social Media", "start_time": "2026-03-02T09:00:00Z",
+                "end_time": null, "duration": null, "tags": [], "notes": null, "is_productive": false{}}}"#,
+            extra,
+        );
+        let old: Activity = serde_json::from_str(&json("")).unwrap();
+        assert_eq!(old.weight, 0);
+        let weighted: Activity = serde_json::from_str(&json(r#", "weight": -2"#)).unwrap();
+        assert_eq!(weighted.weight, -2);
+    }
+}
@@ -0,0 +1,52 @@
+// Fixtures shared by the unit tests
+use super::Activity;
+use chrono::{DateTime, Local, TimeZone, Utc};
+
+// A time in March 2026, given in UTC so tests read the same in any local time zone.
+// The 2nd is a Monday.
+pub fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap().with_timezone(&Local)
+}
+
+// A "Code" activity in Development from `start` to `end`, recorded in UTC
+pub fn finished(start: DateTime<Local>, end: DateTime<Local>) -> ActivityBuilder {
+    let mut activity = Activity::new("Code".to_string(), "Development".to_string(), Vec::new(), true);
+    activity.start_time = start;
+    activity.end_time = Some(end);
+    activity.duration = Some((end - start).to_std().unwrap());
+    activity.zone = Some("UTC".to_string());
+    ActivityBuilder(activity)
+}
+
+pub struct ActivityBuilder(Activity);
+
+impl ActivityBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.0.name = name.to_string();
+        self
+    }
+
+    pub fn category(mut self, category: &str) -> Self {
+        self.0.category = category.to_string();
+        self
+    }
+
+    pub fn tags(mut self, tags: &[&str]) -> Self {
+        self.0.tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn weight(mut self, weight: i8) -> Self {
+        self.0.set_weight(weight);
+        self
+    }
+
+    pub fn repo(mut self, repo: Option<&str>) -> Self {
+        self.0.git_repo = repo.map(str::to_string);
+        self
+    }
+
+    pub fn build(self) -> Activity {
+        self.0
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+// Time zone used when presenting timestamps to the user.
+// Accepts "local", "utc", a fixed offset such as "+05:30" or an IANA name such as "Europe/Berlin".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayZone {
+    Local,
+    Utc,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl DisplayZone {
+    // ISO 8601 / RFC 3339 representation of the timestamp in this zone
+    pub fn format(&self, time: &DateTime<Local>) -> String {
+        match self {
+            DisplayZone::Local => time.to_rfc3339_opts(SecondsFormat::Secs, false),
+            DisplayZone::Utc => time.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true),
+            DisplayZone::Fixed(offset) => time.with_timezone(offset).to_rfc3339_opts(SecondsFormat::Secs, false),
+            DisplayZone::Named(tz) => time.with_timezone(tz).to_rfc3339_opts(SecondsFormat::Secs, false),
+        }
+    }
+    
+    // Resolve a wall-clock time in this zone to an absolute instant.
+    // Ambiguous times pick the earlier instant, skipped times (DST gaps) move forward an hour.
+    pub fn resolve(&self, naive: NaiveDateTime) -> DateTime<Local> {
+        fn pick<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> DateTime<Utc> {
+            zone.from_local_datetime(&naive)
+                .earliest()
+                .or_else(|| zone.from_local_datetime(&(naive + chrono::Duration::hours(1))).earliest())
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&naive))
+        }
+        
+        let utc = match self {
+            DisplayZone::Local => pick(&Local, naive),
+            DisplayZone::Utc => pick(&Utc, naive),
+            DisplayZone::Fixed(offset) => pick(offset, naive),
+            DisplayZone::Named(tz) => pick(tz, naive),
+        };
+        utc.with_timezone(&Local)
+    }
+    
+    // The instant at which the given calendar day begins in this zone
+    pub fn start_of_day(&self, date: NaiveDate) -> DateTime<Local> {
+        self.resolve(date.and_hms_opt(0, 0, 0).unwrap())
+    }
+    
+    // Wall-clock time of the instant in this zone
+    pub fn naive_local(&self, time: &DateTime<Local>) -> NaiveDateTime {
+        match self {
+            DisplayZone::Local => time.naive_local(),
+            DisplayZone::Utc => time.naive_utc(),
+            DisplayZone::Fixed(offset) => time.with_timezone(offset).naive_local(),
+            DisplayZone::Named(tz) => time.with_timezone(tz).naive_local(),
+        }
+    }
+}
+
+// IANA name of the system time zone, e.g. "Europe/Berlin", stored with every activity so the
+// wall-clock time it was recorded at can be recovered after travel. Falls back to the current
+// UTC offset when the name cannot be determined.
+pub fn local_zone_name() -> String {
+    iana_time_zone::get_timezone().unwrap_or_else(|_| Local::now().format("%:z").to_string())
+}
+
+// Used when reports are not given --day-start
+pub const DAY_START_ENV: &str = "PRODUCTIVITY_TRACKER_DAY_START";
+
+// Where one reporting day ends and the next begins: midnight by default, or a later hour for
+// people who work past midnight, so 01:30 still counts towards the evening before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DayBoundary {
+    pub zone: DisplayZone,
+    // 0 to 23
+    pub start_hour: u32,
+}
+
+impl Default for DayBoundary {
+    fn default() -> Self {
+        Self { zone: DisplayZone::Local, start_hour: 0 }
+    }
+}
+
+impl DayBoundary {
+    // A missing hour falls back to $PRODUCTIVITY_TRACKER_DAY_START, then to midnight
+    pub fn new(zone: DisplayZone, start_hour: Option<u32>) -> Result<Self, String> {
+        let start_hour = match start_hour {
+            Some(hour) => hour,
+            None => match std::env::var(DAY_START_ENV) {
+                Ok(value) => value.trim().parse().map_err(|_| format!("{} must be an hour from 0 to 23", DAY_START_ENV))?,
+                Err(_) => 0,
+            },
+        };
+        if start_hour > 23 {
+            return Err(format!("day start hour {} is not between 0 and 23", start_hour));
+        }
+        Ok(Self { zone, start_hour })
+    }
+    
+    // The instant the reporting day `date` begins
+    pub fn start_of(&self, date: NaiveDate) -> DateTime<Local> {
+        self.zone.resolve(date.and_hms_opt(self.start_hour, 0, 0).unwrap())
+    }
+    
+    // The reporting day an instant belongs to
+    pub fn date_of(&self, time: &DateTime<Local>) -> NaiveDate {
+        (self.zone.naive_local(time) - chrono::Duration::hours(self.start_hour as i64)).date()
+    }
+}
+
+impl FromStr for DisplayZone {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        match trimmed.to_lowercase().as_str() {
+            "local" => return Ok(DisplayZone::Local),
+            "utc" | "z" => return Ok(DisplayZone::Utc),
+            _ => {}
+        }
+        
+        if trimmed.starts_with('+') || trimmed.starts_with('-') {
+            // Reuse chrono's offset parser by attaching the offset to a dummy timestamp
+            return DateTime::parse_from_str(&format!("2000-01-01T00:00:00{}", trimmed), "%Y-%m-%dT%H:%M:%S%:z")
+                .or_else(|_| DateTime::parse_from_str(&format!("2000-01-01T00:00:00{}", trimmed), "%Y-%m-%dT%H:%M:%S%z"))
+                .map(|t| DisplayZone::Fixed(*t.offset()))
+                .map_err(|_| format!("invalid UTC offset '{}'", trimmed));
+        }
+        
+        trimmed.parse::<Tz>()
+            .map(DisplayZone::Named)
+            .map_err(|_| format!("unknown time zone '{}'", trimmed))
+    }
+}
+
+impl fmt::Display for DisplayZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayZone::Local => write!(f, "local"),
+            DisplayZone::Utc => write!(f, "utc"),
+            DisplayZone::Fixed(offset) => write!(f, "{}", offset),
+            DisplayZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
@@ -1,7 +1,27 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{params_from_iter, Connection, Result, Row};
 use std::path::PathBuf;
+use std::time::Duration;
+use chrono::{DateTime, Local, NaiveDate};
 use dirs::home_dir;
-use crate::data::Activity;
+use crate::data::{Activity, ActivityChange, ActivityFilter, BudgetKind, CalendarEvent, DailySummary, DistractionAlert, FocusSession, Gap, Goal, GoalDirection, GoalPeriod, GoalTarget, Heartbeat, ShellCommand, StoredText};
+use crate::data::{weight_from_flag, MAX_WEIGHT, MIN_WEIGHT};
+use crate::data::timezone::{local_zone_name, DisplayZone};
+use crate::monitor::AppMonitor;
+
+// Directory holding the database and the other files the tracker keeps per user
+pub fn data_dir() -> PathBuf {
+    let mut path = home_dir().unwrap_or_default();
+    path.push(".productivity_tracker");
+    std::fs::create_dir_all(&path).unwrap_or_default();
+    path
+}
+
+// Column order expected by row_to_activity
+const ACTIVITY_COLUMNS: &str = "id, name, category, start_time, end_time, duration, tags, notes, is_productive, source, window_title, git_repo, git_branch, command, weight, start_epoch, zone, url";
+
+// Bumped whenever the rollup triggers or the way days are split changes, to rebuild them once.
+// Kept in the meta table, so `PRAGMA user_version` stays free for the schema as a whole.
+const ROLLUP_VERSION: i64 = 3;
 
 pub struct Database {
     conn: Connection,
@@ -10,16 +30,22 @@ pub struct Database {
 impl Database {
     pub fn new() -> Result<Self> {
         let db_path = Self::get_db_path();
-        let conn = Connection::open(db_path)?;
+        Self::open(Connection::open(db_path)?)
+    }
+    
+    fn open(conn: Connection) -> Result<Self> {
         let db = Self { conn };
         db.init()?;
         Ok(db)
     }
     
+    #[cfg(test)]
+    pub(crate) fn open_in_memory() -> Result<Self> {
+        Self::open(Connection::open_in_memory()?)
+    }
+    
     fn get_db_path() -> PathBuf {
-        let mut path = home_dir().unwrap_or_default();
-        path.push(".productivity_tracker");
-        std::fs::create_dir_all(&path).unwrap_or_default();
+        let mut path = data_dir();
         path.push("activities.db");
         path
     }
@@ -39,14 +65,366 @@ impl Database {
             )",
             [],
         )?;
+        // Bookkeeping values such as the rollup version, one row each
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value INTEGER NOT NULL)",
+            [],
+        )?;
+        
+        self.add_column_if_missing("activities", "source", "TEXT")?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_activities_source ON activities (source)",
+            [],
+        )?;
+        self.add_column_if_missing("activities", "window_title", "TEXT")?;
+        self.add_column_if_missing("activities", "git_repo", "TEXT")?;
+        self.add_column_if_missing("activities", "git_branch", "TEXT")?;
+        self.add_column_if_missing("activities", "command", "TEXT")?;
+        // Weights replace the productive flag
+        if self.add_column_if_missing("activities", "weight", "INTEGER")? {
+            self.fill_weights()?;
+        }
+        // The text timestamps only compare correctly while the UTC offset stays the same, so
+        // ranges are queried on UTC epoch seconds. `utc_offset` and `zone` keep the wall clock
+        // the activity was recorded on, which is what decides the day it belongs to.
+        let added_epochs = self.add_column_if_missing("activities", "start_epoch", "INTEGER")?;
+        self.add_column_if_missing("activities", "end_epoch", "INTEGER")?;
+        self.add_column_if_missing("activities", "utc_offset", "INTEGER")?;
+        self.add_column_if_missing("activities", "zone", "TEXT")?;
+        if added_epochs {
+            self.fill_epochs()?;
+        }
+        self.add_column_if_missing("activities", "url", "TEXT")?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_activities_start_epoch ON activities (start_epoch)",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_activities_category_epoch ON activities (category, start_epoch)",
+            [],
+        )?;
+        self.conn.execute_batch(
+            "DROP INDEX IF EXISTS idx_activities_start_time;
+            DROP INDEX IF EXISTS idx_activities_category;",
+        )?;
+        
+        // Every insert, update and delete of an activity gets the next change number, kept per
+        // activity id, so other processes can follow edits as well as new rows
+        let note_new = note_change_sql("NEW");
+        let note_old = note_change_sql("OLD");
+        self.conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS activity_changes (activity_id INTEGER PRIMARY KEY, seq INTEGER NOT NULL);
+            CREATE INDEX IF NOT EXISTS idx_activity_changes_seq ON activity_changes (seq);
+            CREATE TRIGGER IF NOT EXISTS activities_change_insert AFTER INSERT ON activities BEGIN {note_new} END;
+            CREATE TRIGGER IF NOT EXISTS activities_change_update AFTER UPDATE ON activities BEGIN {note_new} END;
+            CREATE TRIGGER IF NOT EXISTS activities_change_delete AFTER DELETE ON activities BEGIN {note_old} END;",
+        ))?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS calendar_events (
+                uid TEXT PRIMARY KEY,
+                summary TEXT NOT NULL,
+                start_time TEXT NOT NULL,
+                end_time TEXT NOT NULL,
+                location TEXT
+            )",
+            [],
+        )?;
+        self.add_epoch_column("calendar_events", "start_epoch", "start_time")?;
+        self.add_epoch_column("calendar_events", "end_epoch", "end_time")?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS gaps (
+                id INTEGER PRIMARY KEY,
+                start_time TEXT NOT NULL,
+                end_time TEXT NOT NULL,
+                reason TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.add_epoch_column("gaps", "start_epoch", "start_time")?;
+        self.add_epoch_column("gaps", "end_epoch", "end_time")?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_gaps_start_epoch ON gaps (start_epoch)", [])?;
+        
+        // A single row, present only while a daemon is running
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS heartbeat (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                beat_time TEXT NOT NULL,
+                app TEXT,
+                browser_url TEXT,
+                span_start TEXT
+            )",
+            [],
+        )?;
+        self.add_column_if_missing("heartbeat", "window_title", "TEXT")?;
+        self.add_column_if_missing("heartbeat", "git_repo", "TEXT")?;
+        self.add_column_if_missing("heartbeat", "git_branch", "TEXT")?;
+        self.add_column_if_missing("heartbeat", "command", "TEXT")?;
+        self.add_column_if_missing("heartbeat", "paused_since", "TEXT")?;
+        self.add_column_if_missing("heartbeat", "paused_until", "TEXT")?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS shell_commands (
+                id INTEGER PRIMARY KEY,
+                command TEXT NOT NULL,
+                cwd TEXT NOT NULL,
+                start_time TEXT NOT NULL,
+                end_time TEXT NOT NULL,
+                exit_status INTEGER,
+                shell_pid INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS focus_sessions (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                start_time TEXT NOT NULL,
+                end_time TEXT NOT NULL,
+                work_seconds INTEGER NOT NULL,
+                break_seconds INTEGER NOT NULL,
+                planned_cycles INTEGER NOT NULL,
+                completed_cycles INTEGER NOT NULL,
+                interruptions INTEGER NOT NULL,
+                interrupted_by TEXT NOT NULL,
+                completed INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        self.add_epoch_column("focus_sessions", "start_epoch", "start_time")?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS alerts (
+                id INTEGER PRIMARY KEY,
+                target TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                budget_seconds INTEGER NOT NULL,
+                spent_seconds INTEGER NOT NULL,
+                app TEXT NOT NULL,
+                triggered_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        self.add_epoch_column("alerts", "triggered_epoch", "triggered_at")?;
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_alerts_triggered_epoch ON alerts (triggered_epoch)", [])?;
+        
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS goals (
+                id INTEGER PRIMARY KEY,
+                target_kind TEXT NOT NULL,
+                target TEXT NOT NULL,
+                direction TEXT NOT NULL,
+                seconds INTEGER NOT NULL,
+                period TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+        
+        self.init_daily_summaries()?;
         Ok(())
     }
     
+    // The rollup is kept up to date by triggers, so every insert, edit and delete of an activity
+    // is reflected in the same statement, whichever code path (or sqlite3 shell) made it.
+    // An empty tag marks the row every activity is counted in once.
+    fn init_daily_summaries(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "CREATE TABLE IF NOT EXISTS daily_summaries (
+                day TEXT NOT NULL,
+                category TEXT NOT NULL,
+                app TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                seconds INTEGER NOT NULL,
+                activities INTEGER NOT NULL,
+                weighted_seconds INTEGER NOT NULL,
+                productive_seconds INTEGER NOT NULL,
+                unproductive_seconds INTEGER NOT NULL,
+                PRIMARY KEY (day, category, app, tag)
+            )",
+            [],
+        )?;
+        // Day offsets an activity can be split over, so SQL can split spans crossing midnight
+        tx.execute("CREATE TABLE IF NOT EXISTS rollup_days (n INTEGER PRIMARY KEY)", [])?;
+        tx.execute(
+            "INSERT OR IGNORE INTO rollup_days (n)
+            WITH RECURSIVE days(n) AS (SELECT 0 UNION ALL SELECT n + 1 FROM days WHERE n < 366)
+            SELECT n FROM days",
+            [],
+        )?;
+        
+        // New databases and ones with an older rollup get the triggers replaced and the rows refilled.
+        // That reads every activity once, so whoever opened the database is told why it waits.
+        if rollup_version(&tx)? < ROLLUP_VERSION {
+            tx.execute_batch(DROP_ROLLUP_TRIGGERS)?;
+            tx.execute("DELETE FROM daily_summaries", [])?;
+            let activities: i64 = tx.query_row("SELECT COUNT(*) FROM activities", [], |row| row.get(0))?;
+            if activities > 0 {
+                eprintln!("Building daily summaries for {} activities (once after an upgrade)...", activities);
+            }
+            let started = std::time::Instant::now();
+            let rows = fill_daily_summaries(&tx)?;
+            if activities > 0 {
+                eprintln!("Wrote {} summary rows in {:.1}s", rows, started.elapsed().as_secs_f64());
+            }
+            set_rollup_version(&tx, ROLLUP_VERSION)?;
+        }
+        let add = rollup_sql("NEW", 1);
+        let remove = rollup_sql("OLD", -1);
+        tx.execute_batch(&format!(
+            "CREATE TRIGGER IF NOT EXISTS activities_rollup_insert AFTER INSERT ON activities BEGIN {add} END;
+            CREATE TRIGGER IF NOT EXISTS activities_rollup_delete AFTER DELETE ON activities BEGIN {remove} END;
+            CREATE TRIGGER IF NOT EXISTS activities_rollup_update AFTER UPDATE ON activities BEGIN {remove} {add} END;",
+        ))?;
+        tx.commit()
+    }
+    
+    // Recomputes the whole rollup from the activities table. Returns the number of rows written.
+    pub fn rebuild_daily_summaries(&self) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM daily_summaries", [])?;
+        let rows = fill_daily_summaries(&tx)?;
+        tx.commit()?;
+        Ok(rows)
+    }
+    
+    // Rows between two days, both included, optionally for one category only
+    pub fn get_daily_summaries(&self, from: NaiveDate, to: NaiveDate, category: Option<&str>) -> Result<Vec<DailySummary>> {
+        let mut sql = String::from(
+            "SELECT day, category, app, tag, seconds, activities, weighted_seconds, productive_seconds, unproductive_seconds
+            FROM daily_summaries WHERE day >= ?1 AND day <= ?2"
+        );
+        let mut params = vec![from.to_string(), to.to_string()];
+        if let Some(category) = category {
+            params.push(category.to_string());
+            sql.push_str(" AND category = ?3");
+        }
+        sql.push_str(" ORDER BY day");
+        
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(params.iter()), |row| {
+            let day: String = row.get(0)?;
+            let tag: String = row.get(3)?;
+            let seconds: i64 = row.get(4)?;
+            let activities: i64 = row.get(5)?;
+            let productive_seconds: i64 = row.get(7)?;
+            let unproductive_seconds: i64 = row.get(8)?;
+            Ok(DailySummary {
+                date: day.parse().unwrap_or_default(),
+                category: row.get(1)?,
+                app: row.get(2)?,
+                tag: Some(tag).filter(|t| !t.is_empty()),
+                seconds: seconds.max(0) as u64,
+                activities: activities.max(0) as usize,
+                weighted_seconds: row.get(6)?,
+                productive_seconds: productive_seconds.max(0) as u64,
+                unproductive_seconds: unproductive_seconds.max(0) as u64,
+            })
+        })?;
+        rows.collect()
+    }
+    
+    // Weights for rows saved before they existed. The flag only told productive time from the
+    // rest, and the rest included neutral apps, chat and unknown apps as well as distractions,
+    // so the built-in weight decides where on its side of the scale a row lands.
+    fn fill_weights(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let rows: Vec<(i64, String, String, bool)> = {
+            let mut stmt = tx.prepare("SELECT id, name, category, is_productive FROM activities WHERE weight IS NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, i64>(3)? != 0)))?;
+            rows.collect::<Result<_>>()?
+        };
+        {
+            let mut update = tx.prepare("UPDATE activities SET weight = ?1 WHERE id = ?2")?;
+            for (id, name, category, is_productive) in rows {
+                let weight = AppMonitor::productivity_weight(&name, &category, None);
+                let flag = weight_from_flag(is_productive);
+                let weight = if is_productive { weight.max(flag) } else { weight.min(flag) };
+                update.execute(rusqlite::params![weight, id])?;
+            }
+        }
+        tx.commit()
+    }
+    
+    // Epochs, offset and zone for rows saved before they existed, from the RFC 3339 text, which
+    // carries the offset it was written with
+    fn fill_epochs(&self) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        // The rollup is rebuilt afterwards; no need to keep it in step row by row
+        tx.execute_batch(DROP_ROLLUP_TRIGGERS)?;
+        let rows: Vec<(i64, String, Option<String>)> = {
+            let mut stmt = tx.prepare("SELECT id, start_time, end_time FROM activities")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+        {
+            let mut update = tx.prepare("UPDATE activities SET start_epoch = ?1, end_epoch = ?2, utc_offset = ?3, zone = ?4 WHERE id = ?5")?;
+            for (id, start_time, end_time) in rows {
+                let Ok(start) = DateTime::parse_from_rfc3339(&start_time) else {
+                    continue;
+                };
+                let end = end_time.as_deref().and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+                update.execute(rusqlite::params![
+                    start.timestamp(),
+                    end.map(|t| t.timestamp()),
+                    start.offset().local_minus_utc(),
+                    start.format("%:z").to_string(),
+                    id,
+                ])?;
+            }
+        }
+        set_rollup_version(&tx, 0)?;
+        tx.commit()
+    }
+    
+    // Columns added after the first release are created lazily so existing databases keep working.
+    // Returns whether the column had to be added, for migrations that fill it in.
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<bool> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|name| name.ok())
+            .any(|name| name == column);
+        
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+        Ok(!exists)
+    }
+    
+    // UTC epoch seconds next to a time stored as RFC 3339 text, which only compares correctly
+    // while the offset stays the same. Rows from before the column existed get it from the text.
+    fn add_epoch_column(&self, table: &str, column: &str, text_column: &str) -> Result<()> {
+        if self.add_column_if_missing(table, column, "INTEGER")? {
+            self.conn.execute(
+                &format!("UPDATE {} SET {} = CAST(strftime('%s', {}) AS INTEGER)", table, column, text_column),
+                [],
+            )?;
+        }
+        Ok(())
+    }
+    
+    // Runs `f` in one transaction, so bulk writes such as an import commit once rather than per row
+    pub fn in_transaction<T, E: From<rusqlite::Error>>(&self, f: impl FnOnce() -> std::result::Result<T, E>) -> std::result::Result<T, E> {
+        let tx = self.conn.unchecked_transaction()?;
+        let result = f()?;
+        tx.commit()?;
+        Ok(result)
+    }
+    
     pub fn save_activity(&self, activity: &Activity) -> Result<i64> {
         let tags_json = serde_json::to_string(&activity.tags).unwrap_or_default();
-        let id = self.conn.execute(
-            "INSERT INTO activities (name, category, start_time, end_time, duration, tags, notes, is_productive)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        let zone = activity.zone.clone().unwrap_or_else(local_zone_name);
+        self.conn.execute(
+            "INSERT INTO activities (name, category, start_time, end_time, duration, tags, notes, is_productive, source, window_title, git_repo, git_branch, command, weight,
+                start_epoch, end_epoch, utc_offset, zone, url)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, NULLIF(?16, ''), ?17, ?18, ?19)",
             [
                 &activity.name,
                 &activity.category,
@@ -56,11 +434,938 @@ impl Database {
                 &tags_json,
                 &activity.notes.clone().unwrap_or_default(),
                 &(if activity.is_productive { 1 } else { 0 }).to_string(),
+                &activity.source.clone().unwrap_or_default(),
+                &activity.window_title.clone().unwrap_or_default(),
+                &activity.git_repo.clone().unwrap_or_default(),
+                &activity.git_branch.clone().unwrap_or_default(),
+                &activity.command.clone().unwrap_or_default(),
+                &activity.weight.to_string(),
+                &activity.start_time.timestamp().to_string(),
+                &activity.end_time.map(|t| t.timestamp().to_string()).unwrap_or_default(),
+                &utc_offset(&zone, &activity.start_time).to_string(),
+                &zone,
+                &activity.url.clone().unwrap_or_default(),
             ],
         )?;
-        Ok(id as i64)
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    pub fn update_activity(&self, activity: &Activity) -> Result<()> {
+        let tags_json = serde_json::to_string(&activity.tags).unwrap_or_default();
+        let zone = activity.zone.clone().unwrap_or_else(local_zone_name);
+        self.conn.execute(
+            "UPDATE activities SET name = ?1, category = ?2, start_time = ?3, end_time = ?4, duration = ?5,
+                tags = ?6, notes = ?7, is_productive = ?8, source = ?9, window_title = ?10,
+                git_repo = ?11, git_branch = ?12, command = ?13, weight = ?14,
+                start_epoch = ?16, end_epoch = NULLIF(?17, ''), utc_offset = ?18, zone = ?19, url = ?20
+            WHERE id = ?15",
+            [
+                &activity.name,
+                &activity.category,
+                &activity.start_time.to_rfc3339(),
+                &activity.end_time.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                &activity.duration.map(|d| d.as_secs() as i64).unwrap_or_default().to_string(),
+                &tags_json,
+                &activity.notes.clone().unwrap_or_default(),
+                &(if activity.is_productive { 1 } else { 0 }).to_string(),
+                &activity.source.clone().unwrap_or_default(),
+                &activity.window_title.clone().unwrap_or_default(),
+                &activity.git_repo.clone().unwrap_or_default(),
+                &activity.git_branch.clone().unwrap_or_default(),
+                &activity.command.clone().unwrap_or_default(),
+                &activity.weight.to_string(),
+                &activity.id.unwrap_or_default().to_string(),
+                &activity.start_time.timestamp().to_string(),
+                &activity.end_time.map(|t| t.timestamp().to_string()).unwrap_or_default(),
+                &utc_offset(&zone, &activity.start_time).to_string(),
+                &zone,
+                &activity.url.clone().unwrap_or_default(),
+            ],
+        )?;
+        Ok(())
+    }
+    
+    // The most recent activity that was started but not stopped yet
+    pub fn get_open_activity(&self) -> Result<Option<Activity>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM activities WHERE end_time IS NULL OR end_time = ''
+            ORDER BY id DESC LIMIT 1",
+            ACTIVITY_COLUMNS
+        ))?;
+        let mut rows = stmt.query_map([], Self::row_to_activity)?;
+        rows.next().transpose()
+    }
+    
+    // When tracking began, i.e. the start of the earliest activity
+    pub fn first_activity_start(&self) -> Result<Option<DateTime<Local>>> {
+        let epoch: Option<i64> = self.conn.query_row("SELECT MIN(start_epoch) FROM activities", [], |row| row.get(0))?;
+        Ok(epoch.and_then(|e| DateTime::from_timestamp(e, 0)).map(|t| t.with_timezone(&Local)))
+    }
+    
+    pub fn last_activity_id(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COALESCE(MAX(id), 0) FROM activities", [], |row| row.get(0))
+    }
+    
+    // Number of the latest change to any activity; 0 before the first
+    pub fn last_activity_change(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM activity_changes", [], |row| row.get(0))
+    }
+    
+    // Number of the latest change to one activity, if it was ever changed
+    pub fn activity_change(&self, id: i64) -> Result<Option<i64>> {
+        let seq = self.conn.query_row("SELECT seq FROM activity_changes WHERE activity_id = ?1", [id], |row| row.get(0));
+        match seq {
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            seq => seq.map(Some),
+        }
+    }
+    
+    // Activities inserted, updated or deleted since change `after`, in the order of their latest
+    // change. An activity changed several times is returned once, as it is now.
+    pub fn get_activity_changes(&self, after: i64) -> Result<Vec<ActivityChange>> {
+        let changed: Vec<(i64, i64)> = {
+            let mut stmt = self.conn.prepare("SELECT seq, activity_id FROM activity_changes WHERE seq > ?1 ORDER BY seq")?;
+            let rows = stmt.query_map([after], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+        
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM activities WHERE id = ?1", ACTIVITY_COLUMNS))?;
+        let mut changes = Vec::with_capacity(changed.len());
+        for (seq, id) in changed {
+            let activity = stmt.query_map([id], Self::row_to_activity)?.next().transpose()?;
+            changes.push(ActivityChange { seq, id, activity });
+        }
+        Ok(changes)
+    }
+    
+    pub fn source_exists(&self, source: &str) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM activities WHERE source = ?1)",
+            [source],
+            |row| row.get(0),
+        )
+    }
+    
+    // Epoch ranges of the finished activities between `from` and `to` that an import could
+    // duplicate. Those imported from the same tool (source starting with "<prefix>:") or entered
+    // by hand are flagged `true` and clash with any imported row; automatically tracked ones are
+    // `false` and only clash with automatic imports. Imports may overlap the other kinds, which
+    // record the same time from another angle.
+    pub fn get_import_clashes(&self, from: i64, to: i64, source_prefix: &str) -> Result<Vec<(i64, i64, bool)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT start_epoch, end_epoch, always FROM (
+                SELECT a.start_epoch, a.end_epoch, {automatic_row} AS automatic,
+                    COALESCE(substr(a.source, 1, length(?3) + 1) = ?3 || ':', 0)
+                        OR (COALESCE(a.source, '') = '' AND NOT {automatic_row}) AS always
+                FROM activities a
+                WHERE a.start_epoch < ?2 AND a.end_epoch IS NOT NULL AND a.end_epoch > ?1)
+            WHERE always OR automatic",
+            automatic_row = format!("EXISTS(SELECT 1 FROM json_each({}) WHERE value = 'automatic')", valid_tags_sql("a")),
+        ))?;
+        let rows = stmt.query_map(rusqlite::params![from, to, source_prefix], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+        rows.collect()
+    }
+    
+    pub fn get_activities(&self, filter: &ActivityFilter) -> Result<Vec<Activity>> {
+        let mut sql = format!("SELECT {} FROM activities WHERE 1 = 1", ACTIVITY_COLUMNS);
+        let mut params: Vec<String> = Vec::new();
+        
+        if let Some(from) = filter.from {
+            params.push(from.timestamp().to_string());
+            if filter.overlapping {
+                sql.push_str(&format!(" AND (end_epoch IS NULL OR end_epoch > CAST(?{} AS INTEGER))", params.len()));
+            } else {
+                sql.push_str(&format!(" AND start_epoch >= CAST(?{} AS INTEGER)", params.len()));
+            }
+        }
+        if let Some(to) = filter.to {
+            params.push(to.timestamp().to_string());
+            sql.push_str(&format!(" AND start_epoch < CAST(?{} AS INTEGER)", params.len()));
+        }
+        if let Some(category) = &filter.category {
+            params.push(category.clone());
+            sql.push_str(&format!(" AND category = ?{}", params.len()));
+        }
+        sql.push_str(" ORDER BY start_epoch");
+        
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(params.iter()), Self::row_to_activity)?;
+        
+        let mut activities = Vec::new();
+        for row in rows {
+            let activity = row?;
+            if filter.matches_tags(&activity) {
+                activities.push(activity);
+            }
+        }
+        Ok(activities)
+    }
+    
+    pub fn save_calendar_event(&self, event: &CalendarEvent) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO calendar_events (uid, summary, start_time, end_time, location, start_epoch, end_epoch)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                event.uid,
+                event.summary,
+                event.start_time.to_rfc3339(),
+                event.end_time.to_rfc3339(),
+                event.location.clone().unwrap_or_default(),
+                event.start_time.timestamp(),
+                event.end_time.timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+    
+    // The shortest event covering the given time, so a specific meeting wins over a long block
+    pub fn get_calendar_event_at(&self, time: &DateTime<Local>) -> Result<Option<CalendarEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT uid, summary, start_time, end_time, location FROM calendar_events
+            WHERE start_epoch <= ?1 AND end_epoch > ?1"
+        )?;
+        let events = stmt.query_map([time.timestamp()], |row| {
+            let start_time: String = row.get(2)?;
+            let end_time: String = row.get(3)?;
+            let location: Option<String> = row.get(4)?;
+            Ok(CalendarEvent {
+                uid: row.get(0)?,
+                summary: row.get(1)?,
+                start_time: Self::parse_time(&start_time).unwrap_or_else(Local::now),
+                end_time: Self::parse_time(&end_time).unwrap_or_else(Local::now),
+                location: location.filter(|l| !l.is_empty()),
+            })
+        })?;
+        
+        let mut best: Option<CalendarEvent> = None;
+        for event in events {
+            let event = event?;
+            let length = event.end_time - event.start_time;
+            if best.as_ref().map(|b| length < b.end_time - b.start_time).unwrap_or(true) {
+                best = Some(event);
+            }
+        }
+        Ok(best)
+    }
+    
+    pub fn save_gap(&self, gap: &Gap) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO gaps (start_time, end_time, reason, start_epoch, end_epoch) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                gap.start_time.to_rfc3339(),
+                gap.end_time.to_rfc3339(),
+                gap.reason,
+                gap.start_time.timestamp(),
+                gap.end_time.timestamp(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    // Gaps overlapping the range, so one that began before `from` is included
+    pub fn get_gaps(&self, from: &DateTime<Local>, to: &DateTime<Local>) -> Result<Vec<Gap>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, start_time, end_time, reason FROM gaps
+            WHERE end_epoch > ?1 AND start_epoch < ?2 ORDER BY start_epoch"
+        )?;
+        let gaps = stmt.query_map([from.timestamp(), to.timestamp()], |row| {
+            let start_time: String = row.get(1)?;
+            let end_time: String = row.get(2)?;
+            Ok(Gap {
+                id: Some(row.get(0)?),
+                start_time: Self::parse_time(&start_time).unwrap_or_else(Local::now),
+                end_time: Self::parse_time(&end_time).unwrap_or_else(Local::now),
+                reason: row.get(3)?,
+            })
+        })?;
+        gaps.collect()
+    }
+    
+    pub fn save_heartbeat(&self, heartbeat: &Heartbeat) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO heartbeat (id, beat_time, app, browser_url, window_title, git_repo, git_branch, command, span_start, paused_since, paused_until)
+            VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            [
+                Some(heartbeat.time.to_rfc3339()),
+                heartbeat.app.clone(),
+                heartbeat.browser_url.clone(),
+                heartbeat.window_title.clone(),
+                heartbeat.git_repo.clone(),
+                heartbeat.git_branch.clone(),
+                heartbeat.command.clone(),
+                heartbeat.span_start.map(|t| t.to_rfc3339()),
+                heartbeat.paused_since.map(|t| t.to_rfc3339()),
+                heartbeat.paused_until.map(|t| t.to_rfc3339()),
+            ],
+        )?;
+        Ok(())
+    }
+    
+    pub fn get_heartbeat(&self) -> Result<Option<Heartbeat>> {
+        let mut stmt = self.conn.prepare("SELECT beat_time, app, browser_url, span_start, window_title, git_repo, git_branch, command, paused_since, paused_until FROM heartbeat WHERE id = 1")?;
+        let mut rows = stmt.query_map([], |row| {
+            let time: String = row.get(0)?;
+            let app: Option<String> = row.get(1)?;
+            let browser_url: Option<String> = row.get(2)?;
+            let span_start: Option<String> = row.get(3)?;
+            let window_title: Option<String> = row.get(4)?;
+            let git_repo: Option<String> = row.get(5)?;
+            let git_branch: Option<String> = row.get(6)?;
+            let command: Option<String> = row.get(7)?;
+            let paused_since: Option<String> = row.get(8)?;
+            let paused_until: Option<String> = row.get(9)?;
+            Ok(Heartbeat {
+                time: Self::parse_time(&time).unwrap_or_else(Local::now),
+                app: app.filter(|a| !a.is_empty()),
+                browser_url,
+                window_title,
+                git_repo,
+                git_branch,
+                command,
+                span_start: span_start.as_deref().and_then(Self::parse_time),
+                paused_since: paused_since.as_deref().and_then(Self::parse_time),
+                paused_until: paused_until.as_deref().and_then(Self::parse_time),
+            })
+        })?;
+        rows.next().transpose()
+    }
+    
+    // Activity names and notes, window titles, page addresses and commands as stored, for the
+    // privacy audit. Imported rows carry whatever the other tool had in their names and notes.
+    pub fn get_stored_texts(&self) -> Result<Vec<StoredText>> {
+        let mut texts = Vec::new();
+        {
+            let mut stmt = self.conn.prepare(
+                "SELECT id, name, category, window_title, url, command, notes FROM activities ORDER BY id"
+            )?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let (id, app, category): (i64, String, String) = (row.get(0)?, row.get(1)?, row.get(2)?);
+                texts.push(StoredText { table: "activities", column: "name", id, app: app.clone(), category: Some(category.clone()), value: app.clone() });
+                for (index, column) in [(3, "window_title"), (4, "url"), (5, "command"), (6, "notes")] {
+                    let value: Option<String> = row.get(index)?;
+                    if let Some(value) = value.filter(|v| !v.is_empty()) {
+                        texts.push(StoredText { table: "activities", column, id, app: app.clone(), category: Some(category.clone()), value });
+                    }
+                }
+            }
+        }
+        if let Some(heartbeat) = self.get_heartbeat()? {
+            let app = heartbeat.app.unwrap_or_default();
+            for (column, value) in [("browser_url", heartbeat.browser_url), ("window_title", heartbeat.window_title), ("command", heartbeat.command)] {
+                if let Some(value) = value.filter(|v| !v.is_empty()) {
+                    texts.push(StoredText { table: "heartbeat", column, id: 1, app: app.clone(), category: None, value });
+                }
+            }
+        }
+        let mut stmt = self.conn.prepare("SELECT id, command FROM shell_commands ORDER BY id")?;
+        let rows = stmt.query_map([], |row| Ok(StoredText {
+            table: "shell_commands",
+            column: "command",
+            id: row.get(0)?,
+            app: String::new(),
+            category: None,
+            value: row.get(1)?,
+        }))?;
+        for row in rows {
+            texts.push(row?);
+        }
+        Ok(texts)
+    }
+    
+    pub fn clear_heartbeat(&self) -> Result<()> {
+        self.conn.execute("DELETE FROM heartbeat", [])?;
+        Ok(())
+    }
+    
+    pub fn save_shell_command(&self, command: &ShellCommand) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO shell_commands (command, cwd, start_time, end_time, exit_status, shell_pid)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            [
+                Some(command.command.clone()),
+                Some(command.cwd.clone()),
+                Some(command.start_time.to_rfc3339()),
+                Some(command.end_time.to_rfc3339()),
+                command.exit_status.map(|s| s.to_string()),
+                Some(command.shell_pid.to_string()),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    pub fn save_focus_session(&self, session: &FocusSession) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO focus_sessions (name, start_time, end_time, work_seconds, break_seconds,
+                planned_cycles, completed_cycles, interruptions, interrupted_by, completed, start_epoch)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            [
+                &session.name,
+                &session.start_time.to_rfc3339(),
+                &session.end_time.to_rfc3339(),
+                &session.work_length.as_secs().to_string(),
+                &session.break_length.as_secs().to_string(),
+                &session.planned_cycles.to_string(),
+                &session.completed_cycles.to_string(),
+                &session.interruptions.to_string(),
+                &serde_json::to_string(&session.interrupted_by).unwrap_or_default(),
+                &(if session.completed { 1 } else { 0 }).to_string(),
+                &session.start_time.timestamp().to_string(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    // Most recent first
+    pub fn get_focus_sessions(&self, limit: usize) -> Result<Vec<FocusSession>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, start_time, end_time, work_seconds, break_seconds, planned_cycles,
+                completed_cycles, interruptions, interrupted_by, completed
+            FROM focus_sessions ORDER BY start_epoch DESC LIMIT ?1"
+        )?;
+        let sessions = stmt.query_map([limit as i64], |row| {
+            let start_time: String = row.get(2)?;
+            let end_time: String = row.get(3)?;
+            let work_seconds: i64 = row.get(4)?;
+            let break_seconds: i64 = row.get(5)?;
+            let interrupted_by: String = row.get(9)?;
+            let completed: i64 = row.get(10)?;
+            Ok(FocusSession {
+                id: Some(row.get(0)?),
+                name: row.get(1)?,
+                start_time: Self::parse_time(&start_time).unwrap_or_else(Local::now),
+                end_time: Self::parse_time(&end_time).unwrap_or_else(Local::now),
+                work_length: Duration::from_secs(work_seconds.max(0) as u64),
+                break_length: Duration::from_secs(break_seconds.max(0) as u64),
+                planned_cycles: row.get(6)?,
+                completed_cycles: row.get(7)?,
+                interruptions: row.get(8)?,
+                interrupted_by: serde_json::from_str(&interrupted_by).unwrap_or_default(),
+                completed: completed != 0,
+            })
+        })?;
+        sessions.collect()
+    }
+    
+    pub fn save_alert(&self, alert: &DistractionAlert) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO alerts (target, kind, budget_seconds, spent_seconds, app, triggered_at, triggered_epoch)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            [
+                &alert.target,
+                alert.kind.as_str(),
+                &alert.budget.as_secs().to_string(),
+                &alert.spent.as_secs().to_string(),
+                &alert.app,
+                &alert.triggered_at.to_rfc3339(),
+                &alert.triggered_at.timestamp().to_string(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    // Oldest first
+    pub fn get_alerts(&self, from: &DateTime<Local>, to: &DateTime<Local>) -> Result<Vec<DistractionAlert>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, target, kind, budget_seconds, spent_seconds, app, triggered_at FROM alerts
+            WHERE triggered_epoch >= ?1 AND triggered_epoch < ?2 ORDER BY triggered_epoch"
+        )?;
+        let alerts = stmt.query_map([from.timestamp(), to.timestamp()], |row| {
+            let kind: String = row.get(2)?;
+            let budget_seconds: i64 = row.get(3)?;
+            let spent_seconds: i64 = row.get(4)?;
+            let triggered_at: String = row.get(6)?;
+            Ok(DistractionAlert {
+                id: Some(row.get(0)?),
+                target: row.get(1)?,
+                kind: if kind == "daily" { BudgetKind::Daily } else { BudgetKind::Continuous },
+                budget: Duration::from_secs(budget_seconds.max(0) as u64),
+                spent: Duration::from_secs(spent_seconds.max(0) as u64),
+                app: row.get(5)?,
+                triggered_at: Self::parse_time(&triggered_at).unwrap_or_else(Local::now),
+            })
+        })?;
+        alerts.collect()
+    }
+    
+    pub fn save_goal(&self, goal: &Goal) -> Result<i64> {
+        let (target_kind, target) = match &goal.target {
+            GoalTarget::Category(name) => ("category", name),
+            GoalTarget::Tag(name) => ("tag", name),
+        };
+        let direction = match goal.direction {
+            GoalDirection::AtLeast => "at_least",
+            GoalDirection::AtMost => "at_most",
+        };
+        let period = match goal.period {
+            GoalPeriod::Day => "day",
+            GoalPeriod::Weekday => "weekday",
+            GoalPeriod::Week => "week",
+        };
+        self.conn.execute(
+            "INSERT INTO goals (target_kind, target, direction, seconds, period, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            [
+                target_kind,
+                target,
+                direction,
+                &goal.amount.as_secs().to_string(),
+                period,
+                &goal.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    
+    pub fn get_goals(&self) -> Result<Vec<Goal>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, target_kind, target, direction, seconds, period, created_at FROM goals ORDER BY id"
+        )?;
+        let goals = stmt.query_map([], |row| {
+            let target_kind: String = row.get(1)?;
+            let target: String = row.get(2)?;
+            let direction: String = row.get(3)?;
+            let seconds: i64 = row.get(4)?;
+            let period: String = row.get(5)?;
+            let created_at: String = row.get(6)?;
+            Ok(Goal {
+                id: Some(row.get(0)?),
+                target: if target_kind == "tag" { GoalTarget::Tag(target) } else { GoalTarget::Category(target) },
+                direction: if direction == "at_most" { GoalDirection::AtMost } else { GoalDirection::AtLeast },
+                amount: Duration::from_secs(seconds.max(0) as u64),
+                period: match period.as_str() {
+                    "weekday" => GoalPeriod::Weekday,
+                    "week" => GoalPeriod::Week,
+                    _ => GoalPeriod::Day,
+                },
+                created_at: Self::parse_time(&created_at).unwrap_or_else(Local::now),
+            })
+        })?;
+        goals.collect()
+    }
+    
+    // False when there was no such goal
+    pub fn delete_goal(&self, id: i64) -> Result<bool> {
+        Ok(self.conn.execute("DELETE FROM goals WHERE id = ?1", [id])? > 0)
+    }
+    
+    // Closes the connection, reporting any error SQLite raises while finishing up
+    pub fn close(self) -> Result<()> {
+        self.conn.close().map_err(|(_, e)| e)
+    }
+    
+    // Map a row selected in the column order used by get_activities
+    fn row_to_activity(row: &Row) -> Result<Activity> {
+        let start_time: String = row.get(3)?;
+        let end_time: Option<String> = row.get(4)?;
+        let duration: Option<i64> = row.get(5)?;
+        let tags: String = row.get(6)?;
+        let notes: Option<String> = row.get(7)?;
+        let is_productive: i64 = row.get(8)?;
+        let source: Option<String> = row.get(9)?;
+        let window_title: Option<String> = row.get(10)?;
+        let git_repo: Option<String> = row.get(11)?;
+        let git_branch: Option<String> = row.get(12)?;
+        let command: Option<String> = row.get(13)?;
+        let weight: Option<i64> = row.get(14)?;
+        let start_epoch: Option<i64> = row.get(15)?;
+        let zone: Option<String> = row.get(16)?;
+        let url: Option<String> = row.get(17)?;
+        
+        let end_time = end_time.as_deref().and_then(Self::parse_time);
+        // The text keeps sub-second precision; the epoch covers rows written by other tools
+        let start_time = Self::parse_time(&start_time)
+            .or_else(|| start_epoch.and_then(|e| DateTime::from_timestamp(e, 0)).map(|t| t.with_timezone(&Local)))
+            .unwrap_or_else(Local::now);
+        
+        Ok(Activity {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            category: row.get(2)?,
+            start_time,
+            // Running activities have no duration yet
+            duration: end_time.and(duration).map(|secs| Duration::from_secs(secs.max(0) as u64)),
+            end_time,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            notes: notes.filter(|n| !n.is_empty()),
+            is_productive: is_productive != 0,
+            source: source.filter(|s| !s.is_empty()),
+            window_title: window_title.filter(|t| !t.is_empty()),
+            url: url.filter(|u| !u.is_empty()),
+            git_repo: git_repo.filter(|r| !r.is_empty()),
+            git_branch: git_branch.filter(|b| !b.is_empty()),
+            command: command.filter(|c| !c.is_empty()),
+            weight: weight
+                .map(|w| w.clamp(MIN_WEIGHT as i64, MAX_WEIGHT as i64) as i8)
+                .unwrap_or_else(|| weight_from_flag(is_productive != 0)),
+            zone: zone.filter(|z| !z.is_empty()),
+        })
+    }
+    
+    fn parse_time(value: &str) -> Option<DateTime<Local>> {
+        DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|t| t.with_timezone(&Local))
     }
     
     // Additional methods for querying and updating activities will be added here
 }
 
+// Weight of an activity row, falling back to the productive flag for rows written without one
+// (e.g. from the sqlite3 shell), the same way `weight_from_flag` reads it
+fn weight_sql(row: &str) -> String {
+    format!(
+        "COALESCE({row}.weight, CASE WHEN {row}.is_productive != 0 THEN {} ELSE {} END)",
+        weight_from_flag(true),
+        weight_from_flag(false),
+    )
+}
+
+// Gives the activity in `row` (NEW or OLD) the next change number
+fn note_change_sql(row: &str) -> String {
+    format!(
+        "INSERT OR REPLACE INTO activity_changes (activity_id, seq)
+        VALUES ({row}.id, (SELECT COALESCE(MAX(seq), 0) + 1 FROM activity_changes));"
+    )
+}
+
+// The row's tags, or an empty list should they not be valid JSON
+fn valid_tags_sql(row: &str) -> String {
+    format!("CASE WHEN json_valid({row}.tags) THEN {row}.tags ELSE '[]' END")
+}
+
+// Version of the rollup the database was filled with; 0 when it has none yet
+fn rollup_version(conn: &Connection) -> Result<i64> {
+    let version = conn.query_row("SELECT value FROM meta WHERE key = 'rollup_version'", [], |row| row.get(0));
+    match version {
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(0),
+        version => version,
+    }
+}
+
+fn set_rollup_version(conn: &Connection, version: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO meta (key, value) VALUES ('rollup_version', ?1)
+        ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        [version],
+    )?;
+    Ok(())
+}
+
+const DROP_ROLLUP_TRIGGERS: &str = "DROP TRIGGER IF EXISTS activities_rollup_insert;
+    DROP TRIGGER IF EXISTS activities_rollup_delete;
+    DROP TRIGGER IF EXISTS activities_rollup_update;";
+
+// Seconds east of UTC that `zone` (an IANA name or offset) had at `time`; the offset `time`
+// carries when the zone is not known
+fn utc_offset(zone: &str, time: &DateTime<Local>) -> i32 {
+    match zone.parse::<DisplayZone>() {
+        Ok(display) => (display.naive_local(time) - time.naive_utc()).num_seconds() as i32,
+        Err(_) => time.offset().local_minus_utc(),
+    }
+}
+
+// Expressions for the piece of a row falling on day `n.n` after the one it started on, in the
+// wall clock it was recorded on: the day, its seconds, and whether there is such a piece. Rows
+// from before the epoch columns were filled fall back to the text timestamp read as UTC.
+// `exists` bounds `n.n` by the last day the row reaches, so the join with `rollup_days` is a
+// range lookup on its key rather than all 367 offsets per row.
+struct Piece {
+    day: String,
+    seconds: String,
+    exists: String,
+}
+
+fn piece_sql(row: &str) -> Piece {
+    let start = format!(
+        "(COALESCE({row}.start_epoch, CAST(strftime('%s', {row}.start_time) AS INTEGER)) + COALESCE({row}.utc_offset, 0))"
+    );
+    let end = format!("({start} + COALESCE({row}.duration, 0))");
+    let first_day = format!("({start} - {start} % 86400)");
+    let day_start = format!("({first_day} + n.n * 86400)");
+    Piece {
+        day: format!("date({day_start}, 'unixepoch')"),
+        seconds: format!("(MIN({end}, {day_start} + 86400) - MAX({start}, {day_start}))"),
+        exists: format!("n.n <= MAX(0, ({end} - 1 - {first_day}) / 86400)"),
+    }
+}
+
+// Activities are counted once, on the day they started; their time goes to every day they cover
+fn fill_daily_summaries(conn: &Connection) -> Result<usize> {
+    let piece = piece_sql("a");
+    conn.execute(&format!(
+        "INSERT INTO daily_summaries (day, category, app, tag, seconds, activities, weighted_seconds, productive_seconds, unproductive_seconds)
+        SELECT day, category, name, tag,
+            SUM(seconds), SUM(first), SUM(weight * seconds),
+            SUM(CASE WHEN weight > 0 THEN seconds ELSE 0 END),
+            SUM(CASE WHEN weight < 0 THEN seconds ELSE 0 END)
+        FROM (
+            SELECT a.id, n.n, {day} AS day, a.category, a.name, '' AS tag, {seconds} AS seconds, n.n = 0 AS first, {weight} AS weight
+            FROM activities a, rollup_days n WHERE {exists}
+            UNION
+            SELECT a.id, n.n, {day}, a.category, a.name, t.value, {seconds}, n.n = 0, {weight}
+            FROM activities a, rollup_days n, json_each({tags}) t WHERE {exists}
+        )
+        GROUP BY day, category, name, tag",
+        day = piece.day,
+        seconds = piece.seconds,
+        exists = piece.exists,
+        weight = weight_sql("a"),
+        tags = valid_tags_sql("a"),
+    ), [])
+}
+
+// Statements for a trigger body adding (sign 1) or removing (sign -1) one row's contribution
+fn rollup_sql(row: &str, sign: i64) -> String {
+    let Piece { day, seconds, exists } = piece_sql(row);
+    let weight = weight_sql(row);
+    format!(
+        "INSERT INTO daily_summaries (day, category, app, tag, seconds, activities, weighted_seconds, productive_seconds, unproductive_seconds)
+        SELECT {day}, {row}.category, {row}.name, t.tag,
+            {sign} * {seconds}, {sign} * (n.n = 0), {sign} * {weight} * {seconds},
+            {sign} * CASE WHEN {weight} > 0 THEN {seconds} ELSE 0 END,
+            {sign} * CASE WHEN {weight} < 0 THEN {seconds} ELSE 0 END
+        FROM rollup_days n, (SELECT '' AS tag UNION SELECT value FROM json_each({tags})) t WHERE {exists}
+        ON CONFLICT (day, category, app, tag) DO UPDATE SET
+            seconds = seconds + excluded.seconds,
+            activities = activities + excluded.activities,
+            weighted_seconds = weighted_seconds + excluded.weighted_seconds,
+            productive_seconds = productive_seconds + excluded.productive_seconds,
+            unproductive_seconds = unproductive_seconds + excluded.unproductive_seconds;
+        DELETE FROM daily_summaries WHERE activities <= 0 AND seconds <= 0
+            AND day IN (SELECT {day} FROM rollup_days n WHERE {exists});",
+        tags = valid_tags_sql(row),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::{at, finished};
+    
+    type SummaryRow = (String, String, String, String, i64, i64, i64, i64, i64);
+    
+    fn activity(name: &str, tags: &[&str], start: DateTime<Local>, end: DateTime<Local>) -> Activity {
+        finished(start, end).name(name).tags(tags).build()
+    }
+    
+    fn summary_rows(db: &Database) -> Vec<SummaryRow> {
+        let mut stmt = db.conn.prepare(
+            "SELECT day, category, app, tag, seconds, activities, weighted_seconds, productive_seconds, unproductive_seconds
+            FROM daily_summaries ORDER BY day, category, app, tag"
+        ).unwrap();
+        let rows = stmt.query_map([], |row| Ok((
+            row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?,
+            row.get(5)?, row.get(6)?, row.get(7)?, row.get(8)?,
+        ))).unwrap();
+        rows.collect::<Result<_>>().unwrap()
+    }
+    
+    // What the triggers left must be what a rebuild from scratch writes
+    fn assert_rollup_matches_rebuild(db: &Database) -> Vec<SummaryRow> {
+        let kept = summary_rows(db);
+        db.rebuild_daily_summaries().unwrap();
+        assert_eq!(kept, summary_rows(db));
+        kept
+    }
+    
+    #[test]
+    fn rollup_follows_insert_update_and_delete() {
+        let db = Database::open_in_memory().unwrap();
+        let mut first = activity("cargo", &["rust"], at(2, 9, 0), at(2, 10, 0));
+        first.id = Some(db.save_activity(&first).unwrap());
+        let second = activity("vim", &[], at(2, 11, 0), at(2, 11, 30));
+        let second_id = db.save_activity(&second).unwrap();
+        assert_rollup_matches_rebuild(&db);
+        
+        first.category = "Writing".to_string();
+        first.set_weight(-2);
+        first.tags = vec!["docs".to_string()];
+        db.update_activity(&first).unwrap();
+        assert_rollup_matches_rebuild(&db);
+        
+        db.conn.execute("DELETE FROM activities WHERE id = ?1", [second_id]).unwrap();
+        let rows = assert_rollup_matches_rebuild(&db);
+        assert!(rows.iter().all(|row| row.2 != "vim"));
+    }
+    
+    #[test]
+    fn changes_are_numbered_per_activity() {
+        let db = Database::open_in_memory().unwrap();
+        let mut first = activity("cargo", &[], at(2, 9, 0), at(2, 10, 0));
+        first.id = Some(db.save_activity(&first).unwrap());
+        let second_id = db.save_activity(&activity("vim", &[], at(2, 11, 0), at(2, 11, 30))).unwrap();
+        let seen = db.last_activity_change().unwrap();
+        assert_eq!(db.activity_change(second_id).unwrap(), Some(seen));
+        
+        first.tags = vec!["rust".to_string()];
+        db.update_activity(&first).unwrap();
+        db.update_activity(&first).unwrap();
+        db.conn.execute("DELETE FROM activities WHERE id = ?1", [second_id]).unwrap();
+        
+        let changes: Vec<(i64, Option<Vec<String>>)> = db.get_activity_changes(seen).unwrap()
+            .into_iter()
+            .map(|c| (c.id, c.activity.map(|a| a.tags)))
+            .collect();
+        assert_eq!(changes, [(first.id.unwrap(), Some(vec!["rust".to_string()])), (second_id, None)]);
+        assert!(db.get_activity_changes(db.last_activity_change().unwrap()).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn rollup_follows_stopping_an_open_activity() {
+        let db = Database::open_in_memory().unwrap();
+        let mut running = Activity::new("cargo".to_string(), "Development".to_string(), vec!["rust".to_string()], true);
+        running.start_time = Local::now() - chrono::Duration::hours(2);
+        running.zone = Some("UTC".to_string());
+        running.id = Some(db.save_activity(&running).unwrap());
+        assert_rollup_matches_rebuild(&db);
+        
+        let mut open = db.get_open_activity().unwrap().unwrap();
+        open.stop();
+        db.update_activity(&open).unwrap();
+        let rows = assert_rollup_matches_rebuild(&db);
+        let seconds: i64 = rows.iter().filter(|row| row.3.is_empty()).map(|row| row.4).sum();
+        assert!((7199..=7201).contains(&seconds));
+    }
+    
+    #[test]
+    fn rollup_splits_spans_crossing_midnight() {
+        let db = Database::open_in_memory().unwrap();
+        db.save_activity(&activity("cargo", &[], at(2, 23, 0), at(3, 1, 30))).unwrap();
+        let rows = assert_rollup_matches_rebuild(&db);
+        let days: Vec<(&str, i64, i64)> = rows.iter().map(|row| (row.0.as_str(), row.4, row.5)).collect();
+        assert_eq!(days, [("2026-03-02", 3600, 1), ("2026-03-03", 5400, 0)]);
+    }
+    
+    #[test]
+    fn recorded_day_split_matches_rollup() {
+        let db = Database::open_in_memory().unwrap();
+        // 23:30 to 01:00 in Tokyo and 22:00 to 00:45 in New York
+        let mut tokyo = activity("cargo", &[], at(2, 14, 30), at(2, 16, 0));
+        tokyo.zone = Some("+09:00".to_string());
+        let mut new_york = activity("vim", &[], at(3, 3, 0), at(3, 5, 45));
+        new_york.zone = Some("America/New_York".to_string());
+        db.save_activity(&tokyo).unwrap();
+        db.save_activity(&new_york).unwrap();
+        
+        let activities = db.get_activities(&ActivityFilter::default()).unwrap();
+        for day in 1..=4 {
+            let date = NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+            let rollup: u64 = db.get_daily_summaries(date, date, None).unwrap().iter()
+                .filter(|row| row.tag.is_none())
+                .map(|row| row.seconds)
+                .sum();
+            let split: u64 = crate::reports::split_by_recorded_day(&activities, date, date).iter()
+                .map(|piece| piece.duration.unwrap().as_secs())
+                .sum();
+            assert_eq!(split, rollup, "on {}", date);
+        }
+        // Both ran past their own midnight, neither past UTC's
+        let on_third = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        assert_eq!(crate::reports::split_by_recorded_day(&activities, on_third, on_third).len(), 2);
+    }
+    
+    #[test]
+    fn rollup_counts_duplicate_tags_once() {
+        let db = Database::open_in_memory().unwrap();
+        let id = db.save_activity(&activity("cargo", &["rust", "rust"], at(2, 9, 0), at(2, 10, 0))).unwrap();
+        let rows = assert_rollup_matches_rebuild(&db);
+        let tags: Vec<(&str, i64)> = rows.iter().map(|row| (row.3.as_str(), row.4)).collect();
+        assert_eq!(tags, [("", 3600), ("rust", 3600)]);
+        
+        db.conn.execute("DELETE FROM activities WHERE id = ?1", [id]).unwrap();
+        assert!(assert_rollup_matches_rebuild(&db).is_empty());
+    }
+    
+    #[test]
+    fn upgrade_from_text_timestamps_fills_epochs_and_rollup() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE activities (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                category TEXT NOT NULL,
+                start_time TEXT NOT NULL,
+                end_time TEXT,
+                duration INTEGER,
+                tags TEXT NOT NULL,
+                notes TEXT,
+                is_productive INTEGER NOT NULL,
+                source TEXT,
+                window_title TEXT
+            );
+            INSERT INTO activities (name, category, start_time, end_time, duration, tags, is_productive)
+            VALUES ('cargo', 'Development', '2026-03-02T23:00:00+02:00', '2026-03-03T01:00:00+02:00', 7200, '[\"rust\"]', 1),
+                ('reddit', 'Social Media', '2026-03-03T10:00:00+00:00', '2026-03-03T10:15:00+00:00', 900, '[]', 0),
+                ('Slack', 'Communication', '2026-03-03T11:00:00+00:00', '2026-03-03T11:10:00+00:00', 600, '[]', 0);
+            PRAGMA user_version = 7;",
+        ).unwrap();
+        let db = Database::open(conn).unwrap();
+        
+        let epochs: Vec<(i64, i64, i32, String)> = {
+            let mut stmt = db.conn.prepare("SELECT start_epoch, end_epoch, utc_offset, zone FROM activities ORDER BY id").unwrap();
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))).unwrap();
+            rows.collect::<Result<_>>().unwrap()
+        };
+        let start = at(2, 21, 0).timestamp();
+        assert_eq!(epochs[0], (start, start + 7200, 7200, "+02:00".to_string()));
+        
+        // Days follow the wall clock the row was written on, not UTC
+        let rows = assert_rollup_matches_rebuild(&db);
+        let days: Vec<(&str, &str, &str, i64, i64)> = rows.iter().map(|row| (row.0.as_str(), row.2.as_str(), row.3.as_str(), row.4, row.6)).collect();
+        assert_eq!(days, [
+            ("2026-03-02", "cargo", "", 3600, 7200),
+            ("2026-03-02", "cargo", "rust", 3600, 7200),
+            ("2026-03-03", "Slack", "", 600, 0),
+            ("2026-03-03", "cargo", "", 3600, 7200),
+            ("2026-03-03", "cargo", "rust", 3600, 7200),
+            ("2026-03-03", "reddit", "", 900, -1800),
+        ]);
+        
+        // The rollup version lives in its own row and leaves user_version alone
+        assert_eq!(rollup_version(&db.conn).unwrap(), ROLLUP_VERSION);
+        let user_version: i64 = db.conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(user_version, 7);
+    }
+    
+    #[test]
+    fn gaps_alerts_and_events_are_ranged_on_epochs_across_offsets() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE gaps (id INTEGER PRIMARY KEY, start_time TEXT NOT NULL, end_time TEXT NOT NULL, reason TEXT NOT NULL);
+            INSERT INTO gaps (start_time, end_time, reason)
+            VALUES ('2026-03-02T08:30:00+09:00', '2026-03-02T09:00:00+09:00', 'paused'),
+                ('2026-03-02T01:00:00+00:00', '2026-03-02T01:30:00+00:00', 'snoozed');
+            CREATE TABLE calendar_events (uid TEXT PRIMARY KEY, summary TEXT NOT NULL, start_time TEXT NOT NULL, end_time TEXT NOT NULL, location TEXT);
+            INSERT INTO calendar_events (uid, summary, start_time, end_time)
+            VALUES ('a', 'Standup', '2026-03-02T10:00:00+09:00', '2026-03-02T10:30:00+09:00');",
+        ).unwrap();
+        let db = Database::open(conn).unwrap();
+        
+        // 23:30 to 00:00 UTC on the 1st, and 01:00 to 01:30 UTC on the 2nd; as text the first sorts last
+        let gaps = db.get_gaps(&at(1, 23, 0), &at(2, 2, 0)).unwrap();
+        let reasons: Vec<&str> = gaps.iter().map(|g| g.reason.as_str()).collect();
+        assert_eq!(reasons, ["paused", "snoozed"]);
+        assert_eq!(db.get_gaps(&at(2, 0, 0), &at(2, 0, 45)).unwrap().len(), 0);
+        
+        let event = db.get_calendar_event_at(&at(2, 1, 15)).unwrap().unwrap();
+        assert_eq!(event.summary, "Standup");
+        assert!(db.get_calendar_event_at(&at(2, 10, 15)).unwrap().is_none());
+        
+        for (day, hour, spent) in [(1, 23, 60), (2, 1, 120)] {
+            db.save_alert(&DistractionAlert {
+                id: None,
+                target: "Social Media".to_string(),
+                kind: BudgetKind::Daily,
+                budget: Duration::from_secs(60),
+                spent: Duration::from_secs(spent),
+                app: "reddit".to_string(),
+                triggered_at: at(day, hour, 0),
+            }).unwrap();
+        }
+        let alerts = db.get_alerts(&at(1, 22, 0), &at(2, 0, 30)).unwrap();
+        assert_eq!(alerts.iter().map(|a| a.spent.as_secs()).collect::<Vec<_>>(), [60]);
+    }
+}
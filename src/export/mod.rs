@@ -0,0 +1,224 @@
+use crate::calendar::ics;
+use crate::data::timezone::DisplayZone;
+use crate::data::Activity;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+    // iCalendar, one VEVENT per activity, for overlaying tracked blocks on a calendar
+    Ics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DurationFormat {
+    // Whole seconds, e.g. 5400
+    Seconds,
+    // Clock style, e.g. 01:30:00
+    Hms,
+}
+
+// Exportable columns, named after the fields of `Activity`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Column {
+    Id,
+    Name,
+    Category,
+    StartTime,
+    EndTime,
+    Duration,
+    Tags,
+    Notes,
+    IsProductive,
+    Source,
+    WindowTitle,
+    Url,
+    GitRepo,
+    GitBranch,
+    Command,
+    Weight,
+    Zone,
+}
+
+impl Column {
+    pub const ALL: [Column; 17] = [
+        Column::Id,
+        Column::Name,
+        Column::Category,
+        Column::StartTime,
+        Column::EndTime,
+        Column::Duration,
+        Column::Tags,
+        Column::Notes,
+        Column::IsProductive,
+        Column::Source,
+        Column::WindowTitle,
+        Column::Url,
+        Column::GitRepo,
+        Column::GitBranch,
+        Column::Command,
+        Column::Weight,
+        Column::Zone,
+    ];
+
+    // Matches the serde field name so CSV headers and JSON keys line up
+    pub fn key(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::Name => "name",
+            Column::Category => "category",
+            Column::StartTime => "start_time",
+            Column::EndTime => "end_time",
+            Column::Duration => "duration",
+            Column::Tags => "tags",
+            Column::Notes => "notes",
+            Column::IsProductive => "is_productive",
+            Column::Source => "source",
+            Column::WindowTitle => "window_title",
+            Column::Url => "url",
+            Column::GitRepo => "git_repo",
+            Column::GitBranch => "git_branch",
+            Column::Command => "command",
+            Column::Weight => "weight",
+            Column::Zone => "zone",
+        }
+    }
+}
+
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub columns: Vec<Column>,
+    pub zone: DisplayZone,
+    pub durations: DurationFormat,
+}
+
+impl ExportOptions {
+    fn columns(&self) -> &[Column] {
+        if self.columns.is_empty() {
+            &Column::ALL
+        } else {
+            &self.columns
+        }
+    }
+}
+
+pub fn export_activities<W: Write>(activities: &[Activity], options: &ExportOptions, out: W) -> Result<(), Box<dyn Error>> {
+    match options.format {
+        ExportFormat::Csv => write_csv(activities, options, out),
+        ExportFormat::Json => {
+            let records: Vec<Value> = activities.iter()
+                .map(|a| Value::Object(to_json_record(a, options)))
+                .collect();
+            let mut out = out;
+            serde_json::to_writer_pretty(&mut out, &records)?;
+            writeln!(out)?;
+            Ok(())
+        },
+        ExportFormat::Ndjson => {
+            let mut out = out;
+            for activity in activities {
+                serde_json::to_writer(&mut out, &to_json_record(activity, options))?;
+                writeln!(out)?;
+            }
+            Ok(())
+        },
+        ExportFormat::Ics => ics::write_activities(activities, out),
+    }
+}
+
+fn write_csv<W: Write>(activities: &[Activity], options: &ExportOptions, out: W) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(out);
+    let columns = options.columns();
+
+    writer.write_record(columns.iter().map(|c| c.key()))?;
+    for activity in activities {
+        writer.write_record(columns.iter().map(|c| csv_cell(activity, *c, options)))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn csv_cell(activity: &Activity, column: Column, options: &ExportOptions) -> String {
+    match column {
+        Column::Id => activity.id.map(|id| id.to_string()).unwrap_or_default(),
+        Column::Name => activity.name.clone(),
+        Column::Category => activity.category.clone(),
+        Column::StartTime => options.zone.format(&activity.start_time),
+        Column::EndTime => activity.end_time.map(|t| options.zone.format(&t)).unwrap_or_default(),
+        Column::Duration => activity.duration
+            .map(|d| format_duration(d.as_secs(), options.durations))
+            .unwrap_or_default(),
+        Column::Tags => activity.tags.join(";"),
+        Column::Notes => activity.notes.clone().unwrap_or_default(),
+        Column::IsProductive => activity.is_productive.to_string(),
+        Column::Source => activity.source.clone().unwrap_or_default(),
+        Column::WindowTitle => activity.window_title.clone().unwrap_or_default(),
+        Column::Url => activity.url.clone().unwrap_or_default(),
+        Column::GitRepo => activity.git_repo.clone().unwrap_or_default(),
+        Column::GitBranch => activity.git_branch.clone().unwrap_or_default(),
+        Column::Command => activity.command.clone().unwrap_or_default(),
+        Column::Weight => activity.weight.to_string(),
+        Column::Zone => activity.zone.clone().unwrap_or_default(),
+    }
+}
+
+// Start from the serde representation of `Activity` so the JSON shape follows the struct,
+// then apply the requested time zone, duration format and column selection.
+fn to_json_record(activity: &Activity, options: &ExportOptions) -> Map<String, Value> {
+    let mut full = match serde_json::to_value(activity) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+
+    full.insert("start_time".to_string(), Value::String(options.zone.format(&activity.start_time)));
+    full.insert(
+        "end_time".to_string(),
+        activity.end_time.map(|t| Value::String(options.zone.format(&t))).unwrap_or(Value::Null),
+    );
+    full.insert(
+        "duration".to_string(),
+        activity.duration
+            .map(|d| match options.durations {
+                DurationFormat::Seconds => Value::from(d.as_secs()),
+                DurationFormat::Hms => Value::String(format_duration(d.as_secs(), DurationFormat::Hms)),
+            })
+            .unwrap_or(Value::Null),
+    );
+
+    let mut record = Map::new();
+    for column in options.columns() {
+        if let Some(value) = full.remove(column.key()) {
+            record.insert(column.key().to_string(), value);
+        }
+    }
+    record
+}
+
+pub fn format_duration(secs: u64, format: DurationFormat) -> String {
+    match format {
+        DurationFormat::Seconds => secs.to_string(),
+        DurationFormat::Hms => format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_columns_cover_every_activity_field() {
+        let activity = Activity::new("Code".to_string(), "Development".to_string(), Vec::new(), true);
+        let fields = match serde_json::to_value(&activity).unwrap() {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        };
+        let keys: Vec<&str> = Column::ALL.iter().map(|c| c.key()).collect();
+        assert_eq!(keys, fields.keys().map(|k| k.as_str()).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,165 @@
+// Pomodoro-style focus sessions. Each work block is tracked as a manual activity tagged "focus",
+// through the daemon when one runs and the database otherwise. While a block runs, the daemon's
+// view of the foreground app, its title and page is polled; every switch to an app or site
+// weighted below zero counts as one interruption. Without a daemon the blocks are still
+// tracked, but nothing watches for them.
+
+use crate::data::duration::format_short;
+use crate::data::{FocusSession, MAX_WEIGHT};
+use crate::ipc::{self, Request, Response};
+use crate::monitor::{AppMonitor, BrowserMonitor};
+use crate::notify::Notifier;
+use crate::rules::RuleSet;
+use crate::tracker::Tracker;
+use chrono::Local;
+use colored::*;
+use std::error::Error;
+use std::time::Duration;
+use tokio::time::Instant;
+
+// How often the foreground app is checked during a work block
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub const FOCUS_TAG: &str = "focus";
+
+pub struct FocusOptions {
+    pub name: String,
+    pub category: String,
+    pub work_length: Duration,
+    pub break_length: Duration,
+    pub cycles: u32,
+    pub notify_command: Option<String>,
+}
+
+enum BlockEnd {
+    Finished,
+    Interrupted,
+}
+
+struct Distractions {
+    rules: RuleSet,
+    // Whether the last poll saw a distracting app, so staying on it counts once
+    distracted: bool,
+    count: u32,
+    // Apps, or site hosts for browsers, in the order they first distracted
+    sources: Vec<String>,
+}
+
+impl Distractions {
+    // Browsers are judged by the page they show, so a site counts under its host name
+    fn observe(&mut self, app_name: &str, window_title: Option<&str>, url: Option<&str>) {
+        if app_name.is_empty() || app_name == "Unknown" {
+            return;
+        }
+        let (_, weight) = AppMonitor::classify(&self.rules, app_name, window_title, url, None);
+        let distracting = weight < 0;
+        if distracting && !self.distracted {
+            let source = url
+                .and_then(BrowserMonitor::site_host)
+                .unwrap_or_else(|| app_name.to_string());
+            self.count += 1;
+            if !self.sources.contains(&source) {
+                self.sources.push(source.clone());
+            }
+            println!("{} {}", "Distraction:".yellow(), source);
+        }
+        self.distracted = distracting;
+    }
+}
+
+// Runs the whole session in the foreground; Ctrl+C ends it early. The session is saved either
+// way, also when starting or stopping a block fails part way through.
+pub async fn run(options: FocusOptions) -> Result<FocusSession, Box<dyn Error>> {
+    let notifier = Notifier::new(options.notify_command.clone());
+    let watching = matches!(ipc::send(&Request::Status), Some(Ok(Response::Status { .. })));
+    if !watching {
+        println!("{}", "No daemon is running; interruptions will not be detected".yellow());
+    }
+
+    let mut distractions = Distractions {
+        rules: RuleSet::load_or_warn(),
+        distracted: false,
+        count: 0,
+        sources: Vec::new(),
+    };
+    let mut session = FocusSession {
+        id: None,
+        name: options.name.clone(),
+        start_time: Local::now(),
+        end_time: Local::now(),
+        work_length: options.work_length,
+        break_length: options.break_length,
+        planned_cycles: options.cycles,
+        completed_cycles: 0,
+        interruptions: 0,
+        interrupted_by: Vec::new(),
+        completed: false,
+    };
+
+    let outcome = run_blocks(&options, &notifier, watching, &mut distractions, &mut session).await;
+
+    session.end_time = Local::now();
+    session.interruptions = distractions.count;
+    session.interrupted_by = distractions.sources;
+    let saved = Tracker::new().and_then(|tracker| tracker.database().save_focus_session(&session));
+    outcome?;
+    session.id = Some(saved?);
+    Ok(session)
+}
+
+async fn run_blocks(options: &FocusOptions, notifier: &Notifier, watching: bool, distractions: &mut Distractions, session: &mut FocusSession) -> Result<(), Box<dyn Error>> {
+    for cycle in 1..=options.cycles {
+        println!("{} {} of {}: {} ({})", "Focus block".green(), cycle, options.cycles, options.name, format_short(options.work_length));
+        ipc::start_activity(options.name.clone(), options.category.clone(), vec![FOCUS_TAG.to_string()], MAX_WEIGHT)?;
+        distractions.distracted = false;
+        let end = wait(options.work_length, watching.then_some(&mut *distractions)).await;
+        ipc::stop_activity()?;
+        if let BlockEnd::Interrupted = end {
+            println!("{}", "Focus session stopped".yellow());
+            break;
+        }
+        session.completed_cycles = cycle;
+
+        if cycle == options.cycles {
+            session.completed = true;
+            notify(notifier, "Focus session complete", &format!("{} work blocks of {} done", cycle, format_short(options.work_length)));
+            break;
+        }
+        notify(notifier, &format!("Work block {} of {} done", cycle, options.cycles), &format!("Take a {} break", format_short(options.break_length)));
+        println!("{} ({})", "Break".cyan(), format_short(options.break_length));
+        if let BlockEnd::Interrupted = wait(options.break_length, None).await {
+            println!("{}", "Focus session stopped".yellow());
+            break;
+        }
+        notify(notifier, "Break over", &format!("Back to {} for block {} of {}", options.name, cycle + 1, options.cycles));
+    }
+    Ok(())
+}
+
+// Sleeps for `length`, polling the daemon's foreground app along the way when asked to
+async fn wait(length: Duration, mut distractions: Option<&mut Distractions>) -> BlockEnd {
+    let deadline = Instant::now() + length;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return BlockEnd::Finished;
+        }
+        let nap = (deadline - now).min(POLL_INTERVAL);
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => return BlockEnd::Interrupted,
+            _ = tokio::time::sleep(nap) => {},
+        }
+        if let Some(distractions) = distractions.as_deref_mut() {
+            if let Some(Ok(Response::Status { status })) = ipc::send(&Request::Status) {
+                distractions.observe(&status.foreground_app, status.foreground_title.as_deref(), status.foreground_url.as_deref());
+            }
+        }
+    }
+}
+
+fn notify(notifier: &Notifier, title: &str, body: &str) {
+    println!("{} {}", title.bold(), body);
+    if !notifier.send(title, body) {
+        eprintln!("Could not show a desktop notification; set {} to run a command instead", crate::notify::HOOK_ENV);
+    }
+}
@@ -0,0 +1,318 @@
+// Daily and weekly time goals, e.g. at least 4h of Development per weekday or at most 45m of
+// Social Media per day. Progress is computed from the daily rollup whenever it is needed, so
+// goals also apply to time tracked before they were set; streaks only start at creation.
+
+use crate::data::duration::format_short;
+use crate::data::{Activity, DailySummary, Goal, GoalDirection, GoalPeriod, GoalTarget};
+use crate::db::Database;
+use crate::notify::Notifier;
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+// Longest streak that gets counted, which bounds how much history is read
+pub const STREAK_LOOKBACK_DAYS: i64 = 366;
+
+// Limits are checked this often by the daemon rather than on every sample
+const WATCH_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DayMark {
+    Met,
+    Missed,
+    // Today, with a minimum not reached yet
+    InProgress,
+    // A weekend for a weekday goal, a day before the goal existed, or a day still to come
+    Skipped,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    pub description: String,
+    // Time counted in the current day, or the current week for weekly goals
+    pub spent: Duration,
+    pub met: bool,
+    // Consecutive days (weeks for weekly goals) the goal was met, up to now
+    pub streak: u32,
+    // Monday to Sunday of the current week; only for daily goals
+    pub week: Vec<(NaiveDate, DayMark)>,
+}
+
+pub fn describe(goal: &Goal) -> String {
+    let target = match &goal.target {
+        GoalTarget::Category(name) => name.clone(),
+        GoalTarget::Tag(name) => format!("tagged {}", name),
+    };
+    let direction = match goal.direction {
+        GoalDirection::AtLeast => "at least",
+        GoalDirection::AtMost => "at most",
+    };
+    let period = match goal.period {
+        GoalPeriod::Day => "day",
+        GoalPeriod::Weekday => "weekday",
+        GoalPeriod::Week => "week",
+    };
+    format!("{} {} {} per {}", target, direction, format_short(goal.amount), period)
+}
+
+pub fn matches(goal: &Goal, activity: &Activity) -> bool {
+    match &goal.target {
+        GoalTarget::Category(name) => activity.category.eq_ignore_ascii_case(name),
+        GoalTarget::Tag(name) => activity.tags.iter().any(|t| t == name),
+    }
+}
+
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+fn applies_on(period: GoalPeriod, date: NaiveDate) -> bool {
+    match period {
+        GoalPeriod::Weekday => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+        GoalPeriod::Day | GoalPeriod::Week => true,
+    }
+}
+
+fn is_met(goal: &Goal, spent: Duration) -> bool {
+    match goal.direction {
+        GoalDirection::AtLeast => spent >= goal.amount,
+        GoalDirection::AtMost => spent <= goal.amount,
+    }
+}
+
+// Category goals count the rows without a tag, which hold every activity once
+fn counts(goal: &Goal, row: &DailySummary) -> bool {
+    match (&goal.target, &row.tag) {
+        (GoalTarget::Category(name), None) => row.category.eq_ignore_ascii_case(name),
+        (GoalTarget::Tag(name), Some(tag)) => tag == name,
+        _ => false,
+    }
+}
+
+// Seconds per day for one goal, from the rollup. The rollup has no time yet for the activity
+// still running, which counts up to now on the day it started.
+fn daily_seconds(goal: &Goal, rows: &[DailySummary], running: Option<&Activity>) -> HashMap<NaiveDate, u64> {
+    let mut days = HashMap::new();
+    for row in rows.iter().filter(|r| counts(goal, r)) {
+        *days.entry(row.date).or_insert(0) += row.seconds;
+    }
+    if let Some(activity) = running.filter(|a| a.end_time.is_none() && matches(goal, a)) {
+        let seconds = (Local::now() - activity.start_time).num_seconds().max(0) as u64;
+        *days.entry(activity.start_time.date_naive()).or_insert(0) += seconds;
+    }
+    days
+}
+
+// Progress of `goals` on `today`, with rollup rows reaching back far enough for full streaks
+pub fn load_progress(db: &Database, goals: &[Goal], today: NaiveDate) -> Result<Vec<GoalProgress>, Box<dyn Error>> {
+    let rows = db.get_daily_summaries(today - chrono::Duration::days(STREAK_LOOKBACK_DAYS + 7), today, None)?;
+    let running = db.get_open_activity()?;
+    Ok(progress(goals, &rows, running.as_ref(), today))
+}
+
+// `rows` should reach back STREAK_LOOKBACK_DAYS before `today` for full streaks
+pub fn progress(goals: &[Goal], rows: &[DailySummary], running: Option<&Activity>, today: NaiveDate) -> Vec<GoalProgress> {
+    goals.iter().map(|goal| {
+        let days = daily_seconds(goal, rows, running);
+        let on = |date: NaiveDate| Duration::from_secs(days.get(&date).copied().unwrap_or(0));
+        let created = goal.created_at.date_naive();
+
+        match goal.period {
+            GoalPeriod::Week => {
+                let week_total = |start: NaiveDate| -> Duration {
+                    (0..7).map(|i| on(start + chrono::Duration::days(i))).sum()
+                };
+                let current = week_start(today);
+                let spent = week_total(current);
+
+                // The week in progress counts once met; only a crossed limit breaks the streak early
+                let mut streak = if is_met(goal, spent) { 1 } else { 0 };
+                if streak > 0 || goal.direction == GoalDirection::AtLeast {
+                    let mut start = current;
+                    loop {
+                        start -= chrono::Duration::days(7);
+                        let counted = start + chrono::Duration::days(6) >= created
+                            && (today - start).num_days() <= STREAK_LOOKBACK_DAYS
+                            && is_met(goal, week_total(start));
+                        if !counted {
+                            break;
+                        }
+                        streak += 1;
+                    }
+                }
+
+                GoalProgress {
+                    goal: goal.clone(),
+                    description: describe(goal),
+                    spent,
+                    met: is_met(goal, spent),
+                    streak,
+                    week: Vec::new(),
+                }
+            },
+            GoalPeriod::Day | GoalPeriod::Weekday => {
+                let spent = on(today);
+                let mark = |date: NaiveDate| -> DayMark {
+                    if date > today || date < created || !applies_on(goal.period, date) {
+                        DayMark::Skipped
+                    } else if is_met(goal, on(date)) {
+                        DayMark::Met
+                    } else if date == today && goal.direction == GoalDirection::AtLeast {
+                        DayMark::InProgress
+                    } else {
+                        DayMark::Missed
+                    }
+                };
+
+                let mut streak = 0;
+                let mut date = today;
+                while (today - date).num_days() <= STREAK_LOOKBACK_DAYS && date >= created {
+                    match mark(date) {
+                        DayMark::Met => streak += 1,
+                        DayMark::Missed => break,
+                        DayMark::InProgress | DayMark::Skipped => {},
+                    }
+                    date = match date.pred_opt() {
+                        Some(previous) => previous,
+                        None => break,
+                    };
+                }
+
+                let monday = week_start(today);
+                GoalProgress {
+                    goal: goal.clone(),
+                    description: describe(goal),
+                    spent,
+                    met: is_met(goal, spent),
+                    streak,
+                    week: (0..7).map(|i| monday + chrono::Duration::days(i)).map(|d| (d, mark(d))).collect(),
+                }
+            },
+        }
+    }).collect()
+}
+
+// Used by the daemon to notify once per day (or week) when a limit goal is crossed
+pub struct GoalWatcher {
+    notifier: Notifier,
+    last_check: Option<Instant>,
+    // Goal id and the start of the period it was last reported for
+    notified: HashMap<i64, NaiveDate>,
+}
+
+impl GoalWatcher {
+    pub fn new() -> Self {
+        Self {
+            notifier: Notifier::new(None),
+            last_check: None,
+            notified: HashMap::new(),
+        }
+    }
+
+    // Goals are read again on every check, so ones added while the daemon runs are picked up
+    pub fn check(&mut self, db: &Database) -> Result<(), Box<dyn Error>> {
+        if self.last_check.map(|t| t.elapsed() < WATCH_INTERVAL).unwrap_or(false) {
+            return Ok(());
+        }
+        self.last_check = Some(Instant::now());
+
+        let today = Local::now().date_naive();
+        let limits: Vec<Goal> = db.get_goals()?
+            .into_iter()
+            .filter(|g| g.direction == GoalDirection::AtMost && applies_on(g.period, today))
+            .collect();
+        if limits.is_empty() {
+            return Ok(());
+        }
+        let rows = db.get_daily_summaries(week_start(today), today, None)?;
+        let running = db.get_open_activity()?;
+
+        for goal in &limits {
+            let days = daily_seconds(goal, &rows, running.as_ref());
+            let (period_start, spent) = match goal.period {
+                GoalPeriod::Week => {
+                    let start = week_start(today);
+                    (start, days.iter().filter(|(day, _)| **day >= start).map(|(_, secs)| secs).sum::<u64>())
+                },
+                GoalPeriod::Day | GoalPeriod::Weekday => (today, days.get(&today).copied().unwrap_or(0)),
+            };
+            let spent = Duration::from_secs(spent);
+            let id = goal.id.unwrap_or_default();
+            if is_met(goal, spent) || self.notified.get(&id) == Some(&period_start) {
+                continue;
+            }
+            self.notified.insert(id, period_start);
+
+            let title = format!("Goal limit crossed: {}", describe(goal));
+            let body = format!("{} so far {}", format_short(spent), if goal.period == GoalPeriod::Week { "this week" } else { "today" });
+            println!("{} - {}", title, body);
+            if !self.notifier.send(&title, &body) {
+                eprintln!("Could not show a desktop notification for the goal");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::timezone::DisplayZone;
+
+    fn row(date: NaiveDate, category: &str, tag: Option<&str>, seconds: u64) -> DailySummary {
+        DailySummary {
+            date,
+            category: category.to_string(),
+            app: "Code".to_string(),
+            tag: tag.map(|t| t.to_string()),
+            seconds,
+            activities: 1,
+            weighted_seconds: 0,
+            productive_seconds: 0,
+            unproductive_seconds: 0,
+        }
+    }
+
+    fn goal(target: GoalTarget, direction: GoalDirection, period: GoalPeriod, hours: u64, created: NaiveDate) -> Goal {
+        Goal {
+            id: Some(1),
+            target,
+            direction,
+            amount: Duration::from_secs(hours * 3600),
+            period,
+            created_at: DisplayZone::Local.start_of_day(created),
+        }
+    }
+
+    #[test]
+    fn progress_reads_daily_rollup_rows() {
+        // A Wednesday, so the week began on the Monday two days before
+        let today = NaiveDate::from_ymd_opt(2026, 3, 4).unwrap();
+        let day = |offset: i64| today - chrono::Duration::days(offset);
+        let rows = [
+            row(day(2), "Development", None, 4 * 3600),
+            row(day(2), "Development", Some("rust"), 4 * 3600),
+            row(day(1), "development", None, 5 * 3600),
+            row(day(0), "Development", None, 3600),
+            row(day(3), "Development", None, 4 * 3600),
+            row(day(1), "Writing", Some("rust"), 3600),
+        ];
+        // Still running since noon, so far more than enough for today
+        let mut running = Activity::new("Code".to_string(), "Development".to_string(), Vec::new(), true);
+        running.start_time = DisplayZone::Local.start_of_day(today) + chrono::Duration::hours(12);
+
+        let daily = goal(GoalTarget::Category("Development".to_string()), GoalDirection::AtLeast, GoalPeriod::Day, 4, day(30));
+        let weekly = goal(GoalTarget::Tag("rust".to_string()), GoalDirection::AtMost, GoalPeriod::Week, 4, day(30));
+        let progress = progress(&[daily, weekly], &rows, Some(&running), today);
+
+        // Today, the two days before it and the Sunday before those
+        assert!(progress[0].met);
+        assert_eq!(progress[0].streak, 4);
+        assert_eq!(progress[1].spent.as_secs(), 5 * 3600);
+        assert!(!progress[1].met);
+    }
+}
@@ -0,0 +1,346 @@
+use super::{build_activity, source_marker};
+use crate::data::Activity;
+use crate::monitor::{AppMonitor, BrowserMonitor};
+use crate::rules::RuleSet;
+use chrono::{DateTime, Local, Utc};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::error::Error;
+use std::ops::Range;
+use std::path::Path;
+
+// Pieces shorter than this are heartbeat noise rather than activity
+const MIN_PIECE_SECS: i64 = 1;
+// Consecutive pieces of the same activity closer than this are merged into one row
+const MERGE_GAP_SECS: i64 = 5;
+
+// `aw-server` exports either all buckets ({"buckets": {...}}) or a single bucket object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExportFile {
+    Buckets { buckets: HashMap<String, Bucket> },
+    Single(Bucket),
+}
+
+#[derive(Deserialize)]
+struct Bucket {
+    #[serde(default)]
+    id: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    client: String,
+    #[serde(default)]
+    events: Vec<Event>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    timestamp: DateTime<Utc>,
+    // Seconds, fractional
+    duration: f64,
+    #[serde(default)]
+    data: Map<String, Value>,
+}
+
+impl Event {
+    fn interval(&self) -> Interval {
+        let millis = (self.duration.max(0.0) * 1000.0) as i64;
+        Interval {
+            start: self.timestamp,
+            end: self.timestamp + chrono::Duration::milliseconds(millis),
+        }
+    }
+
+    fn field(&self, key: &str) -> Option<String> {
+        self.data.get(key).and_then(|v| v.as_str()).map(|s| s.to_string())
+    }
+}
+
+enum BucketKind {
+    Window,
+    Afk,
+    Web,
+    Unsupported,
+}
+
+impl Bucket {
+    fn kind(&self) -> BucketKind {
+        if self.kind == "currentwindow" || self.client == "aw-watcher-window" {
+            BucketKind::Window
+        } else if self.kind == "afkstatus" || self.client == "aw-watcher-afk" {
+            BucketKind::Afk
+        } else if self.kind == "web.tab.current" || self.id.starts_with("aw-watcher-web") {
+            BucketKind::Web
+        } else {
+            BucketKind::Unsupported
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Interval {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+}
+
+impl Interval {
+    fn intersect(&self, other: &Interval) -> Option<Interval> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Interval { start, end })
+    }
+
+    // Parts of `self` not covered by `cuts`, which must be sorted and disjoint (see `union`)
+    fn subtract(&self, cuts: &[Interval]) -> Vec<Interval> {
+        let mut remaining = Vec::new();
+        let mut start = self.start;
+        for cut in &cuts[overlapping(cuts, self)] {
+            if start < cut.start {
+                remaining.push(Interval { start, end: cut.start });
+            }
+            start = start.max(cut.end);
+        }
+        if start < self.end {
+            remaining.push(Interval { start, end: self.end });
+        }
+        remaining
+    }
+}
+
+// Sorted, disjoint intervals covering the same time as `intervals`
+fn union(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.retain(|i| i.start < i.end);
+    intervals.sort_by_key(|i| i.start);
+    let mut merged: Vec<Interval> = Vec::new();
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+// Positions of the sorted, disjoint `intervals` that overlap `within`, found by binary search.
+// Exports span months of events, so scanning every interval for every event is far too slow.
+fn overlapping(intervals: &[Interval], within: &Interval) -> Range<usize> {
+    let first = intervals.partition_point(|i| i.end <= within.start);
+    let last = intervals.partition_point(|i| i.start < within.end);
+    first..last.max(first)
+}
+
+struct WebVisit {
+    interval: Interval,
+    url: String,
+    // Page title as the web watcher saw it
+    title: Option<String>,
+}
+
+// A stretch of time attributed to one app (and URL, for browsers)
+struct Piece {
+    interval: Interval,
+    app: String,
+    title: Option<String>,
+    url: Option<String>,
+}
+
+// Activities from the export, and warnings about the parts that were left out
+pub fn parse_file(path: &Path) -> Result<(Vec<Activity>, Vec<String>), Box<dyn Error>> {
+    let contents = std::fs::read_to_string(path)?;
+    parse(&contents, &RuleSet::load()?)
+}
+
+fn parse(contents: &str, rules: &RuleSet) -> Result<(Vec<Activity>, Vec<String>), Box<dyn Error>> {
+    let buckets = match serde_json::from_str::<ExportFile>(contents)? {
+        ExportFile::Buckets { buckets } => buckets.into_values().collect(),
+        ExportFile::Single(bucket) => vec![bucket],
+    };
+
+    let mut windows = Vec::new();
+    let mut afk = Vec::new();
+    let mut visits = Vec::new();
+    let mut warnings = Vec::new();
+
+    for bucket in buckets {
+        match bucket.kind() {
+            BucketKind::Window => windows.extend(bucket.events),
+            BucketKind::Afk => afk.extend(
+                bucket.events.iter()
+                    .filter(|e| e.field("status").as_deref() == Some("afk"))
+                    .map(|e| e.interval())
+            ),
+            BucketKind::Web => visits.extend(
+                bucket.events.iter()
+                    .filter_map(|e| e.field("url").map(|url| WebVisit { interval: e.interval(), url, title: e.field("title") }))
+            ),
+            BucketKind::Unsupported => warnings.push(format!("skipped unsupported bucket '{}'", bucket.id)),
+        }
+    }
+
+    let afk = union(afk);
+    let visits = without_overlaps(visits);
+    let visit_intervals: Vec<Interval> = visits.iter().map(|v| v.interval).collect();
+    let mut pieces = Vec::new();
+    for event in &windows {
+        let app = match event.field("app") {
+            Some(app) => app,
+            None => continue,
+        };
+        let title = event.field("title");
+
+        // Away time is dropped entirely
+        for active in event.interval().subtract(&afk) {
+            if !BrowserMonitor::is_browser_app(&app) || visits.is_empty() {
+                pieces.push(Piece { interval: active, app: app.clone(), title: title.clone(), url: None });
+                continue;
+            }
+
+            // Browser time is attributed to the tab that was open, the rest stays with the browser
+            for visit in &visits[overlapping(&visit_intervals, &active)] {
+                if let Some(overlap) = active.intersect(&visit.interval) {
+                    pieces.push(Piece { interval: overlap, app: app.clone(), title: visit.title.clone(), url: Some(visit.url.clone()) });
+                }
+            }
+            for rest in active.subtract(&visit_intervals) {
+                pieces.push(Piece { interval: rest, app: app.clone(), title: title.clone(), url: None });
+            }
+        }
+    }
+
+    pieces.retain(|p| (p.interval.end - p.interval.start).num_seconds() >= MIN_PIECE_SECS);
+    pieces.sort_by_key(|p| p.interval.start);
+
+    Ok((merge(pieces.into_iter().map(|p| to_activity(p, rules)).collect()), warnings))
+}
+
+// Several web buckets (one per browser, or the same browser exported twice) can claim the same
+// moment. Each moment goes to the visit that started first, so browser time is counted once.
+// The result is sorted and disjoint.
+fn without_overlaps(mut visits: Vec<WebVisit>) -> Vec<WebVisit> {
+    visits.sort_by_key(|v| (v.interval.start, v.interval.end));
+    let mut kept: Vec<WebVisit> = Vec::new();
+    for mut visit in visits {
+        if let Some(last) = kept.last_mut() {
+            if visit.interval.start < last.interval.end {
+                if visit.url == last.url {
+                    last.interval.end = last.interval.end.max(visit.interval.end);
+                    continue;
+                }
+                visit.interval.start = last.interval.end;
+            }
+        }
+        if visit.interval.start < visit.interval.end {
+            kept.push(visit);
+        }
+    }
+    kept
+}
+
+fn to_activity(piece: Piece, rules: &RuleSet) -> Activity {
+    let name = match &piece.url {
+        // Same naming as the live monitor uses for browser samples
+        Some(_) => format!("{} - Web Browsing", piece.app),
+        None => piece.app.clone(),
+    };
+    let (category, weight) = AppMonitor::classify(rules, &piece.app, piece.title.as_deref(), piece.url.as_deref(), None);
+
+    let mut activity = build_activity(
+        "activitywatch",
+        name,
+        category,
+        vec!["automatic".to_string()],
+        piece.interval.start.with_timezone(&Local),
+        piece.interval.end.with_timezone(&Local),
+        None,
+    );
+    activity.set_weight(weight);
+    activity.window_title = piece.title;
+    activity.url = piece.url;
+    activity
+}
+
+// Joins back-to-back rows for the same thing so every AW heartbeat doesn't become its own row
+fn merge(activities: Vec<Activity>) -> Vec<Activity> {
+    let mut merged: Vec<Activity> = Vec::new();
+
+    for activity in activities {
+        if let Some(last) = merged.last_mut() {
+            let last_end = last.end_time.unwrap_or(last.start_time);
+            let same = last.name == activity.name && last.category == activity.category && last.url == activity.url
+                && last.window_title == activity.window_title;
+            let gap = activity.start_time.signed_duration_since(last_end).num_seconds();
+
+            if same && (0..=MERGE_GAP_SECS).contains(&gap) {
+                let end = activity.end_time.unwrap_or(activity.start_time).max(last_end);
+                last.end_time = Some(end);
+                last.duration = Some(end.signed_duration_since(last.start_time).to_std().unwrap_or_default());
+                // Re-key the marker so it still describes the merged range
+                last.source = Some(source_marker("activitywatch", &last.start_time, &end, &last.name));
+                continue;
+            }
+        }
+        merged.push(activity);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_web_buckets_count_browser_time_once() {
+        let export = r#"{"buckets": {
+            "aw-watcher-window_host": {"id": "aw-watcher-window_host", "type": "currentwindow", "events": [
+                {"timestamp": "2026-03-02T09:00:00Z", "duration": 60, "data": {"app": "Google Chrome", "title": "Docs"}}
+            ]},
+            "aw-watcher-web-chrome": {"id": "aw-watcher-web-chrome", "type": "web.tab.current", "events": [
+                {"timestamp": "2026-03-02T09:00:00Z", "duration": 40, "data": {"url": "https://docs.rs/chrono", "title": "chrono - Rust"}}
+            ]},
+            "aw-watcher-web-chrome_2": {"id": "aw-watcher-web-chrome_2", "type": "web.tab.current", "events": [
+                {"timestamp": "2026-03-02T09:00:10Z", "duration": 20, "data": {"url": "https://docs.rs/chrono", "title": "chrono - Rust"}},
+                {"timestamp": "2026-03-02T09:00:30Z", "duration": 30, "data": {"url": "https://github.com/", "title": "GitHub"}}
+            ]},
+            "aw-watcher-input_host": {"id": "aw-watcher-input_host", "type": "os.hid.input", "events": []}
+        }}"#;
+        let (activities, warnings) = parse(export, &RuleSet::default()).unwrap();
+
+        let total: u64 = activities.iter().map(|a| a.duration.unwrap().as_secs()).sum();
+        assert_eq!(total, 60);
+        let pages: Vec<(&str, &str, u64)> = activities.iter()
+            .map(|a| (a.url.as_deref().unwrap(), a.window_title.as_deref().unwrap(), a.duration.unwrap().as_secs()))
+            .collect();
+        assert_eq!(pages, [("https://docs.rs/chrono", "chrono - Rust", 40), ("https://github.com/", "GitHub", 20)]);
+        assert!(activities.iter().all(|a| a.notes.is_none()));
+        assert_eq!(warnings, ["skipped unsupported bucket 'aw-watcher-input_host'"]);
+    }
+
+    #[test]
+    fn away_time_is_cut_out_of_window_events() {
+        let export = r#"{"buckets": {
+            "aw-watcher-window_host": {"id": "aw-watcher-window_host", "type": "currentwindow", "events": [
+                {"timestamp": "2026-03-02T09:00:00Z", "duration": 600, "data": {"app": "Code", "title": "main.rs"}},
+                {"timestamp": "2026-03-02T09:10:00Z", "duration": 300, "data": {"app": "Terminal", "title": "zsh"}}
+            ]},
+            "aw-watcher-afk_host": {"id": "aw-watcher-afk_host", "type": "afkstatus", "events": [
+                {"timestamp": "2026-03-02T09:08:00Z", "duration": 60, "data": {"status": "afk"}},
+                {"timestamp": "2026-03-02T09:02:00Z", "duration": 60, "data": {"status": "afk"}},
+                {"timestamp": "2026-03-02T09:02:30Z", "duration": 60, "data": {"status": "afk"}},
+                {"timestamp": "2026-03-02T09:09:00Z", "duration": 120, "data": {"status": "afk"}},
+                {"timestamp": "2026-03-02T09:12:00Z", "duration": 600, "data": {"status": "not-afk"}}
+            ]}
+        }}"#;
+        let (activities, _) = parse(export, &RuleSet::default()).unwrap();
+
+        let spans: Vec<(&str, String, u64)> = activities.iter()
+            .map(|a| (a.name.as_str(), a.start_time.with_timezone(&Utc).format("%H:%M:%S").to_string(), a.duration.unwrap().as_secs()))
+            .collect();
+        assert_eq!(spans, [
+            ("Code", "09:00:00".to_string(), 120),
+            ("Code", "09:03:30".to_string(), 270),
+            ("Terminal", "09:11:00".to_string(), 240),
+        ]);
+    }
+}
@@ -0,0 +1,49 @@
+use super::{build_activity, check_order, parse_date, parse_time, split_tags, CsvRow, ImportOptions};
+use crate::data::Activity;
+
+// Toggl Track and Clockify "Detailed" CSV reports share the same layout:
+// Project, Client, Description, Task, Tags, Start Date, Start Time, End Date, End Time, Duration.
+// Header case differs between the two and Clockify uses US dates and 12-hour times by default.
+pub fn parse_row(row: &CsvRow, options: &ImportOptions) -> Result<Activity, String> {
+    let date_format = options.date_format.as_deref();
+    
+    let start_date = parse_date(row.require("Start date")?, date_format)?;
+    let start_time = parse_time(row.require("Start time")?)?;
+    let end_date = row.get("End date").map(|value| parse_date(value, date_format)).transpose()?;
+    let end_time = parse_time(row.require("End time")?)?;
+    
+    let start = options.zone.resolve(start_date.and_time(start_time));
+    let mut end = options.zone.resolve(end_date.unwrap_or(start_date).and_time(end_time));
+    if end < start && end_date.is_none() {
+        // Entries running past midnight without an end date
+        end += chrono::Duration::days(1);
+    }
+    check_order(&start, &end, &options.zone)?;
+    
+    let project = row.get("Project");
+    let task = row.get("Task");
+    let name = row.get("Description")
+        .or(task)
+        .or(project)
+        .unwrap_or("Imported entry")
+        .to_string();
+    let category = project.unwrap_or("Other").to_string();
+    
+    let mut tags = split_tags(row.get("Tags"));
+    if let Some(client) = row.get("Client") {
+        tags.push(format!("client/{}", client.to_lowercase().replace(' ', "-")));
+    }
+    
+    // Keep the task as a note when the description was used for the name
+    let notes = task.filter(|t| *t != name).map(|t| t.to_string());
+    
+    Ok(build_activity(
+        options.format.source_prefix(),
+        name,
+        category,
+        tags,
+        start,
+        end,
+        notes,
+    ))
+}
@@ -0,0 +1,108 @@
+use super::{build_activity, check_order, parse_date, parse_duration, parse_time, parse_timestamp, split_tags, CsvRow, ImportOptions};
+use crate::data::{weight_from_flag, Activity};
+use std::str::FromStr;
+
+// Describes which CSV header feeds which activity field, parsed from
+// "name=Task,start=Begin,end=Finish,category=Project,tags=Labels".
+//
+// `start` and `end` hold full timestamps, unless `date` is mapped too,
+// in which case they are read as times of day on that date.
+// Either `end` or `duration` must be mapped.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMapping {
+    pub name: String,
+    pub start: String,
+    pub end: Option<String>,
+    pub duration: Option<String>,
+    pub date: Option<String>,
+    pub category: Option<String>,
+    pub tags: Option<String>,
+    pub notes: Option<String>,
+    pub productive: Option<String>,
+}
+
+impl FromStr for ColumnMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = ColumnMapping::default();
+
+        for pair in s.split(',').filter(|p| !p.trim().is_empty()) {
+            let (field, column) = pair.split_once('=')
+                .ok_or_else(|| format!("expected field=column, got '{}'", pair))?;
+            let column = column.trim().to_string();
+
+            match field.trim() {
+                "name" => mapping.name = column,
+                "start" => mapping.start = column,
+                "end" => mapping.end = Some(column),
+                "duration" => mapping.duration = Some(column),
+                "date" => mapping.date = Some(column),
+                "category" => mapping.category = Some(column),
+                "tags" => mapping.tags = Some(column),
+                "notes" => mapping.notes = Some(column),
+                "productive" => mapping.productive = Some(column),
+                other => return Err(format!("unknown field '{}' in column mapping", other)),
+            }
+        }
+
+        if mapping.name.is_empty() || mapping.start.is_empty() {
+            return Err("column mapping needs at least name= and start=".to_string());
+        }
+        if mapping.end.is_none() && mapping.duration.is_none() {
+            return Err("column mapping needs either end= or duration=".to_string());
+        }
+        Ok(mapping)
+    }
+}
+
+pub fn parse_row(row: &CsvRow, mapping: &ColumnMapping, options: &ImportOptions) -> Result<Activity, String> {
+    let date_format = options.date_format.as_deref();
+
+    let date = match &mapping.date {
+        Some(column) => Some(parse_date(row.require(column)?, date_format)?),
+        None => None,
+    };
+    let read_time = |column: &str| match date {
+        Some(day) => parse_time(row.require(column)?).map(|t| options.zone.resolve(day.and_time(t))),
+        None => parse_timestamp(row.require(column)?, date_format, &options.zone),
+    };
+
+    let start = read_time(&mapping.start)?;
+    let mut end = match (&mapping.end, &mapping.duration) {
+        (Some(column), _) => read_time(column)?,
+        (None, Some(column)) => {
+            let value = row.require(column)?;
+            chrono::Duration::from_std(parse_duration(value)?).ok()
+                .and_then(|duration| start.checked_add_signed(duration))
+                .ok_or_else(|| format!("duration '{}' is too long", value))?
+        },
+        (None, None) => unreachable!("validated when the mapping was parsed"),
+    };
+    if end < start && date.is_some() {
+        end += chrono::Duration::days(1);
+    }
+    check_order(&start, &end, &options.zone)?;
+
+    let name = row.require(&mapping.name)?.to_string();
+    let category = mapping.category.as_deref()
+        .and_then(|c| row.get(c))
+        .unwrap_or("Other")
+        .to_string();
+    let tags = split_tags(mapping.tags.as_deref().and_then(|c| row.get(c)));
+    let notes = mapping.notes.as_deref().and_then(|c| row.get(c)).map(|n| n.to_string());
+
+    let mut activity = build_activity(
+        options.format.source_prefix(),
+        name,
+        category,
+        tags,
+        start,
+        end,
+        notes,
+    );
+    if let Some(value) = mapping.productive.as_deref().and_then(|c| row.get(c)) {
+        activity.set_weight(weight_from_flag(matches!(value.to_lowercase().as_str(), "1" | "true" | "yes" | "y")));
+    }
+    Ok(activity)
+}
@@ -0,0 +1,500 @@
+mod activitywatch;
+mod detailed;
+mod generic;
+
+use crate::data::timezone::DisplayZone;
+use crate::data::Activity;
+use crate::db::Database;
+use crate::monitor::AppMonitor;
+use crate::privacy::Redactor;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use clap::ValueEnum;
+use csv::StringRecord;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+pub use self::generic::ColumnMapping;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ImportFormat {
+    // Toggl Track "Detailed" report exported as CSV
+    Toggl,
+    // Clockify "Detailed" report exported as CSV
+    Clockify,
+    // Any CSV described by a --map column mapping
+    Csv,
+    // ActivityWatch bucket export (JSON) with window, AFK and web watcher buckets
+    #[value(name = "activitywatch")]
+    ActivityWatch,
+}
+
+impl ImportFormat {
+    // Prefix of the source marker stored on imported activities
+    pub fn source_prefix(&self) -> &'static str {
+        match self {
+            ImportFormat::Toggl => "toggl",
+            ImportFormat::Clockify => "clockify",
+            ImportFormat::Csv => "csv",
+            ImportFormat::ActivityWatch => "activitywatch",
+        }
+    }
+}
+
+pub struct ImportOptions {
+    pub format: ImportFormat,
+    pub mapping: Option<ColumnMapping>,
+    pub date_format: Option<String>,
+    pub zone: DisplayZone,
+    pub dry_run: bool,
+}
+
+// Outcome of an import run; in dry-run mode `imported` counts what would have been written
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub already_imported: usize,
+    pub overlapping: usize,
+    pub errors: Vec<String>,
+    // Parts of the file left out on purpose, such as buckets of an unsupported kind
+    pub warnings: Vec<String>,
+    pub total_duration: Duration,
+}
+
+impl ImportSummary {
+    pub fn print(&self, dry_run: bool) {
+        let verb = if dry_run { "Would import" } else { "Imported" };
+        println!("{} {} activities ({:.1}h)", verb, self.imported, self.total_duration.as_secs_f64() / 3600.0);
+        println!("Skipped {} already imported", self.already_imported);
+        println!("Skipped {} overlapping existing activities", self.overlapping);
+        for warning in &self.warnings {
+            println!("Note: {}", warning);
+        }
+        if !self.errors.is_empty() {
+            println!("{} entries could not be imported:", self.errors.len());
+            for error in &self.errors {
+                println!("  {}", error);
+            }
+        }
+    }
+}
+
+pub fn import_file(db: &Database, path: &Path, options: &ImportOptions) -> Result<ImportSummary, Box<dyn Error>> {
+    let redactor = Redactor::load()?;
+    if options.format == ImportFormat::ActivityWatch {
+        let (activities, warnings) = activitywatch::parse_file(path)?;
+        let mut summary = ImportSummary { warnings, ..Default::default() };
+        db.in_transaction(|| record_activities(db, activities, options, &redactor, &mut summary))?;
+        return Ok(summary);
+    }
+    
+    let reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(path)?;
+    let mut summary = ImportSummary::default();
+    let activities = parse_csv(reader, options, &mut summary)?;
+
+    db.in_transaction(|| record_activities(db, activities, options, &redactor, &mut summary))?;
+    Ok(summary)
+}
+
+// Rows that cannot be read are reported in the summary by line and do not stop the import
+fn parse_csv<R: std::io::Read>(mut reader: csv::Reader<R>, options: &ImportOptions, summary: &mut ImportSummary) -> Result<Vec<Activity>, Box<dyn Error>> {
+    let headers = reader.headers()?.clone();
+
+    let mut activities = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        // Header is line 1
+        let line = index + 2;
+        let row = CsvRow { headers: &headers, record: &record? };
+
+        let parsed = match options.format {
+            ImportFormat::Toggl | ImportFormat::Clockify => detailed::parse_row(&row, options),
+            ImportFormat::Csv => match &options.mapping {
+                Some(mapping) => generic::parse_row(&row, mapping, options),
+                None => return Err("generic CSV import requires a --map column mapping".into()),
+            },
+            ImportFormat::ActivityWatch => unreachable!("handled above"),
+        };
+
+        match parsed {
+            Ok(activity) => activities.push(activity),
+            Err(e) => summary.errors.push(format!("line {}: {}", line, e)),
+        }
+    }
+    Ok(activities)
+}
+
+// Writes parsed activities, leaving out those imported before or clashing with existing data.
+// What this run accepted is tracked too, because a dry run never writes it, so duplicates
+// within one file are caught the same way with or without --dry-run.
+fn record_activities(db: &Database, activities: Vec<Activity>, options: &ImportOptions, redactor: &Redactor, summary: &mut ImportSummary) -> Result<(), Box<dyn Error>> {
+    let ends = activities.iter().filter_map(|a| a.end_time);
+    let existing = match (activities.iter().map(|a| a.start_time).min(), ends.max()) {
+        (Some(from), Some(to)) => ExistingRanges::load(db, from.timestamp(), to.timestamp(), options.format.source_prefix())?,
+        _ => ExistingRanges::default(),
+    };
+    let mut accepted_sources = HashSet::new();
+    // Start to end of each accepted activity; they never overlap, so they can be keyed on start
+    let mut accepted_ranges = BTreeMap::new();
+
+    for mut activity in activities {
+        redact(&mut activity, redactor, options.format.source_prefix());
+        if let Some(source) = &activity.source {
+            if accepted_sources.contains(source) || db.source_exists(source)? {
+                summary.already_imported += 1;
+                continue;
+            }
+        }
+
+        if let Some(end) = activity.end_time {
+            let automatic = activity.tags.iter().any(|t| t == "automatic");
+            let clashes_in_file = accepted_ranges.range(..end).next_back()
+                .is_some_and(|(_, accepted_end)| *accepted_end > activity.start_time);
+            if clashes_in_file || existing.clashes(activity.start_time.timestamp(), end.timestamp(), automatic) {
+                summary.overlapping += 1;
+                continue;
+            }
+            if activity.start_time < end {
+                accepted_ranges.insert(activity.start_time, end);
+            }
+        }
+
+        // Times read in another zone keep it, so their days are split on that zone's midnight
+        if options.zone != DisplayZone::Local {
+            activity.zone = Some(options.zone.to_string());
+        }
+        if !options.dry_run {
+            db.save_activity(&activity)?;
+        }
+        summary.imported += 1;
+        summary.total_duration += activity.duration.unwrap_or_default();
+        if let Some(source) = activity.source {
+            accepted_sources.insert(source);
+        }
+    }
+    Ok(())
+}
+
+// Nothing imported is stored before it went through the privacy settings. The source marker
+// repeats the name, so it is rebuilt from the redacted one.
+fn redact(activity: &mut Activity, redactor: &Redactor, prefix: &str) {
+    let name = redactor.text(&activity.name);
+    activity.window_title = activity.window_title.as_deref().map(|t| redactor.title(&activity.name, t));
+    activity.url = activity.url.as_deref().map(|url| redactor.url(url, &activity.category));
+    activity.notes = activity.notes.as_deref().map(|n| redactor.text(n));
+    if name != activity.name {
+        activity.name = name;
+        if let (Some(_), Some(end)) = (&activity.source, &activity.end_time) {
+            activity.source = Some(source_marker(prefix, &activity.start_time, end, &activity.name));
+        }
+    }
+}
+
+// Stored activities an import must not overlap (see `Database::get_import_clashes`), loaded
+// once for the whole file. Sorted by start, with the latest end reached so far at each
+// position, so a clash is found by binary search.
+#[derive(Default)]
+struct ExistingRanges {
+    always: RangeIndex,
+    automatic: RangeIndex,
+}
+
+impl ExistingRanges {
+    fn load(db: &Database, from: i64, to: i64, source_prefix: &str) -> Result<Self, Box<dyn Error>> {
+        let (always, automatic): (Vec<_>, Vec<_>) = db.get_import_clashes(from, to, source_prefix)?
+            .into_iter()
+            .partition(|(_, _, always)| *always);
+        Ok(ExistingRanges { always: RangeIndex::new(always), automatic: RangeIndex::new(automatic) })
+    }
+
+    fn clashes(&self, start: i64, end: i64, automatic: bool) -> bool {
+        self.always.overlaps(start, end) || (automatic && self.automatic.overlaps(start, end))
+    }
+}
+
+#[derive(Default)]
+struct RangeIndex {
+    starts: Vec<i64>,
+    latest_ends: Vec<i64>,
+}
+
+impl RangeIndex {
+    fn new(mut ranges: Vec<(i64, i64, bool)>) -> Self {
+        ranges.sort_by_key(|(start, _, _)| *start);
+        let starts = ranges.iter().map(|(start, _, _)| *start).collect();
+        let latest_ends = ranges.iter()
+            .scan(i64::MIN, |latest, (_, end, _)| {
+                *latest = (*latest).max(*end);
+                Some(*latest)
+            })
+            .collect();
+        RangeIndex { starts, latest_ends }
+    }
+
+    fn overlaps(&self, start: i64, end: i64) -> bool {
+        let before_end = self.starts.partition_point(|s| *s < end);
+        before_end > 0 && self.latest_ends[before_end - 1] > start
+    }
+}
+
+// Builds an imported activity with its times and a stable source marker.
+// The marker only depends on the row contents, so importing the same file twice is a no-op.
+fn build_activity(
+    prefix: &str,
+    name: String,
+    category: String,
+    tags: Vec<String>,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    notes: Option<String>,
+) -> Activity {
+    // Project names rarely say how productive the time was, so unknown ones stay neutral
+    let weight = AppMonitor::productivity_weight(&name, &category, None);
+    let mut activity = Activity::new(name, category, tags, weight > 0);
+    activity.set_weight(weight);
+    activity.source = Some(source_marker(prefix, &start, &end, &activity.name));
+    activity.start_time = start;
+    activity.end_time = Some(end);
+    activity.duration = Some(end.signed_duration_since(start).to_std().unwrap_or_default());
+    activity.notes = notes;
+    activity
+}
+
+pub(crate) fn source_marker(prefix: &str, start: &DateTime<Local>, end: &DateTime<Local>, name: &str) -> String {
+    format!("{}:{}:{}:{}", prefix, start.timestamp(), end.timestamp(), name)
+}
+
+// Case-insensitive access to a CSV record by header name
+pub(crate) struct CsvRow<'a> {
+    headers: &'a StringRecord,
+    record: &'a StringRecord,
+}
+
+impl CsvRow<'_> {
+    pub fn get(&self, column: &str) -> Option<&str> {
+        self.headers.iter()
+            .position(|h| h.trim_start_matches('\u{feff}').eq_ignore_ascii_case(column))
+            .and_then(|i| self.record.get(i))
+            .filter(|v| !v.is_empty())
+    }
+
+    pub fn require(&self, column: &str) -> Result<&str, String> {
+        self.get(column).ok_or_else(|| format!("missing value for column '{}'", column))
+    }
+}
+
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y", "%Y/%m/%d"];
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+pub(crate) fn parse_date(value: &str, custom: Option<&str>) -> Result<NaiveDate, String> {
+    let parsed = match custom {
+        Some(format) => NaiveDate::parse_from_str(value, format).ok(),
+        None => DATE_FORMATS.iter().find_map(|f| NaiveDate::parse_from_str(value, f).ok()),
+    };
+    parsed.ok_or_else(|| format!("unrecognised date '{}'", value))
+}
+
+pub(crate) fn parse_time(value: &str) -> Result<NaiveTime, String> {
+    TIME_FORMATS.iter()
+        .find_map(|f| NaiveTime::parse_from_str(&value.to_uppercase(), f).ok())
+        .ok_or_else(|| format!("unrecognised time '{}'", value))
+}
+
+// Full timestamps: RFC 3339 keeps its own offset, anything else is read in the import time zone
+pub(crate) fn parse_timestamp(value: &str, custom: Option<&str>, zone: &DisplayZone) -> Result<DateTime<Local>, String> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Local));
+    }
+
+    let naive = match custom {
+        Some(format) => NaiveDateTime::parse_from_str(value, format).ok(),
+        None => ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok()),
+    };
+    naive
+        .map(|n| zone.resolve(n))
+        .ok_or_else(|| format!("unrecognised timestamp '{}'", value))
+}
+
+// Accepts "1:30:00", "1:30" (hours and minutes) or plain seconds
+pub(crate) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let numbers: Result<Vec<u64>, _> = parts.iter().map(|p| p.trim().parse::<u64>()).collect();
+
+    let total = |h: u64, m: u64, s: u64| {
+        h.checked_mul(3600)
+            .zip(m.checked_mul(60))
+            .and_then(|(h, m)| h.checked_add(m))
+            .and_then(|hm| hm.checked_add(s))
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("duration '{}' is too long", value))
+    };
+
+    match numbers.as_deref() {
+        Ok([secs]) => Ok(Duration::from_secs(*secs)),
+        Ok([h, m]) => total(*h, *m, 0),
+        Ok([h, m, s]) => total(*h, *m, *s),
+        _ => Err(format!("unrecognised duration '{}'", value)),
+    }
+}
+
+// Rows ending before they start are mistakes in the file, not negative durations
+pub(crate) fn check_order(start: &DateTime<Local>, end: &DateTime<Local>, zone: &DisplayZone) -> Result<(), String> {
+    if end < start {
+        return Err(format!("end {} is before start {}", zone.format(end), zone.format(start)));
+    }
+    Ok(())
+}
+
+pub(crate) fn split_tags(value: Option<&str>) -> Vec<String> {
+    value.unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const TOGGL: &str = "User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags
+Jen,jen@example.com,Acme Corp,Website,,Fix header,No,2026-03-02,09:00:00,2026-03-02,10:30:00,01:30:00,\"frontend, bug\"
+Jen,jen@example.com,,Internal,,Planning,No,2026-03-02,11:00:00,2026-03-02,11:20:00,00:20:00,
+";
+
+    fn options(format: ImportFormat, mapping: Option<&str>) -> ImportOptions {
+        ImportOptions {
+            format,
+            mapping: mapping.map(|m| m.parse().unwrap()),
+            date_format: None,
+            zone: DisplayZone::Utc,
+            dry_run: false,
+        }
+    }
+
+    fn parse(csv: &str, options: &ImportOptions) -> (Vec<Activity>, ImportSummary) {
+        let reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(csv.as_bytes());
+        let mut summary = ImportSummary::default();
+        let activities = parse_csv(reader, options, &mut summary).unwrap();
+        (activities, summary)
+    }
+
+    fn utc(time: &DateTime<Local>) -> String {
+        time.with_timezone(&Utc).format("%Y-%m-%d %H:%M").to_string()
+    }
+
+    #[test]
+    fn reads_toggl_detailed_rows() {
+        let (activities, summary) = parse(TOGGL, &options(ImportFormat::Toggl, None));
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        let fix = &activities[0];
+        assert_eq!(fix.name, "Fix header");
+        assert_eq!(fix.category, "Website");
+        assert_eq!(fix.tags, ["frontend", "bug", "client/acme-corp"]);
+        assert_eq!(utc(&fix.start_time), "2026-03-02 09:00");
+        assert_eq!(fix.duration.unwrap().as_secs(), 5400);
+        assert!(fix.source.as_deref().unwrap().starts_with("toggl:"));
+        assert_eq!(activities[1].tags, Vec::<String>::new());
+    }
+
+    #[test]
+    fn reads_clockify_rows_with_us_dates_and_12_hour_times() {
+        let csv = "Project,Client,Description,Task,User,Email,Tags,Billable,Start Date,Start Time,End Date,End Time,Duration (h)
+Internal,,Standup,Meetings,Jen,jen@example.com,,No,03/02/2026,11:45 PM,03/03/2026,12:15 AM,00:30:00
+";
+        let (activities, summary) = parse(csv, &options(ImportFormat::Clockify, None));
+        assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+        let standup = &activities[0];
+        assert_eq!((standup.name.as_str(), standup.category.as_str()), ("Standup", "Internal"));
+        assert_eq!(standup.notes.as_deref(), Some("Meetings"));
+        assert_eq!(utc(&standup.start_time), "2026-03-02 23:45");
+        assert_eq!(standup.duration.unwrap().as_secs(), 1800);
+        assert!(standup.source.as_deref().unwrap().starts_with("clockify:"));
+    }
+
+    #[test]
+    fn column_mappings_name_their_mistakes() {
+        let error = |mapping: &str| mapping.parse::<ColumnMapping>().unwrap_err();
+        assert_eq!(error("name=Task,begin=Start,end=Finish"), "unknown field 'begin' in column mapping");
+        assert_eq!(error("name=Task,start"), "expected field=column, got 'start'");
+        assert_eq!(error("name=Task,start=Start"), "column mapping needs either end= or duration=");
+        assert_eq!(error("start=Start,end=Finish"), "column mapping needs at least name= and start=");
+    }
+
+    #[test]
+    fn durations_too_long_fail_their_row_only() {
+        let csv = "Task,Start,Seconds
+Write,2026-03-02 09:00,1:30:00
+Huge,2026-03-02 09:00,99999999999999
+Overflow,2026-03-02 09:00,9999999999999999999:0:0
+";
+        let (activities, summary) = parse(csv, &options(ImportFormat::Csv, Some("name=Task,start=Start,duration=Seconds")));
+        assert_eq!(activities.len(), 1);
+        assert_eq!(activities[0].duration.unwrap().as_secs(), 5400);
+        assert_eq!(summary.errors, [
+            "line 3: duration '99999999999999' is too long",
+            "line 4: duration '9999999999999999999:0:0' is too long",
+        ]);
+    }
+
+    #[test]
+    fn ends_before_starts_fail_their_row_only() {
+        let csv = "Task,Start,End
+Write,2026-03-02 09:00,2026-03-02 10:00
+Backwards,2026-03-02 09:00,2026-03-01 10:00
+";
+        let (activities, summary) = parse(csv, &options(ImportFormat::Csv, Some("name=Task,start=Start,end=End")));
+        assert_eq!(activities.len(), 1);
+        assert_eq!(summary.errors, ["line 3: end 2026-03-01T10:00:00Z is before start 2026-03-02T09:00:00Z"]);
+
+        let toggl = "Description,Start date,Start time,End date,End time
+Backwards,2026-03-02,09:00:00,2026-03-01,10:00:00
+Overnight,2026-03-02,23:30:00,,00:30:00
+";
+        let (activities, summary) = parse(toggl, &options(ImportFormat::Toggl, None));
+        assert_eq!(activities.len(), 1);
+        assert_eq!(activities[0].duration.unwrap().as_secs(), 3600);
+        assert_eq!(summary.errors, ["line 2: end 2026-03-01T10:00:00Z is before start 2026-03-02T09:00:00Z"]);
+    }
+
+    #[test]
+    fn importing_the_same_file_again_adds_nothing() {
+        let db = Database::open_in_memory().unwrap();
+        let options = options(ImportFormat::Toggl, None);
+        let redactor = Redactor::default();
+
+        let mut first = ImportSummary::default();
+        record_activities(&db, parse(TOGGL, &options).0, &options, &redactor, &mut first).unwrap();
+        assert_eq!(first.imported, 2);
+
+        let mut again = ImportSummary::default();
+        record_activities(&db, parse(TOGGL, &options).0, &options, &redactor, &mut again).unwrap();
+        assert_eq!((again.imported, again.already_imported, again.overlapping), (0, 2, 0));
+    }
+
+    #[test]
+    fn dry_runs_catch_duplicates_within_the_file() {
+        let db = Database::open_in_memory().unwrap();
+        let options = ImportOptions { dry_run: true, ..options(ImportFormat::Toggl, None) };
+        let twice = format!("{}{}", TOGGL, TOGGL.lines().nth(1).unwrap());
+
+        let activities = parse(&twice, &options).0;
+        let source = activities[0].source.clone().unwrap();
+
+        let mut summary = ImportSummary::default();
+        record_activities(&db, activities, &options, &Redactor::default(), &mut summary).unwrap();
+        assert_eq!((summary.imported, summary.already_imported), (2, 1));
+        assert!(!db.source_exists(&source).unwrap());
+    }
+}
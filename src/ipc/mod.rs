@@ -0,0 +1,384 @@
+use crate::data::{weight_from_flag, Activity};
+use crate::db;
+use crate::monitor::MonitorHandle;
+use crate::server;
+use crate::shell::{ShellEvent, ShellPhase};
+use crate::tracker::Tracker;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+// The daemon answers quickly; a hung daemon should not hang the CLI
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// One JSON object per line, one request per connection
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Start {
+        name: String,
+        category: String,
+        tags: Vec<String>,
+        productive: bool,
+        // Takes precedence over `productive` when given
+        #[serde(default)]
+        weight: Option<i8>,
+    },
+    Stop,
+    Current,
+    Pause {
+        // Snooze length; paused until resumed when absent
+        #[serde(default)]
+        duration_secs: Option<u64>,
+    },
+    Resume,
+    Status,
+    // From the shell hooks, before and after each command
+    ShellEvent { event: ShellEvent },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum Response {
+    Activity { activity: Option<Activity> },
+    Status { status: DaemonStatus },
+    Done { message: String },
+    Error { message: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub running_since: DateTime<Local>,
+    pub paused: bool,
+    pub paused_until: Option<DateTime<Local>>,
+    pub foreground_app: String,
+    // As the privacy settings allow them to be stored
+    #[serde(default)]
+    pub foreground_title: Option<String>,
+    #[serde(default)]
+    pub foreground_url: Option<String>,
+    pub manual_activity: Option<Activity>,
+    pub sampling_interval_secs: u64,
+}
+
+pub fn socket_path() -> PathBuf {
+    db::data_dir().join("daemon.sock")
+}
+
+// Sends a request to the running daemon.
+// Returns None when no daemon is listening, so callers can fall back to the database.
+pub fn send(request: &Request) -> Option<Result<Response, Box<dyn Error>>> {
+    let stream = std::os::unix::net::UnixStream::connect(socket_path()).ok()?;
+    Some(exchange(stream, request))
+}
+
+// Starts an activity in the running daemon, or directly in the database when there is none.
+// Returns the activity as it was started.
+pub fn start_activity(name: String, category: String, tags: Vec<String>, weight: i8) -> Result<Option<Activity>, Box<dyn Error>> {
+    let request = Request::Start {
+        name: name.clone(),
+        category: category.clone(),
+        tags: tags.clone(),
+        productive: weight > 0,
+        weight: Some(weight),
+    };
+    match send(&request) {
+        Some(Ok(Response::Activity { activity })) => Ok(activity),
+        Some(Ok(Response::Error { message })) => Err(format!("daemon: {}", message).into()),
+        Some(Ok(_)) => Err("unexpected reply from the daemon".into()),
+        Some(Err(e)) => Err(format!("could not talk to the daemon: {}", e).into()),
+        None => {
+            let mut tracker = Tracker::new()?;
+            tracker.start_activity(name, category, tags, weight)?;
+            Ok(tracker.get_current_activity().cloned())
+        },
+    }
+}
+
+// Returns the stopped activity, if one was running
+pub fn stop_activity() -> Result<Option<Activity>, Box<dyn Error>> {
+    match send(&Request::Stop) {
+        Some(Ok(Response::Activity { activity })) => Ok(activity),
+        Some(Ok(Response::Error { message })) => Err(format!("daemon: {}", message).into()),
+        Some(Ok(_)) => Err("unexpected reply from the daemon".into()),
+        Some(Err(e)) => Err(format!("could not talk to the daemon: {}", e).into()),
+        None => Ok(Tracker::new()?.stop_activity()?),
+    }
+}
+
+fn exchange(mut stream: std::os::unix::net::UnixStream, request: &Request) -> Result<Response, Box<dyn Error>> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    Ok(serde_json::from_str(&reply)?)
+}
+
+pub struct ControlServer {
+    tracker: Arc<Mutex<Tracker>>,
+    monitor: MonitorHandle,
+    running_since: DateTime<Local>,
+    sampling_interval: Duration,
+}
+
+impl ControlServer {
+    pub fn new(monitor: MonitorHandle, sampling_interval: Duration) -> Result<Self, Box<dyn Error>> {
+        Ok(Self::with_tracker(Tracker::new()?, monitor, sampling_interval))
+    }
+
+    fn with_tracker(tracker: Tracker, monitor: MonitorHandle, sampling_interval: Duration) -> Self {
+        // An activity started from the CLI before the daemon came up keeps owning the time
+        monitor.set_manual_tracking(tracker.get_current_activity().is_some());
+
+        Self {
+            tracker: Arc::new(Mutex::new(tracker)),
+            monitor,
+            running_since: Local::now(),
+            sampling_interval,
+        }
+    }
+
+    pub async fn listen(self) -> Result<(), Box<dyn Error>> {
+        // Anyone who can connect can start and stop tracking, so only the owner ever can
+        let listener = server::bind_unix(&socket_path())?;
+
+        let server = Arc::new(self);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = Arc::clone(&server);
+            tokio::spawn(async move {
+                if let Err(e) = server.serve_connection(stream).await {
+                    eprintln!("Control socket error: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn serve_connection(&self, stream: UnixStream) -> Result<(), Box<dyn Error>> {
+        let (reader, mut writer) = stream.into_split();
+        let mut line = String::new();
+        tokio::io::BufReader::new(reader).read_line(&mut line).await?;
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => self.handle(request),
+            Err(e) => Response::Error { message: format!("invalid request: {}", e) },
+        };
+
+        let mut reply = serde_json::to_string(&response)?;
+        reply.push('\n');
+        writer.write_all(reply.as_bytes()).await?;
+        Ok(())
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        match self.try_handle(request) {
+            Ok(response) => response,
+            Err(e) => Response::Error { message: e.to_string() },
+        }
+    }
+
+    fn try_handle(&self, request: Request) -> rusqlite::Result<Response> {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.refresh()?;
+
+        let response = match request {
+            Request::Start { name, category, tags, productive, weight } => {
+                tracker.start_activity(name, category, tags, weight.unwrap_or_else(|| weight_from_flag(productive)))?;
+                Response::Activity { activity: tracker.get_current_activity().cloned() }
+            },
+            Request::Stop => Response::Activity { activity: tracker.stop_activity()? },
+            Request::Current => Response::Activity { activity: tracker.get_current_activity().cloned() },
+            Request::Pause { duration_secs: Some(0) } => {
+                Response::Error { message: "A snooze must last at least a second".to_string() }
+            },
+            Request::Pause { duration_secs } => {
+                match self.monitor.pause(duration_secs.map(Duration::from_secs)) {
+                    Ok(state) => {
+                        let message = match state.until {
+                            Some(until) => format!("Automatic tracking snoozed until {}", until.format("%H:%M")),
+                            None => "Automatic tracking paused".to_string(),
+                        };
+                        Response::Done { message }
+                    },
+                    Err(message) => Response::Error { message },
+                }
+            },
+            Request::Resume => {
+                let message = if self.monitor.resume() {
+                    "Automatic tracking resumed"
+                } else {
+                    "Automatic tracking was not paused"
+                };
+                Response::Done { message: message.to_string() }
+            },
+            Request::ShellEvent { event } => {
+                match event.phase {
+                    ShellPhase::Start => self.monitor.command_started(event),
+                    ShellPhase::End => {
+                        self.monitor.command_finished(event.shell_pid);
+                        tracker.database().save_shell_command(&event.to_command())?;
+                    },
+                }
+                Response::Done { message: "Recorded".to_string() }
+            },
+            Request::Status => {
+                let foreground = self.monitor.foreground();
+                Response::Status {
+                    status: DaemonStatus {
+                        pid: std::process::id(),
+                        running_since: self.running_since,
+                        paused: self.monitor.is_paused(),
+                        paused_until: self.monitor.pause_state().and_then(|p| p.until),
+                        foreground_app: foreground.app,
+                        foreground_title: foreground.window_title,
+                        foreground_url: foreground.url,
+                        manual_activity: tracker.get_current_activity().cloned(),
+                        sampling_interval_secs: self.sampling_interval.as_secs(),
+                    },
+                }
+            },
+        };
+
+        // Auto-tracking steps aside while a manual activity runs; the span it was
+        // sampling is saved on its next tick
+        self.monitor.set_manual_tracking(tracker.get_current_activity().is_some());
+        Ok(response)
+    }
+}
+
+// SIGUSR1 pauses automatic tracking until SIGUSR2 resumes it, for scripts and hotkeys
+// that would rather not speak the socket protocol
+pub async fn handle_pause_signals(monitor: MonitorHandle) -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut pause = signal(SignalKind::user_defined1())?;
+    let mut resume = signal(SignalKind::user_defined2())?;
+    loop {
+        tokio::select! {
+            _ = pause.recv() => {
+                // Only a snooze can be too long
+                let _ = monitor.pause(None);
+                println!("SIGUSR1 received, automatic tracking paused");
+            },
+            _ = resume.recv() => {
+                monitor.resume();
+                println!("SIGUSR2 received, automatic tracking resumed");
+            },
+        }
+    }
+}
+
+// Ctrl+C, SIGTERM from systemd or launchd, and SIGHUP from a closed terminal all stop the
+// daemon the same way. Handlers are installed before this returns, so no signal is missed
+// while the daemon is starting.
+pub fn shutdown_signal() -> std::io::Result<impl std::future::Future<Output = &'static str>> {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    Ok(async move {
+        tokio::select! {
+            _ = interrupt.recv() => "SIGINT",
+            _ = terminate.recv() => "SIGTERM",
+            _ = hangup.recv() => "SIGHUP",
+        }
+    })
+}
+
+// Only ever a socket: anything else at the path was refused by `listen` and is not ours
+pub fn remove_socket() {
+    use std::os::unix::fs::FileTypeExt;
+
+    let path = socket_path();
+    if std::fs::symlink_metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::monitor::AppMonitor;
+    use serde_json::json;
+
+    fn control_server() -> ControlServer {
+        let monitor = AppMonitor::new(Database::open_in_memory().unwrap()).handle();
+        let tracker = Tracker::with_database(Database::open_in_memory().unwrap()).unwrap();
+        ControlServer::with_tracker(tracker, monitor, Duration::from_secs(5))
+    }
+
+    fn start(productive: bool, weight: Option<i8>) -> Request {
+        Request::Start { name: "Review".to_string(), category: "Development".to_string(), tags: Vec::new(), productive, weight }
+    }
+
+    #[test]
+    fn requests_and_responses_keep_their_json_shape() {
+        assert_eq!(serde_json::to_value(start(true, Some(1))).unwrap(), json!({
+            "command": "start", "name": "Review", "category": "Development", "tags": [], "productive": true, "weight": 1,
+        }));
+        assert_eq!(serde_json::to_value(Request::Pause { duration_secs: Some(600) }).unwrap(), json!({ "command": "pause", "duration_secs": 600 }));
+        assert_eq!(serde_json::to_value(Request::Stop).unwrap(), json!({ "command": "stop" }));
+
+        // Clients from before weights and snoozes leave those fields out
+        let old = r#"{"command":"start","name":"Review","category":"Development","tags":[],"productive":false}"#;
+        assert!(matches!(serde_json::from_str(old).unwrap(), Request::Start { productive: false, weight: None, .. }));
+        assert!(matches!(serde_json::from_str(r#"{"command":"pause"}"#).unwrap(), Request::Pause { duration_secs: None }));
+        assert!(serde_json::from_str::<Request>(r#"{"command":"restart"}"#).is_err());
+
+        let done = Response::Done { message: "Recorded".to_string() };
+        assert_eq!(serde_json::to_value(&done).unwrap(), json!({ "result": "done", "message": "Recorded" }));
+        let reply = serde_json::to_string(&Response::Activity { activity: None }).unwrap();
+        assert!(matches!(serde_json::from_str(&reply).unwrap(), Response::Activity { activity: None }));
+    }
+
+    #[test]
+    fn snoozes_of_zero_seconds_are_refused() {
+        let server = control_server();
+        match server.try_handle(Request::Pause { duration_secs: Some(0) }).unwrap() {
+            Response::Error { message } => assert_eq!(message, "A snooze must last at least a second"),
+            other => panic!("unexpected response {:?}", other),
+        }
+        assert!(!server.monitor.is_paused());
+
+        assert!(matches!(server.try_handle(Request::Pause { duration_secs: Some(60) }).unwrap(), Response::Done { .. }));
+        assert!(server.monitor.pause_state().and_then(|p| p.until).is_some());
+    }
+
+    #[test]
+    fn a_weight_takes_precedence_over_the_productive_flag() {
+        let server = control_server();
+        let started = |request| match server.try_handle(request).unwrap() {
+            Response::Activity { activity: Some(activity) } => activity,
+            other => panic!("unexpected response {:?}", other),
+        };
+
+        let activity = started(start(true, Some(-2)));
+        assert_eq!((activity.weight, activity.is_productive), (-2, false));
+        assert_eq!(started(start(true, None)).weight, weight_from_flag(true));
+        assert_eq!(started(start(false, None)).weight, weight_from_flag(false));
+
+        // Starting stopped the earlier activities, and stopping returns the last one
+        match server.try_handle(Request::Stop).unwrap() {
+            Response::Activity { activity: Some(stopped) } => assert!(stopped.end_time.is_some()),
+            other => panic!("unexpected response {:?}", other),
+        }
+        let tracker = server.tracker.lock().unwrap();
+        let saved = tracker.database().get_activities(&Default::default()).unwrap();
+        assert_eq!(saved.iter().map(|a| a.weight).collect::<Vec<_>>(), [-2, weight_from_flag(true), weight_from_flag(false)]);
+        assert!(saved.iter().all(|a| a.end_time.is_some()));
+    }
+}
@@ -4,6 +4,21 @@ mod tracker;
 mod cli;
 mod reports;
 mod monitor;
+mod export;
+mod import;
+mod calendar;
+mod server;
+mod ipc;
+mod service;
+mod rules;
+mod shell;
+mod notify;
+mod focus;
+mod alerts;
+mod goals;
+mod dashboard;
+mod timeline;
+mod privacy;
 
 #[tokio::main]
 async fn main() {
@@ -1,99 +1,73 @@
 use std::process::Command;
 use std::str;
-use url::Url;
 
 pub struct BrowserMonitor;
 
 impl BrowserMonitor {
+    pub fn is_browser_app(app_name: &str) -> bool {
+        let app_lower = app_name.to_lowercase();
+        app_lower.contains("chrome") ||
+            app_lower.contains("safari") ||
+            app_lower.contains("firefox") ||
+            app_lower.contains("edge") ||
+            app_lower.contains("brave")
+    }
+    
+    // Host of a stored page address without "www.", e.g. "github.com". Addresses the privacy
+    // settings reduced to their host are hosts already; anything else gives None.
+    pub fn site_host(url: &str) -> Option<String> {
+        let host = match url::Url::parse(url) {
+            Ok(parsed) => parsed.host_str()?.to_lowercase(),
+            Err(_) if url.contains('.') && !url.contains(char::is_whitespace) && !url.contains('/') => url.to_lowercase(),
+            Err(_) => return None,
+        };
+        Some(host.trim_start_matches("www.").to_string())
+    }
+    
     // Detect browser and potentially return URL
     pub fn detect_browser_activity(app_name: &str) -> Option<(String, String)> {
-        println!("Debug: Attempting to detect browser activity for: {}", app_name);
-        
         // Check for specific browser based on app_name first
         let lower_app_name = app_name.to_lowercase();
         
         // Prioritize checking for the browser that's in focus based on app_name
         if lower_app_name.contains("brave") {
-            println!("Debug: Prioritizing Brave check based on app name");
             if let Some(browser_info) = Self::detect_brave() {
-                println!("Debug: Brave detected, returning info");
                 return Some(browser_info);
             }
         } else if lower_app_name.contains("chrome") {
-            println!("Debug: Prioritizing Chrome check based on app name");
             if let Some(browser_info) = Self::detect_chrome() {
-                println!("Debug: Chrome detected, returning info");
                 return Some(browser_info);
             }
         } else if lower_app_name.contains("safari") {
-            println!("Debug: Prioritizing Safari check based on app name");
             if let Some(browser_info) = Self::detect_safari() {
-                println!("Debug: Safari detected, returning info");
                 return Some(browser_info);
             }
         }
         
         // If we didn't match the specific browser, try the other browsers in order
         if !lower_app_name.contains("chrome") {
-            println!("Debug: Trying Chrome detection");
             if let Some(browser_info) = Self::detect_chrome() {
-                println!("Debug: Chrome detected, returning info");
                 return Some(browser_info);
             }
         }
         
         if !lower_app_name.contains("safari") {
-            println!("Debug: Trying Safari detection");
             if let Some(browser_info) = Self::detect_safari() {
-                println!("Debug: Safari detected, returning info");
                 return Some(browser_info);
             }
         }
         
         if !lower_app_name.contains("brave") {
-            println!("Debug: Trying Brave detection");
             if let Some(browser_info) = Self::detect_brave() {
-                println!("Debug: Brave detected, returning info");
                 return Some(browser_info);
             }
         }
         
-        println!("Debug: No browsers detected");
         None
     }
     
-    // Extract domain from URL
-    fn extract_domain(url_str: &str) -> String {
-        println!("Debug: Extracting domain from URL: {}", url_str);
-        
-        if url_str.is_empty() || url_str == "URL unavailable" {
-            return "unknown.domain".to_string();
-        }
-        
-        match Url::parse(url_str) {
-            Ok(url) => {
-                match url.host_str() {
-                    Some(host) => {
-                        println!("Debug: Extracted domain: {}", host);
-                        host.to_string()
-                    },
-                    None => {
-                        println!("Debug: No host in URL");
-                        "unknown.domain".to_string()
-                    }
-                }
-            },
-            Err(e) => {
-                println!("Debug: Error parsing URL {}: {}", url_str, e);
-                "unknown.domain".to_string()
-            }
-        }
-    }
-    
     // Run AppleScript and return the output
     fn run_applescript(script: &str) -> Result<String, String> {
-        println!("Debug: Running AppleScript: {}", script);
-        
         let output = Command::new("osascript")
             .arg("-e")
             .arg(script)
@@ -106,70 +80,51 @@ impl BrowserMonitor {
                         Ok(s) => s.trim().to_string(),
                         Err(_) => return Err("Invalid UTF-8 in AppleScript output".to_string()),
                     };
-                    println!("Debug: AppleScript output: {}", stdout);
                     Ok(stdout)
                 } else {
                     let stderr = match str::from_utf8(&output.stderr) {
                         Ok(s) => s.trim().to_string(),
                         Err(_) => "Unknown error".to_string(),
                     };
-                    println!("Debug: AppleScript error: {}", stderr);
                     Err(stderr)
                 }
             },
-            Err(e) => {
-                println!("Debug: Error running AppleScript: {}", e);
-                Err(e.to_string())
-            }
+            Err(e) => Err(e.to_string()),
         }
     }
 
     #[cfg(target_os = "macos")]
     fn detect_chrome() -> Option<(String, String)> {
-        println!("Debug: Attempting to detect Chrome browser");
-        
         // More specific check for Chrome to avoid detecting Brave
         let output = Command::new("pgrep")
             .arg("-if")
             .arg("Google Chrome$")
             .output();
             
-        match output {
-            Ok(output) => {
-                let stdout_len = output.stdout.len();
-                println!("Debug: Chrome pgrep output length: {}", stdout_len);
-                if stdout_len > 0 {
-                    println!("Debug: Chrome detected as running");
-                    
-                    // Try to get the URL from Chrome using AppleScript
-                    let script = r#"
-                        tell application "Google Chrome"
-                            if it is running then
-                                try
-                                    get URL of active tab of first window
-                                on error
-                                    return "URL unavailable"
-                                end try
-                            else
-                                return "Chrome not running"
-                            end if
-                        end tell
-                    "#;
-                    
-                    let url = match Self::run_applescript(script) {
-                        Ok(url) if !url.is_empty() && url != "Chrome not running" => {
-                            let domain = Self::extract_domain(&url);
-                            domain
-                        },
-                        _ => "URL unavailable".to_string()
-                    };
-                    
-                    return Some(("Google Chrome".to_string(), url));
-                } else {
-                    println!("Debug: Chrome not detected as running");
-                }
-            },
-            Err(e) => println!("Debug: Error running pgrep for Chrome: {}", e),
+        if let Ok(output) = output {
+            if !output.stdout.is_empty() {
+                // Try to get the URL from Chrome using AppleScript
+                let script = r#"
+                    tell application "Google Chrome"
+                        if it is running then
+                            try
+                                get URL of active tab of first window
+                            on error
+                                return "URL unavailable"
+                            end try
+                        else
+                            return "Chrome not running"
+                        end if
+                    end tell
+                "#;
+                
+                let url = match Self::run_applescript(script) {
+                    Ok(url) if !url.is_empty() && url != "Chrome not running" => url,
+                    _ => "URL unavailable".to_string()
+                };
+                
+                return Some(("Google Chrome".to_string(), url));
+            }
         }
         
         None
@@ -182,49 +137,36 @@ impl BrowserMonitor {
     
     #[cfg(target_os = "macos")]
     fn detect_safari() -> Option<(String, String)> {
-        println!("Debug: Attempting to detect Safari browser");
         // Check if Safari is running
         let output = Command::new("pgrep")
             .arg("-i")
             .arg("Safari")
             .output();
             
-        match output {
-            Ok(output) => {
-                let stdout_len = output.stdout.len();
-                println!("Debug: Safari pgrep output length: {}", stdout_len);
-                if stdout_len > 0 {
-                    println!("Debug: Safari detected as running");
-                    
-                    // Try to get the URL from Safari using AppleScript
-                    let script = r#"
-                        tell application "Safari"
-                            if it is running then
-                                try
-                                    get URL of current tab of first window
-                                on error
-                                    return "URL unavailable"
-                                end try
-                            else
-                                return "Safari not running"
-                            end if
-                        end tell
-                    "#;
-                    
-                    let url = match Self::run_applescript(script) {
-                        Ok(url) if !url.is_empty() && url != "Safari not running" => {
-                            let domain = Self::extract_domain(&url);
-                            domain
-                        },
-                        _ => "URL unavailable".to_string()
-                    };
-                    
-                    return Some(("Safari".to_string(), url));
-                } else {
-                    println!("Debug: Safari not detected as running");
-                }
-            },
-            Err(e) => println!("Debug: Error running pgrep for Safari: {}", e),
+        if let Ok(output) = output {
+            if !output.stdout.is_empty() {
+                // Try to get the URL from Safari using AppleScript
+                let script = r#"
+                    tell application "Safari"
+                        if it is running then
+                            try
+                                get URL of current tab of first window
+                            on error
+                                return "URL unavailable"
+                            end try
+                        else
+                            return "Safari not running"
+                        end if
+                    end tell
+                "#;
+                
+                let url = match Self::run_applescript(script) {
+                    Ok(url) if !url.is_empty() && url != "Safari not running" => url,
+                    _ => "URL unavailable".to_string()
+                };
+                
+                return Some(("Safari".to_string(), url));
+            }
         }
         
         None
@@ -237,50 +179,37 @@ impl BrowserMonitor {
     
     #[cfg(target_os = "macos")]
     fn detect_brave() -> Option<(String, String)> {
-        println!("Debug: Attempting to detect Brave browser");
         // Check if Brave is running with more specific pattern
         let output = Command::new("pgrep")
             .arg("-if")
             .arg("Brave Browser$")
             .output();
             
-        match output {
-            Ok(output) => {
-                let stdout_len = output.stdout.len();
-                println!("Debug: Brave pgrep output length: {}", stdout_len);
-                if stdout_len > 0 {
-                    println!("Debug: Brave detected as running");
-                    
-                    // Try to get the URL from Brave Browser using AppleScript
-                    // Brave is Chromium-based so we can use similar script as Chrome
-                    let script = r#"
-                        tell application "Brave Browser"
-                            if it is running then
-                                try
-                                    get URL of active tab of first window
-                                on error
-                                    return "URL unavailable"
-                                end try
-                            else
-                                return "Brave not running"
-                            end if
-                        end tell
-                    "#;
-                    
-                    let url = match Self::run_applescript(script) {
-                        Ok(url) if !url.is_empty() && url != "Brave not running" => {
-                            let domain = Self::extract_domain(&url);
-                            domain
-                        },
-                        _ => "URL unavailable".to_string()
-                    };
-                    
-                    return Some(("Brave Browser".to_string(), url));
-                } else {
-                    println!("Debug: Brave not detected as running");
-                }
-            },
-            Err(e) => println!("Debug: Error running pgrep for Brave: {}", e),
+        if let Ok(output) = output {
+            if !output.stdout.is_empty() {
+                // Try to get the URL from Brave Browser using AppleScript
+                // Brave is Chromium-based so we can use similar script as Chrome
+                let script = r#"
+                    tell application "Brave Browser"
+                        if it is running then
+                            try
+                                get URL of active tab of first window
+                            on error
+                                return "URL unavailable"
+                            end try
+                        else
+                            return "Brave not running"
+                        end if
+                    end tell
+                "#;
+                
+                let url = match Self::run_applescript(script) {
+                    Ok(url) if !url.is_empty() && url != "Brave not running" => url,
+                    _ => "URL unavailable".to_string()
+                };
+                
+                return Some(("Brave Browser".to_string(), url));
+            }
         }
         
         None
@@ -291,11 +220,10 @@ impl BrowserMonitor {
         None
     }
     
-    // Categorize URLs based on domain into specific categories
+    // Categorize URLs based on domain into specific categories. Only the host counts: the query
+    // of a search or redirect page can name any other site.
     pub fn categorize_url(url: &str) -> String {
-        let domain = url.to_lowercase();
-        
-        println!("Debug: Categorizing domain: {}", domain);
+        let domain = Self::site_host(url).unwrap_or_else(|| url.to_lowercase());
         
         // Development related sites
         if domain.contains("github.com") || 
@@ -420,18 +348,19 @@ impl BrowserMonitor {
         "Web Browsing".to_string()
     }
     
-    // Judge if a URL is likely to be productive
-    // Determine if a URL is likely to be productive based on its category and content
-    pub fn is_url_productive(url: &str, category: &str) -> bool {
+    // Productivity weight of a page, from its category and, failing that, its address
+    pub fn url_weight(url: &str, category: &str) -> i8 {
         match category {
-            // Productive categories
-            "Development" | "Research" | "Communication" | "Productivity" | "Information" => true,
-            
-            // Non-productive categories
-            "Entertainment" | "Social Media" => false,
+            "Development" | "Research" => 2,
+            "Productivity" | "Information" => 1,
+            // Email and chat are work, but not focused work
+            "Communication" => 0,
+            "Entertainment" | "Social Media" => -2,
             
-            // For uncategorized URLs, use heuristics
+            // For uncategorized URLs, use heuristics on the host
             _ => {
+                let domain = Self::site_host(url).unwrap_or_else(|| url.to_lowercase());
+                
                 // Check for obviously non-productive keywords
                 let non_productive_keywords = [
                     "game", "play", "video", "stream", "watch", "movie", "entertainment",
@@ -446,15 +375,34 @@ impl BrowserMonitor {
                 
                 // Check if any non-productive keywords are in the URL
                 let has_non_productive = non_productive_keywords.iter()
-                    .any(|&keyword| url.contains(keyword));
+                    .any(|&keyword| domain.contains(keyword));
                 
                 // Check if any productive keywords are in the URL
                 let has_productive = productive_keywords.iter()
-                    .any(|&keyword| url.contains(keyword));
+                    .any(|&keyword| domain.contains(keyword));
                 
-                // If has productive keywords and no non-productive ones, likely productive
-                // Otherwise, default to not productive
-                has_productive && !has_non_productive
+                // Anything matching neither list is neutral
+                match (has_productive, has_non_productive) {
+                    (true, false) => 1,
+                    (_, true) => -1,
+                    (false, false) => 0,
+                }
             }
         }
 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_classified_by_host_not_query() {
+        assert_eq!(BrowserMonitor::categorize_url("https://www.youtube.com/watch?v=abc"), "Entertainment");
+        let search = "https://www.example.com/search?q=youtube+funny+videos";
+        let category = BrowserMonitor::categorize_url(search);
+        assert_ne!(category, "Entertainment");
+        assert_eq!(BrowserMonitor::url_weight(search, &category), 0);
+        assert_eq!(BrowserMonitor::categorize_url("https://github.com/login?return_to=reddit.com"), "Development");
+    }
+}
@@ -0,0 +1,241 @@
+// Attributes development time to the git repository being worked in. The focused terminal or
+// editor is followed down to its newest descendant (the shell, then whatever runs in it), and
+// the working directories along that chain are searched for an enclosing repository.
+
+use super::title::EditorContext;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, System, UpdateKind};
+
+// Deep enough for terminal -> shell -> tmux -> shell -> editor
+const MAX_DEPTH: usize = 16;
+// How long a process chain is trusted before the whole process list is scanned for new children
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitContext {
+    // Path of the working tree root
+    pub repo: String,
+    // None only when HEAD cannot be read
+    pub branch: Option<String>,
+}
+
+pub fn is_terminal_app(app_name: &str) -> bool {
+    let app_lower = app_name.to_lowercase();
+    ["terminal", "iterm", "alacritty", "kitty", "wezterm", "konsole", "xterm", "foot", "tilix", "ghostty", "warp"]
+        .iter()
+        .any(|t| app_lower.contains(t))
+}
+
+// Runs on every tick, so the process list is kept between calls. All processes are only listed
+// again when the focused app changes, a process in its chain exits or RESCAN_INTERVAL passes;
+// otherwise just the processes in the chain are read, for directories changed with `cd`.
+pub struct GitResolver {
+    system: System,
+    chain: Vec<Pid>,
+    scanned_at: Option<Instant>,
+}
+
+impl GitResolver {
+    pub fn new() -> Self {
+        // sysinfo keeps a /proc file open per process it has seen unless told otherwise
+        sysinfo::set_open_files_limit(0);
+        GitResolver { system: System::new(), chain: Vec::new(), scanned_at: None }
+    }
+
+    // An editor showing a directory path in its title (Vim does) is the best hint; otherwise the
+    // working directories of the process and its newest descendants are tried, innermost first
+    pub fn resolve(&mut self, pid: Option<u32>, editor: Option<&EditorContext>) -> Option<GitContext> {
+        if let Some(dir) = editor.and_then(|e| e.workspace.as_deref()).and_then(expand_path) {
+            if let Some(context) = find_repository(&dir) {
+                return Some(context);
+            }
+        }
+
+        let pid = Pid::from_u32(pid?);
+        // Working directories come from /proc/<pid>/cwd on Linux and are not read by default
+        let with_cwd = ProcessRefreshKind::new().with_cwd(UpdateKind::Always);
+        let stale = self.chain.first() != Some(&pid)
+            || self.scanned_at.is_none_or(|at| at.elapsed() >= RESCAN_INTERVAL)
+            || !self.chain.iter().all(|pid| self.system.refresh_process_specifics(*pid, with_cwd));
+        if stale {
+            self.system.refresh_processes_specifics(ProcessRefreshKind::new());
+            self.chain = process_chain(&self.system, pid);
+            for pid in &self.chain {
+                self.system.refresh_process_specifics(*pid, with_cwd);
+            }
+            self.scanned_at = Some(Instant::now());
+        }
+
+        self.chain
+            .iter()
+            .rev()
+            .filter_map(|pid| self.system.process(*pid).and_then(|p| p.cwd()))
+            .find_map(find_repository)
+    }
+}
+
+// The process followed by its most recently started child, grandchild and so on
+fn process_chain(system: &System, root: Pid) -> Vec<Pid> {
+    let mut children: HashMap<Pid, Vec<(u64, Pid)>> = HashMap::new();
+    for (pid, process) in system.processes() {
+        // Threads show up as processes on Linux; only real children have their own directory
+        if process.thread_kind().is_some() {
+            continue;
+        }
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push((process.start_time(), *pid));
+        }
+    }
+
+    let mut chain = vec![root];
+    while chain.len() < MAX_DEPTH {
+        let newest = children
+            .get(chain.last().unwrap())
+            .and_then(|kids| kids.iter().max_by_key(|(started, _)| *started))
+            .map(|(_, pid)| *pid);
+        match newest {
+            Some(pid) if !chain.contains(&pid) => chain.push(pid),
+            _ => break,
+        }
+    }
+    chain
+}
+
+// Walks up from `dir` to the first directory holding `.git`, which is a directory for normal
+// clones and a file pointing elsewhere for worktrees and submodules
+pub fn find_repository(dir: &Path) -> Option<GitContext> {
+    for root in dir.ancestors() {
+        let dot_git = root.join(".git");
+        let git_dir = if dot_git.is_dir() {
+            dot_git
+        } else if dot_git.is_file() {
+            let contents = std::fs::read_to_string(&dot_git).ok()?;
+            let target = contents.trim().strip_prefix("gitdir:")?.trim();
+            root.join(target)
+        } else {
+            continue;
+        };
+
+        return Some(GitContext {
+            repo: root.to_string_lossy().into_owned(),
+            branch: read_branch(&git_dir),
+        });
+    }
+    None
+}
+
+// Branch name from HEAD, or the short commit id when HEAD is detached
+fn read_branch(git_dir: &Path) -> Option<String> {
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    match head.strip_prefix("ref:") {
+        Some(reference) => {
+            let reference = reference.trim();
+            Some(reference.strip_prefix("refs/heads/").unwrap_or(reference).to_string())
+        },
+        None => Some(head.chars().take(7).collect()).filter(|s: &String| !s.is_empty()),
+    }
+}
+
+fn expand_path(path: &str) -> Option<PathBuf> {
+    if let Some(rest) = path.strip_prefix("~/") {
+        return dirs::home_dir().map(|home| home.join(rest));
+    }
+    Some(PathBuf::from(path)).filter(|p| p.is_absolute())
+}
+
+// "~/src/crate" rather than the full home path, for reports
+pub fn display_repo(repo: &str) -> String {
+    match dirs::home_dir().and_then(|home| Path::new(repo).strip_prefix(home).ok().map(|p| p.to_path_buf())) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => repo.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A scratch directory removed again when the test ends
+    struct Scratch(PathBuf);
+
+    impl Scratch {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("productivity-tracker-git-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            Scratch(dir)
+        }
+
+        fn write(&self, path: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn context(repo: &Path, branch: &str) -> Option<GitContext> {
+        Some(GitContext { repo: repo.to_string_lossy().into_owned(), branch: Some(branch.to_string()) })
+    }
+
+    #[test]
+    fn finds_a_clone_from_a_directory_inside_it() {
+        let scratch = Scratch::new("clone");
+        scratch.write("crate/.git/HEAD", "ref: refs/heads/main\n");
+        let nested = scratch.0.join("crate/src/monitor");
+        fs::create_dir_all(&nested).unwrap();
+
+        assert_eq!(find_repository(&nested), context(&scratch.0.join("crate"), "main"));
+    }
+
+    #[test]
+    fn follows_the_gitdir_file_of_a_worktree() {
+        let scratch = Scratch::new("worktree");
+        scratch.write("crate/.git/HEAD", "ref: refs/heads/main\n");
+        scratch.write("crate/.git/worktrees/feature/HEAD", "ref: refs/heads/feature/login\n");
+        scratch.write("feature/.git", "gitdir: ../crate/.git/worktrees/feature\n");
+        let absolute = scratch.0.join("crate/.git/worktrees/feature");
+        scratch.write("absolute/.git", &format!("gitdir: {}\n", absolute.display()));
+
+        assert_eq!(find_repository(&scratch.0.join("feature")), context(&scratch.0.join("feature"), "feature/login"));
+        assert_eq!(find_repository(&scratch.0.join("absolute")), context(&scratch.0.join("absolute"), "feature/login"));
+    }
+
+    #[test]
+    fn a_detached_head_reads_as_the_short_commit_id() {
+        let scratch = Scratch::new("detached");
+        scratch.write("crate/.git/HEAD", "3f1c2a9e5b7d4c8f9a0b1c2d3e4f5a6b7c8d9e0f\n");
+
+        assert_eq!(find_repository(&scratch.0.join("crate")), context(&scratch.0.join("crate"), "3f1c2a9"));
+    }
+    #[test]
+    fn resolves_the_directory_a_process_runs_in_and_notices_a_new_one() {
+        let scratch = Scratch::new("process");
+        scratch.write("first/.git/HEAD", "ref: refs/heads/main\n");
+        scratch.write("second/.git/HEAD", "ref: refs/heads/next\n");
+        let spawn = |dir: &str| std::process::Command::new("sleep").arg("30").current_dir(scratch.0.join(dir)).spawn().unwrap();
+
+        let mut resolver = GitResolver::new();
+        let mut first = spawn("first");
+        assert_eq!(resolver.resolve(Some(first.id()), None), context(&scratch.0.join("first"), "main"));
+        // Served from the chain found above
+        assert_eq!(resolver.resolve(Some(first.id()), None), context(&scratch.0.join("first"), "main"));
+
+        let mut second = spawn("second");
+        assert_eq!(resolver.resolve(Some(second.id()), None), context(&scratch.0.join("second"), "next"));
+        for child in [&mut first, &mut second] {
+            child.kill().unwrap();
+            child.wait().unwrap();
+        }
+    }
+}
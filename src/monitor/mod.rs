@@ -1,16 +1,26 @@
-mod browser;
+pub mod browser;
+pub mod git;
+pub mod title;
 
-use crate::data::Activity;
+use crate::alerts::AlertMonitor;
+use crate::calendar;
+use crate::goals::GoalWatcher;
+use crate::data::{Activity, Gap, Heartbeat};
 use crate::db::Database;
-use chrono::Local;
+use crate::privacy::Redactor;
+use crate::rules::RuleSet;
+use self::git::{GitContext, GitResolver};
+use crate::service;
+use crate::shell::{self, ShellEvent};
+use chrono::{DateTime, Local};
 use rusqlite::Result;
 use std::collections::HashMap;
 use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::time;
-use sysinfo::{System, Process};
-use self::browser::BrowserMonitor;
+use sysinfo::{Pid, System, Process};
+pub use self::browser::BrowserMonitor;
 #[cfg(target_os = "macos")]
 use {
     cocoa::base::{id, nil},
@@ -22,27 +32,197 @@ pub struct AppMonitor {
     db: Arc<Mutex<Database>>,
     current_app: String,
     current_browser_url: Option<String>,
+    current_window_title: Option<String>,
+    current_git: Option<GitContext>,
+    git: GitResolver,
+    current_command: Option<String>,
     last_switch_time: Instant,
+    // Wall-clock time matching last_switch_time, used for the saved span and the heartbeat
+    span_started_at: DateTime<Local>,
     last_save_time: Instant,
     is_running: Arc<Mutex<bool>>,
+    pause: Arc<Mutex<Option<PauseState>>>,
+    ended_pauses: Arc<Mutex<Vec<Gap>>>,
+    manual_tracking: Arc<Mutex<bool>>,
+    foreground: Arc<Mutex<Foreground>>,
+    // Commands running in hooked shells, by shell pid
+    shell_commands: Arc<Mutex<HashMap<u32, ShellEvent>>>,
     app_durations: HashMap<String, Duration>,
+    rules: RuleSet,
+    redactor: Redactor,
+    alerts: AlertMonitor,
+    goals: GoalWatcher,
     pub sampling_interval: Duration,
 }
 
+// What was in front during a span, as it gets saved
+struct Sample<'a> {
+    app: &'a str,
+    browser_url: Option<&'a str>,
+    window_title: Option<&'a str>,
+    git_repo: Option<&'a str>,
+    git_branch: Option<&'a str>,
+    command: Option<&'a str>,
+}
+
+// What is in front right now, for the control socket. Title and URL are in the form the privacy
+// settings allow to be stored.
+#[derive(Debug, Clone, Default)]
+pub struct Foreground {
+    pub app: String,
+    pub window_title: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PauseState {
+    pub since: DateTime<Local>,
+    // Set for a snooze, which resumes on its own
+    pub until: Option<DateTime<Local>>,
+}
+
+// Shared control over a running monitor, used by the control socket and signal handlers
+#[derive(Clone)]
+pub struct MonitorHandle {
+    pause: Arc<Mutex<Option<PauseState>>>,
+    ended_pauses: Arc<Mutex<Vec<Gap>>>,
+    manual_tracking: Arc<Mutex<bool>>,
+    foreground: Arc<Mutex<Foreground>>,
+    shell_commands: Arc<Mutex<HashMap<u32, ShellEvent>>>,
+}
+
+impl MonitorHandle {
+    // Pausing again while paused changes when the pause ends but keeps when it began. A snooze
+    // ending past the last date chrono can represent is refused.
+    pub fn pause(&self, length: Option<Duration>) -> std::result::Result<PauseState, String> {
+        let now = Local::now();
+        let until = match length {
+            Some(length) => Some(
+                chrono::Duration::from_std(length).ok()
+                    .and_then(|l| now.checked_add_signed(l))
+                    .ok_or_else(|| format!("a snooze of {} days is too long", length.as_secs() / 86400))?,
+            ),
+            None => None,
+        };
+        
+        let mut pause = self.pause.lock().unwrap();
+        let state = PauseState {
+            since: pause.map(|p| p.since).unwrap_or(now),
+            until,
+        };
+        *pause = Some(state);
+        Ok(state)
+    }
+    
+    // Returns false when tracking was not paused
+    pub fn resume(&self) -> bool {
+        self.end_pause(Local::now())
+    }
+    
+    pub fn pause_state(&self) -> Option<PauseState> {
+        *self.pause.lock().unwrap()
+    }
+    
+    // Carries on a pause from before a restart. A snooze that is over by now ends on the next
+    // tick, as of when it was due.
+    fn restore_pause(&self, state: PauseState) {
+        *self.pause.lock().unwrap() = Some(state);
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.pause.lock().unwrap().is_some()
+    }
+    
+    // Ends a snooze whose time is up, as of the moment it was due
+    fn expire_snooze(&self) {
+        let due = self.pause_state().and_then(|p| p.until).filter(|until| *until <= Local::now());
+        if let Some(until) = due {
+            self.end_pause(until);
+        }
+    }
+    
+    fn end_pause(&self, end: DateTime<Local>) -> bool {
+        let ended = match self.pause.lock().unwrap().take() {
+            Some(state) => state,
+            None => return false,
+        };
+        
+        // The monitor loop owns the database, so the gap is handed over to be written there
+        self.ended_pauses.lock().unwrap().push(Gap {
+            id: None,
+            start_time: ended.since,
+            end_time: end.max(ended.since),
+            reason: if ended.until.is_some() { "snoozed".to_string() } else { "paused".to_string() },
+        });
+        true
+    }
+    
+    // While a manual activity runs it owns the time, so automatic samples are not recorded
+    pub fn set_manual_tracking(&self, active: bool) {
+        *self.manual_tracking.lock().unwrap() = active;
+    }
+    
+    pub fn foreground(&self) -> Foreground {
+        self.foreground.lock().unwrap().clone()
+    }
+    
+    pub fn command_started(&self, event: ShellEvent) {
+        self.shell_commands.lock().unwrap().insert(event.shell_pid, event);
+    }
+    
+    pub fn command_finished(&self, shell_pid: u32) {
+        self.shell_commands.lock().unwrap().remove(&shell_pid);
+    }
+    
+    // The most recently started command still running. Which terminal tab has focus is not
+    // known, so the newest command stands for the terminal's work.
+    fn running_command(&self) -> Option<String> {
+        let mut commands = self.shell_commands.lock().unwrap();
+        // A shell closed mid-command never reports its end
+        let mut system = System::new();
+        commands.retain(|pid, _| system.refresh_process(Pid::from_u32(*pid)));
+        commands.values().max_by_key(|e| e.start_time).map(|e| e.command.clone())
+    }
+}
+
 impl AppMonitor {
     pub fn new(db: Database) -> Self {
         Self {
             db: Arc::new(Mutex::new(db)),
             current_app: String::new(),
             current_browser_url: None,
+            current_window_title: None,
+            current_git: None,
+            git: GitResolver::new(),
+            current_command: None,
             last_switch_time: Instant::now(),
+            span_started_at: Local::now(),
             last_save_time: Instant::now(),
             is_running: Arc::new(Mutex::new(false)),
+            pause: Arc::new(Mutex::new(None)),
+            ended_pauses: Arc::new(Mutex::new(Vec::new())),
+            manual_tracking: Arc::new(Mutex::new(false)),
+            foreground: Arc::new(Mutex::new(Foreground::default())),
+            shell_commands: Arc::new(Mutex::new(HashMap::new())),
             app_durations: HashMap::new(),
+            rules: RuleSet::load_or_warn(),
+            redactor: Redactor::load_or_warn(),
+            alerts: AlertMonitor::load_or_warn(),
+            goals: GoalWatcher::new(),
             sampling_interval: Duration::from_secs(5), // Check every 5 seconds
         }
     }
     
+    pub fn handle(&self) -> MonitorHandle {
+        MonitorHandle {
+            pause: Arc::clone(&self.pause),
+            ended_pauses: Arc::clone(&self.ended_pauses),
+            manual_tracking: Arc::clone(&self.manual_tracking),
+            foreground: Arc::clone(&self.foreground),
+            shell_commands: Arc::clone(&self.shell_commands),
+        }
+    }
+    
     pub async fn start_monitoring(&mut self) -> Result<()> {
         println!("Starting background monitoring...");
         
@@ -57,6 +237,11 @@ impl AppMonitor {
         // Start the monitoring loop
         while *is_running.lock().unwrap() {
             self.check_active_application().await?;
+            self.beat()?;
+            if let Err(e) = self.goals.check(&self.db.lock().unwrap()) {
+                eprintln!("Failed to check goals: {}", e);
+            }
+            service::notify::watchdog();
             time::sleep(self.sampling_interval).await;
         }
         
@@ -69,51 +254,165 @@ impl AppMonitor {
         println!("Background monitoring stopped.");
     }
     
+    // Saves the span in progress, ends any pause at this moment and closes the database.
+    // Without the heartbeat left behind, the next start has nothing to recover.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.stop_monitoring();
+        self.handle().end_pause(Local::now());
+        self.record_ended_pauses()?;
+        self.flush_current_span(Instant::now())?;
+        self.db.lock().unwrap().clear_heartbeat()?;
+        
+        match Arc::try_unwrap(self.db) {
+            Ok(db) => db.into_inner().unwrap_or_else(|e| e.into_inner()).close(),
+            Err(_) => Ok(()),
+        }
+    }
+    
+    // A heartbeat left by a daemon that crashed or was killed still describes the span it was
+    // sampling; that span is saved up to the last beat, the last moment it is known to have run.
+    // A pause it was in is picked up again, so its gap still starts when the pause began.
+    pub fn recover_interrupted_span(&self) -> Result<Option<Activity>> {
+        let db = self.db.lock().unwrap();
+        let heartbeat = match db.get_heartbeat()? {
+            Some(heartbeat) => heartbeat,
+            None => return Ok(None),
+        };
+        db.clear_heartbeat()?;
+        drop(db);
+        
+        if let Some(since) = heartbeat.paused_since {
+            println!("Automatic tracking stays paused as it was since {}", since.format("%H:%M:%S"));
+            self.handle().restore_pause(PauseState { since, until: heartbeat.paused_until });
+        }
+        
+        match (heartbeat.app, heartbeat.span_start) {
+            (Some(app), Some(start)) if start < heartbeat.time => {
+                let sample = Sample {
+                    app: &app,
+                    browser_url: heartbeat.browser_url.as_deref(),
+                    window_title: heartbeat.window_title.as_deref(),
+                    git_repo: heartbeat.git_repo.as_deref(),
+                    git_branch: heartbeat.git_branch.as_deref(),
+                    command: heartbeat.command.as_deref(),
+                };
+                let activity = self.save_activity(&sample, start, heartbeat.time, true)?;
+                Ok(Some(activity))
+            },
+            _ => Ok(None),
+        }
+    }
+    
+    fn beat(&self) -> Result<()> {
+        let sampling = !self.current_app.is_empty();
+        let pause = *self.pause.lock().unwrap();
+        let browser_url = self.current_browser_url.as_deref().filter(|_| sampling).map(|url| {
+            let (category, _) = Self::classify(&self.rules, &self.current_app, self.current_window_title.as_deref(), Some(url), None);
+            self.redactor.url(url, &category)
+        });
+        self.db.lock().unwrap().save_heartbeat(&Heartbeat {
+            time: Local::now(),
+            app: Some(self.current_app.clone()).filter(|_| sampling),
+            browser_url,
+            window_title: self.current_window_title.as_deref().filter(|_| sampling).map(|t| self.redactor.title(&self.current_app, t)),
+            git_repo: self.current_git.as_ref().map(|g| g.repo.clone()).filter(|_| sampling),
+            git_branch: self.current_git.as_ref().and_then(|g| g.branch.clone()).filter(|_| sampling),
+            command: self.current_command.clone().filter(|_| sampling),
+            span_start: Some(self.span_started_at).filter(|_| sampling),
+            paused_since: pause.map(|p| p.since),
+            paused_until: pause.and_then(|p| p.until),
+        })
+    }
+    
+    fn publish_foreground(&self, app: String, window_title: Option<&str>, browser_url: Option<&str>) {
+        let url = browser_url.map(|url| {
+            let (category, _) = Self::classify(&self.rules, &app, window_title, Some(url), None);
+            self.redactor.url(url, &category)
+        });
+        let window_title = window_title.map(|t| self.redactor.title(&app, t));
+        *self.foreground.lock().unwrap() = Foreground { app, window_title, url };
+    }
+    
+    fn begin_span(&mut self, now: Instant) {
+        self.last_switch_time = now;
+        self.span_started_at = Local::now();
+    }
+    
     async fn check_active_application(&mut self) -> Result<()> {
-        // First, detect the foreground app
-        let app_name = self.get_foreground_app();
-        println!("Debug: Detected foreground app: {}", app_name);
         let now = Instant::now();
+        let handle = self.handle();
+        handle.expire_snooze();
+        self.record_ended_pauses()?;
+        
+        // Commands that finished while no daemon was running
+        match shell::drain_spool(&self.db.lock().unwrap()) {
+            Ok(0) => {},
+            Ok(count) => println!("Stored {} spooled shell commands", count),
+            Err(e) => eprintln!("Failed to read the shell spool: {}", e),
+        }
+        
+        // While paused or while a manual activity runs, nothing is sampled.
+        // The span in progress when that started is saved once, then dropped.
+        let paused = handle.is_paused();
+        let suspended = paused || *self.manual_tracking.lock().unwrap();
+        if suspended {
+            // Focus sessions run as manual activities and still watch what is in front
+            if !paused {
+                let (app_name, _) = self.get_foreground_app();
+                let browser_url = Some(&app_name)
+                    .filter(|app| BrowserMonitor::is_browser_app(app))
+                    .and_then(|app| BrowserMonitor::detect_browser_activity(app))
+                    .map(|(_, url)| url);
+                let window_title = self.get_window_title();
+                self.publish_foreground(app_name, window_title.as_deref(), browser_url.as_deref());
+            }
+            self.flush_current_span(now)?;
+            self.alerts.reset();
+            self.current_app.clear();
+            self.current_browser_url = None;
+            self.current_window_title = None;
+            self.current_git = None;
+            self.current_command = None;
+            return Ok(());
+        }
+        
+        // First, detect the foreground app
+        let (app_name, pid) = self.get_foreground_app();
         
         // Check if this is a browser and try to get URL info
         let mut browser_url = None;
-        let app_lower = app_name.to_lowercase();
-        println!("Debug: Checking if app is a browser: {}", app_name);
-        if app_lower.contains("chrome") || 
-           app_lower.contains("safari") || 
-           app_lower.contains("firefox") ||
-           app_lower.contains("edge") ||
-           app_lower.contains("brave") {
-            
-            println!("Debug: App detected as browser: {}", app_name);
-            if let Some((browser_name, url)) = BrowserMonitor::detect_browser_activity(&app_name) {
-                // The real URL is not accessible for privacy reasons, but we can 
-                // at least record we detected browser activity
-                println!("Debug: Browser activity detected: {} with URL: {}", browser_name, url);
+        if BrowserMonitor::is_browser_app(&app_name) {
+            if let Some((_, url)) = BrowserMonitor::detect_browser_activity(&app_name) {
+                // Kept whole for classification and never logged; it is redacted before it is stored
                 browser_url = Some(url);
-            } else {
-                println!("Debug: No browser activity detected for {}", app_name);
             }
-        } else {
-            println!("Debug: App not detected as browser: {}", app_name);
         }
         
+        let window_title = self.get_window_title();
+        self.publish_foreground(app_name.clone(), window_title.as_deref(), browser_url.as_deref());
+        
+        // Development time is attributed to the repository the terminal or editor is working in
+        let editor = window_title.as_deref().and_then(|t| title::parse_editor_title(&app_name, t));
+        let git_context = if editor.is_some() || git::is_terminal_app(&app_name) || Self::categorize_app(&app_name, None, None) == "Development" {
+            self.git.resolve(pid, editor.as_ref())
+        } else {
+            None
+        };
+        
+        let command = if git::is_terminal_app(&app_name) { handle.running_command() } else { None };
+        
         // If the app changed, record the previous app's duration
         let app_changed = !self.current_app.is_empty() && self.current_app != app_name;
         let url_changed = self.current_browser_url != browser_url;
+        // A new title usually means another file, document or tab in the same app
+        let title_changed = !self.current_app.is_empty() && self.current_window_title != window_title;
+        let git_changed = !self.current_app.is_empty() && self.current_git != git_context;
+        let command_changed = !self.current_app.is_empty() && self.current_command != command;
         let time_threshold_reached = now.duration_since(self.last_save_time) >= Duration::from_secs(30);
         
-        println!("Debug: Current app: '{}', New app: '{}'", self.current_app, app_name);
-        println!("Debug: Current URL: '{:?}', New URL: '{:?}'", self.current_browser_url, browser_url);
-        println!("Debug: app_changed: {}, url_changed: {}, time_threshold_reached: {}", 
-                 app_changed, url_changed, time_threshold_reached);
-        
-        if app_changed || url_changed || time_threshold_reached {
+        if app_changed || url_changed || title_changed || git_changed || command_changed || time_threshold_reached {
             let duration = now.duration_since(self.last_switch_time);
             
-            // Update the app duration in our tracking map
-            *self.app_durations.entry(self.current_app.clone()).or_insert(Duration::from_secs(0)) += duration;
-            
             // Log the app switch or URL change
             if app_changed {
                 println!("Switched from {} to {} (used for {:?})", 
@@ -123,54 +422,151 @@ impl AppMonitor {
                     app_name, duration);
             }
             
-            // Record completed activity
-            let activity_name = if let Some(_url) = &self.current_browser_url {
-                // Use browser + URL indicator for naming
-                format!("{} - Web Browsing", self.current_app)
-            } else {
-                self.current_app.clone()
-            };
-            
-            self.save_activity(&activity_name, duration)?;
-            self.last_save_time = now;
+            self.flush_current_span(now)?;
         }
         
-        // Update the current app and time
+        // A span runs from the sample that started it until it is saved, so samples in between
+        // leave its start alone
+        if self.current_app.is_empty() {
+            self.begin_span(now);
+        }
         self.current_app = app_name;
         self.current_browser_url = browser_url;
-        self.last_switch_time = now;
+        self.current_window_title = window_title;
+        self.current_git = git_context;
+        self.current_command = command;
         
+        self.check_alerts();
         Ok(())
     }
     
-    fn save_activity(&self, app_name: &str, duration: Duration) -> Result<()> {
-        let category = self.categorize_app(app_name);
-        let is_productive = self.is_app_productive(app_name, &category);
+    // Budgets are measured against the sample just taken, classified the way it will be saved
+    fn check_alerts(&mut self) {
+        let app_name = match &self.current_browser_url {
+            Some(_) => format!("{} - Web Browsing", self.current_app),
+            None => self.current_app.clone(),
+        };
+        let (category, weight) = Self::classify(&self.rules, &app_name, self.current_window_title.as_deref(),
+            self.current_browser_url.as_deref(), self.current_command.as_deref());
+        
+        let db = self.db.lock().unwrap();
+        if let Err(e) = self.alerts.check(&db, &self.current_app, &category, weight, self.span_started_at) {
+            eprintln!("Failed to check alert rules: {}", e);
+        }
+    }
+    
+    fn record_ended_pauses(&self) -> Result<()> {
+        let gaps: Vec<Gap> = self.ended_pauses.lock().unwrap().drain(..).collect();
+        if gaps.is_empty() {
+            return Ok(());
+        }
+        
+        let db = self.db.lock().unwrap();
+        for gap in gaps {
+            println!("Recorded {} gap from {} to {}", gap.reason, gap.start_time.format("%H:%M:%S"), gap.end_time.format("%H:%M:%S"));
+            db.save_gap(&gap)?;
+        }
+        Ok(())
+    }
+    
+    // Records the time since the last switch for the current app, if there is one
+    fn flush_current_span(&mut self, now: Instant) -> Result<()> {
+        if self.current_app.is_empty() {
+            return Ok(());
+        }
+        let duration = now.duration_since(self.last_switch_time);
+        
+        // Update the app duration in our tracking map
+        *self.app_durations.entry(self.current_app.clone()).or_insert(Duration::from_secs(0)) += duration;
+        
+        // Record completed activity
+        let sample = Sample {
+            app: &self.current_app,
+            browser_url: self.current_browser_url.as_deref(),
+            window_title: self.current_window_title.as_deref(),
+            git_repo: self.current_git.as_ref().map(|g| g.repo.as_str()),
+            git_branch: self.current_git.as_ref().and_then(|g| g.branch.as_deref()),
+            command: self.current_command.as_deref(),
+        };
+        self.save_activity(&sample, self.span_started_at, Local::now(), false)?;
+        self.last_save_time = now;
+        self.begin_span(now);
+        Ok(())
+    }
+    
+    fn save_activity(&self, sample: &Sample, start_time: DateTime<Local>, end_time: DateTime<Local>, recovered: bool) -> Result<Activity> {
+        let browser_url = sample.browser_url;
+        let app_name: &str = &if browser_url.is_some() {
+            // Use browser + URL indicator for naming
+            format!("{} - Web Browsing", sample.app)
+        } else {
+            sample.app.to_string()
+        };
+        let (category, weight) = Self::classify(&self.rules, app_name, sample.window_title, browser_url, sample.command);
+        let db = self.db.lock().unwrap();
+        
+        // Meeting apps are named after the calendar event they are used for
+        let meeting = calendar::current_meeting_name(&db, app_name);
+        let mut tags = vec!["automatic".to_string()];
+        if meeting.is_some() {
+            tags.push("meeting".to_string());
+        }
+        if recovered {
+            tags.push("recovered".to_string());
+        }
         
         let mut activity = Activity::new(
-            app_name.to_string(),
+            meeting.unwrap_or_else(|| app_name.to_string()),
             category,
-            vec!["automatic".to_string()],
-            is_productive,
+            tags,
+            weight > 0,
         );
+        activity.set_weight(weight);
+        if activity.name != app_name {
+            activity.notes = Some(format!("Calendar event via {}", app_name));
+        }
+        
+        // Nothing sampled is stored before it went through the privacy settings
+        activity.window_title = sample.window_title.map(|t| self.redactor.title(sample.app, t));
+        activity.url = browser_url.map(|url| self.redactor.url(url, &activity.category));
+        activity.git_repo = sample.git_repo.map(|r| r.to_string());
+        activity.git_branch = sample.git_branch.map(|b| b.to_string());
+        activity.command = sample.command.map(|c| c.to_string());
         
         // Since this is historical data, set the times manually
-        let end_time = Local::now();
-        let start_time = end_time - chrono::Duration::from_std(duration).unwrap_or_default();
         activity.start_time = start_time;
         activity.end_time = Some(end_time);
-        activity.duration = Some(duration);
+        activity.duration = Some(end_time.signed_duration_since(start_time).to_std().unwrap_or_default());
         
         // Save to database
-        let db = self.db.lock().unwrap();
-        db.save_activity(&activity)?;
+        activity.id = Some(db.save_activity(&activity)?);
         
-        Ok(())
+        Ok(activity)
     }
     
-    fn categorize_app(&self, app_name: &str) -> String {
+    // User rules come first, the built-in heuristics cover everything they leave out.
+    // In a terminal, the command running says more about the work than the terminal does.
+    pub fn classify(rules: &RuleSet, app_name: &str, window_title: Option<&str>, browser_url: Option<&str>, command: Option<&str>) -> (String, i8) {
+        match rules.find(app_name, window_title, browser_url, command) {
+            Some(rule) => {
+                let weight = rule.weight()
+                    .unwrap_or_else(|| Self::productivity_weight(app_name, &rule.category, browser_url));
+                (rule.category.clone(), weight)
+            },
+            None => {
+                let category = command
+                    .and_then(shell::command_category)
+                    .map(|c| c.to_string())
+                    .unwrap_or_else(|| Self::categorize_app(app_name, window_title, browser_url));
+                let weight = Self::productivity_weight(app_name, &category, browser_url);
+                (category, weight)
+            },
+        }
+    }
+    
+    pub fn categorize_app(app_name: &str, window_title: Option<&str>, browser_url: Option<&str>) -> String {
         // Special handling for browsers with URL info
-        if let Some(url) = &self.current_browser_url {
+        if let Some(url) = browser_url {
             if url != "URL unavailable - for privacy reasons" {
                 return BrowserMonitor::categorize_url(url);
             }
@@ -179,9 +575,12 @@ impl AppMonitor {
         // Simple categorization based on app name
         let app_lower = app_name.to_lowercase();
         
-        if app_lower.contains("chrome") || app_lower.contains("firefox") || 
-           app_lower.contains("safari") || app_lower.contains("edge") || 
-           app_lower.contains("brave") {
+        // An editor title names a file, whatever the terminal or app hosting it
+        if window_title.and_then(|t| title::parse_editor_title(app_name, t)).is_some() {
+            return "Development".to_string();
+        }
+        
+        if BrowserMonitor::is_browser_app(app_name) {
             return "Browser".to_string();
         } else if app_lower.contains("code") || app_lower.contains("intellij") || 
                   app_lower.contains("xcode") || app_lower.contains("vim") || 
@@ -201,86 +600,102 @@ impl AppMonitor {
         "Other".to_string()
     }
     
-    fn is_app_productive(&self, app_name: &str, category: &str) -> bool {
+    // Built-in weights: coding counts double, email, chat, meetings and unknown apps are neutral,
+    // entertainment counts against the day
+    pub fn productivity_weight(app_name: &str, category: &str, browser_url: Option<&str>) -> i8 {
         // Special handling for browsers with URL info
-        if let Some(url) = &self.current_browser_url {
+        if let Some(url) = browser_url {
             if url != "URL unavailable - for privacy reasons" {
-                return BrowserMonitor::is_url_productive(url, category);
+                return BrowserMonitor::url_weight(url, category);
             }
         }
         
-        // Simple productivity classification
         match category {
-            "Development" | "Productivity" => true,
-            "Browser" => {
-                // Could be refined with URL tracking for browsers
-                true
-            },
-            "Communication" => true,
-            "Entertainment" => false,
+            "Development" => 2,
+            "Productivity" | "Ops" => 1,
+            "Browser" | "Communication" => 0,
+            "Entertainment" | "Social Media" => -2,
             _ => {
-                // Default assumption - can be refined
                 let app_lower = app_name.to_lowercase();
-                !(app_lower.contains("game") || app_lower.contains("play"))
+                if app_lower.contains("game") || app_lower.contains("play") { -2 } else { 0 }
             }
         }
     }
     
+    // Name and pid of the frontmost app; the pid is used to find its working directory
     #[cfg(target_os = "macos")]
-    fn get_foreground_app(&self) -> String {
-        println!("Debug: Attempting to get foreground app using osascript first");
-        
+    fn get_foreground_app(&self) -> (String, Option<u32>) {
         // First approach: Try using osascript to get the frontmost application
-        match Command::new("osascript")
+        let output = Command::new("osascript")
             .arg("-e")
             .arg("tell application \"System Events\" to get name of first application process whose frontmost is true")
-            .output() 
-        {
-            Ok(output) => {
-                if output.status.success() {
-                    let app_name = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    println!("Debug: osascript succeeded, got app name: {}", app_name);
-                    if !app_name.is_empty() {
-                        return app_name;
-                    } else {
-                        println!("Debug: osascript returned empty app name, falling back to Objective-C method");
-                    }
-                } else {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    println!("Debug: osascript failed with error: {}", error);
-                    println!("Debug: Falling back to Objective-C method");
-                }
-            },
-            Err(e) => {
-                println!("Debug: Failed to execute osascript: {}", e);
-                println!("Debug: Falling back to Objective-C method");
+            .output();
+        if let Ok(output) = output {
+            let app_name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if output.status.success() && !app_name.is_empty() {
+                return (app_name, Self::get_frontmost_pid());
             }
         }
         
         // Second approach (fallback): Use Objective-C API
-        println!("Debug: Attempting to get foreground app using macOS Objective-C API");
         unsafe {
             let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
-            println!("Debug: Got shared workspace");
             let app: id = msg_send![workspace, frontmostApplication];
-            println!("Debug: frontmostApplication result: {}", if app != nil { "Some app" } else { "nil" });
             if app != nil {
                 let app_name: id = msg_send![app, localizedName];
                 let app_name_str = NSString::UTF8String(app_name);
                 let result = std::ffi::CStr::from_ptr(app_name_str)
                     .to_string_lossy()
                     .into_owned();
-                println!("Debug: Got app name via Objective-C: {}", result);
-                return result;
+                let pid: i32 = msg_send![app, processIdentifier];
+                return (result, u32::try_from(pid).ok());
             }
         }
         
-        println!("Debug: Both approaches failed to get foreground app, returning 'Unknown'");
-        "Unknown".to_string()
+        ("Unknown".to_string(), None)
+    }
+    
+    #[cfg(target_os = "macos")]
+    fn get_frontmost_pid() -> Option<u32> {
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get unix id of first application process whose frontmost is true")
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    }
+    
+    #[cfg(target_os = "macos")]
+    fn get_window_title(&self) -> Option<String> {
+        let output = Command::new("osascript")
+            .arg("-e")
+            .arg("tell application \"System Events\" to get name of front window of (first application process whose frontmost is true)")
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            // Apps without windows, or no accessibility permission
+            return None;
+        }
+        let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Some(title).filter(|t| !t.is_empty() && t != "missing value")
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    fn get_window_title(&self) -> Option<String> {
+        // X11 only; without xdotool or a display there is simply no title
+        let output = Command::new("xdotool")
+            .args(["getactivewindow", "getwindowname"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Some(title).filter(|t| !t.is_empty())
     }
     
     #[cfg(not(target_os = "macos"))]
-    fn get_foreground_app(&self) -> String {
+    fn get_foreground_app(&self) -> (String, Option<u32>) {
         // For non-macOS platforms, use sysinfo to get a best-effort foreground app
         let mut system = System::new();
         system.refresh_processes();
@@ -289,15 +704,17 @@ impl AppMonitor {
         // This is not perfect but provides a basic fallback
         let mut max_cpu = 0.0;
         let mut foreground_app = "Unknown".to_string();
+        let mut foreground_pid = None;
         
-        for (_, process) in system.processes() {
+        for (pid, process) in system.processes() {
             let cpu_usage = process.cpu_usage();
             if cpu_usage > max_cpu {
                 max_cpu = cpu_usage;
                 foreground_app = process.name().to_string();
+                foreground_pid = Some(pid.as_u32());
             }
         }
         
-        foreground_app
+        (foreground_app, foreground_pid)
     }
 }
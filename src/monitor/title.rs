@@ -0,0 +1,159 @@
+// Editors put the open file and the project in their window title. Pulling them back out lets
+// hours spent in one editor be split by project.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorContext {
+    pub editor: &'static str,
+    pub file: Option<String>,
+    // Folder or project name as the editor shows it, or a directory path for Vim
+    pub workspace: Option<String>,
+}
+
+const JETBRAINS_IDES: [&str; 13] = [
+    "intellij", "pycharm", "webstorm", "goland", "clion", "rider", "rubymine",
+    "phpstorm", "datagrip", "rustrover", "android studio", "appcode", "fleet",
+];
+
+pub fn parse_editor_title(app_name: &str, title: &str) -> Option<EditorContext> {
+    let app_lower = app_name.to_lowercase();
+    let title = title.trim();
+    if title.is_empty() {
+        return None;
+    }
+
+    // Vim usually runs inside a terminal, so it is recognised by its title alone
+    if let Some(context) = parse_vim(title) {
+        return Some(context);
+    }
+    if app_lower.contains("code") || title.ends_with("Visual Studio Code") {
+        return parse_vscode(title);
+    }
+    if JETBRAINS_IDES.iter().any(|ide| app_lower.contains(ide)) {
+        return parse_jetbrains(title);
+    }
+    None
+}
+
+// "● main.rs - crate - Visual Studio Code", with em dashes on macOS. Remote and multi-root
+// windows add suffixes such as "[SSH: host]" or "(Workspace)" to the folder name.
+fn parse_vscode(title: &str) -> Option<EditorContext> {
+    let title = title.trim_start_matches('●').trim();
+    let mut parts = split_title(title, &[" — ", " - "]);
+    if parts.last().map(|p| p.contains("Visual Studio Code") || p.eq_ignore_ascii_case("code")).unwrap_or(false) {
+        parts.pop();
+    }
+
+    let (file, workspace) = match parts.as_slice() {
+        [] => return None,
+        [only] => (None, Some(only.to_string())),
+        [file, workspace, ..] => (Some(file.to_string()), Some(workspace.to_string())),
+    };
+    Some(EditorContext {
+        editor: "VS Code",
+        file: file.filter(|f| !f.is_empty() && !f.starts_with("Welcome")),
+        workspace: workspace.map(|w| strip_suffixes(&w)).filter(|w| !w.is_empty()),
+    })
+}
+
+// "crate – main.rs", or "crate [~/src/crate] – src/main.rs [crate]" in older releases
+fn parse_jetbrains(title: &str) -> Option<EditorContext> {
+    let parts = split_title(title, &[" – ", " - "]);
+    let workspace = parts.first().map(|w| strip_suffixes(w)).filter(|w| !w.is_empty())?;
+    let file = if parts.len() > 1 {
+        parts.last().map(|f| strip_suffixes(f)).filter(|f| !f.is_empty())
+    } else {
+        None
+    };
+    Some(EditorContext { editor: "JetBrains", file, workspace: Some(workspace) })
+}
+
+// Vim's default titlestring: "main.rs + (~/src/crate/src) - VIM", "NVIM" for Neovim
+fn parse_vim(title: &str) -> Option<EditorContext> {
+    let upper = title.to_uppercase();
+    let editor = if upper.ends_with(" - NVIM") {
+        "Neovim"
+    } else if upper.ends_with(" - VIM") {
+        "Vim"
+    } else {
+        return None;
+    };
+    let body = title[..title.rfind(" - ")?].trim();
+
+    let (name, directory) = match (body.rfind('('), body.ends_with(')')) {
+        (Some(open), true) => (body[..open].trim(), Some(body[open + 1..body.len() - 1].trim())),
+        _ => (body, None),
+    };
+    // Modified and read-only flags follow the file name as a word of their own
+    let name = match name.rsplit_once(' ') {
+        Some((file, flags)) if flags.chars().all(|c| matches!(c, '+' | '=' | '-')) => file.trim(),
+        _ => name,
+    };
+
+    Some(EditorContext {
+        editor,
+        file: Some(name.to_string()).filter(|n| !n.is_empty() && !n.starts_with('[')),
+        workspace: directory.map(|d| d.to_string()).filter(|d| !d.is_empty()),
+    })
+}
+
+fn split_title<'a>(title: &'a str, separators: &[&str]) -> Vec<&'a str> {
+    for separator in separators {
+        if title.contains(separator) {
+            return title.split(separator).map(|p| p.trim()).collect();
+        }
+    }
+    vec![title]
+}
+
+// Drops trailing "[...]" and "(...)" annotations
+fn strip_suffixes(value: &str) -> String {
+    let mut value = value.trim();
+    loop {
+        let stripped = match value.chars().last() {
+            Some(']') => value.rfind(" [").map(|i| &value[..i]),
+            Some(')') => value.rfind(" (").map(|i| &value[..i]),
+            _ => None,
+        };
+        match stripped {
+            Some(rest) => value = rest.trim(),
+            None => return value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(editor: &'static str, file: Option<&str>, workspace: Option<&str>) -> Option<EditorContext> {
+        Some(EditorContext { editor, file: file.map(str::to_string), workspace: workspace.map(str::to_string) })
+    }
+
+    #[test]
+    fn reads_vscode_titles() {
+        assert_eq!(parse_editor_title("Code", "● main.rs — crate — Visual Studio Code"), context("VS Code", Some("main.rs"), Some("crate")));
+        assert_eq!(parse_editor_title("Code", "lib.rs - api [SSH: devbox] - Visual Studio Code"), context("VS Code", Some("lib.rs"), Some("api")));
+        assert_eq!(parse_editor_title("Code", "Welcome - crate - Visual Studio Code"), context("VS Code", None, Some("crate")));
+    }
+
+    #[test]
+    fn reads_jetbrains_titles() {
+        assert_eq!(parse_editor_title("RustRover", "crate – main.rs"), context("JetBrains", Some("main.rs"), Some("crate")));
+        assert_eq!(parse_editor_title("IntelliJ IDEA", "crate [~/src/crate] – src/Main.java [crate]"), context("JetBrains", Some("src/Main.java"), Some("crate")));
+        assert_eq!(parse_editor_title("PyCharm", "scripts"), context("JetBrains", None, Some("scripts")));
+    }
+
+    #[test]
+    fn reads_vim_and_neovim_titles_without_their_flags() {
+        assert_eq!(parse_editor_title("Terminal", "main.rs + (~/src/crate/src) - VIM"), context("Vim", Some("main.rs"), Some("~/src/crate/src")));
+        assert_eq!(parse_editor_title("iTerm2", "notes.md = (~/docs) - NVIM"), context("Neovim", Some("notes.md"), Some("~/docs")));
+        assert_eq!(parse_editor_title("Terminal", "[No Name] - VIM"), context("Vim", None, None));
+    }
+
+    #[test]
+    fn vim_flags_leave_file_names_ending_in_flag_characters_alone() {
+        assert_eq!(parse_editor_title("Terminal", "c++ (~/src) - VIM"), context("Vim", Some("c++"), Some("~/src")));
+        assert_eq!(parse_editor_title("Terminal", "notes- + (~/docs) - NVIM"), context("Neovim", Some("notes-"), Some("~/docs")));
+        assert_eq!(parse_editor_title("Terminal", "draft.txt - (~/docs) - VIM"), context("Vim", Some("draft.txt"), Some("~/docs")));
+    }
+}
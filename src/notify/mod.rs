@@ -0,0 +1,72 @@
+// Desktop notifications: notify-send or the freedesktop D-Bus service on Linux, Notification
+// Center on macOS. When neither works, a user command is run instead with the title and body
+// in $PT_NOTIFY_TITLE and $PT_NOTIFY_BODY.
+
+use std::process::{Command, Stdio};
+
+// Used when no hook command is passed explicitly
+pub const HOOK_ENV: &str = "PRODUCTIVITY_TRACKER_NOTIFY_COMMAND";
+
+pub struct Notifier {
+    hook: Option<String>,
+}
+
+impl Notifier {
+    pub fn new(hook: Option<String>) -> Self {
+        Self {
+            hook: hook.or_else(|| std::env::var(HOOK_ENV).ok()).filter(|h| !h.trim().is_empty()),
+        }
+    }
+
+    // Returns false when the notification could not be delivered anywhere
+    pub fn send(&self, title: &str, body: &str) -> bool {
+        if desktop_notification(title, body) {
+            return true;
+        }
+        match &self.hook {
+            Some(hook) => run_hook(hook, title, body),
+            None => false,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn desktop_notification(title: &str, body: &str) -> bool {
+    let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+    let script = format!("display notification \"{}\" with title \"{}\"", quote(body), quote(title));
+    succeeded(Command::new("osascript").arg("-e").arg(script))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn desktop_notification(title: &str, body: &str) -> bool {
+    if succeeded(Command::new("notify-send").args(["--app-name=productivity_tracker", title, body])) {
+        return true;
+    }
+
+    // Same call notify-send makes, for systems with a notification daemon but without libnotify
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    succeeded(Command::new("gdbus").args([
+        "call", "--session",
+        "--dest", "org.freedesktop.Notifications",
+        "--object-path", "/org/freedesktop/Notifications",
+        "--method", "org.freedesktop.Notifications.Notify",
+        "productivity_tracker", "0", "", &quote(title), &quote(body), "[]", "{}", "5000",
+    ]))
+}
+
+fn run_hook(hook: &str, title: &str, body: &str) -> bool {
+    succeeded(Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("PT_NOTIFY_TITLE", title)
+        .env("PT_NOTIFY_BODY", body))
+}
+
+fn succeeded(command: &mut Command) -> bool {
+    command
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
@@ -0,0 +1,279 @@
+// Lines git history up with tracked development time. Each commit is credited with the
+// development time tracked between it and the previous commit on its branch (at most
+// `max_window` before it), which is a reasonable estimate of what it took to write.
+
+use crate::data::Activity;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitTime {
+    pub hash: String,
+    pub branch: String,
+    pub author: String,
+    pub time: DateTime<Local>,
+    pub subject: String,
+    pub tracked_seconds: u64,
+    // Whether any development time was tracked within `max_window` before or after the commit
+    pub tracked_nearby: bool,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct BranchTime {
+    pub branch: String,
+    pub commits: usize,
+    // Sum of the estimates for the branch's commits
+    pub commit_seconds: u64,
+    // Time the monitor saw spent on the branch itself, when branch attribution was available
+    pub tracked_seconds: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommitReport {
+    pub repo: String,
+    // Oldest first
+    pub commits: Vec<CommitTime>,
+    // Most time first
+    pub branches: Vec<BranchTime>,
+    pub development_seconds: u64,
+    pub attributed_seconds: u64,
+}
+
+impl CommitReport {
+    pub fn untracked(&self) -> impl Iterator<Item = &CommitTime> {
+        self.commits.iter().filter(|c| !c.tracked_nearby)
+    }
+}
+
+pub struct CommitOptions {
+    pub from: DateTime<Local>,
+    pub to: DateTime<Local>,
+    // Longest stretch before a commit that can count towards it
+    pub max_window: Duration,
+    // Only commits by this email; None means everyone
+    pub author: Option<String>,
+}
+
+// The repository's own user.email, so commits by teammates are left out by default
+pub fn local_author(repo: &Path) -> Option<String> {
+    git(repo, &["config", "user.email"]).ok().filter(|email| !email.is_empty())
+}
+
+pub fn repository_root(repo: &Path) -> Result<String, Box<dyn Error>> {
+    git(repo, &["rev-parse", "--show-toplevel"])
+}
+
+// `activities` should cover the range plus `max_window` on either side of it
+pub fn commit_report(repo: &Path, activities: &[Activity], options: &CommitOptions) -> Result<CommitReport, Box<dyn Error>> {
+    let root = repository_root(repo)?;
+    let commits = read_commits(repo, options)?;
+    Ok(build_report(root, commits, activities, options))
+}
+
+fn build_report(root: String, mut commits: Vec<CommitTime>, activities: &[Activity], options: &CommitOptions) -> CommitReport {
+    commits.sort_by_key(|c| c.time);
+
+    // Time spent in this repository, or development time with no repository recorded
+    let relevant: Vec<&Activity> = activities.iter()
+        .filter(|a| match &a.git_repo {
+            Some(activity_repo) => *activity_repo == root,
+            None => a.category == "Development",
+        })
+        .collect();
+
+    attribute(&mut commits, &relevant, options.max_window);
+
+    let mut branches: HashMap<String, BranchTime> = HashMap::new();
+    for commit in &commits {
+        let branch = branches.entry(commit.branch.clone()).or_insert_with(|| BranchTime {
+            branch: commit.branch.clone(),
+            ..Default::default()
+        });
+        branch.commits += 1;
+        branch.commit_seconds += commit.tracked_seconds;
+    }
+    for activity in relevant.iter().filter(|a| a.git_repo.is_some()) {
+        if let Some(name) = &activity.git_branch {
+            let branch = branches.entry(name.clone()).or_insert_with(|| BranchTime {
+                branch: name.clone(),
+                ..Default::default()
+            });
+            branch.tracked_seconds += overlap_seconds(activity, options.from, options.to);
+        }
+    }
+    let mut branches: Vec<BranchTime> = branches.into_values().collect();
+    branches.sort_by(|a, b| {
+        (b.commit_seconds.max(b.tracked_seconds)).cmp(&a.commit_seconds.max(a.tracked_seconds))
+            .then_with(|| a.branch.cmp(&b.branch))
+    });
+
+    CommitReport {
+        repo: root,
+        attributed_seconds: commits.iter().map(|c| c.tracked_seconds).sum(),
+        development_seconds: relevant.iter().map(|a| overlap_seconds(a, options.from, options.to)).sum(),
+        commits,
+        branches,
+    }
+}
+
+// Credits each commit (oldest first) with the time tracked since the previous commit on the
+// same branch. Branches are worked on side by side, so a commit on one does not cut short the
+// window of the next commit on another.
+fn attribute(commits: &mut [CommitTime], activities: &[&Activity], max_window: Duration) {
+    let window = chrono::Duration::from_std(max_window).unwrap_or_default();
+    let mut previous: HashMap<String, DateTime<Local>> = HashMap::new();
+    for commit in commits {
+        let earliest = commit.time - window;
+        let start = previous.get(&commit.branch).map_or(earliest, |p| (*p).max(earliest));
+        commit.tracked_seconds = activities.iter().map(|a| overlap_seconds(a, start, commit.time)).sum();
+        commit.tracked_nearby = activities.iter().any(|a| overlap_seconds(a, earliest, commit.time + window) > 0);
+        previous.insert(commit.branch.clone(), commit.time);
+    }
+}
+
+fn read_commits(repo: &Path, options: &CommitOptions) -> Result<Vec<CommitTime>, Box<dyn Error>> {
+    // %S is the ref each commit was reached from, which stands in for its branch. Commits are
+    // timed by author date, when the work was done; git filters on the committer date, which is
+    // never earlier, so --since keeps every candidate and the range is checked below. That way
+    // rebased and cherry-picked commits stay at the time they were written.
+    let since = format!("--since={}", options.from.to_rfc3339());
+    let log = git(repo, &["log", "--branches", "--remotes", "--source", "--no-merges", &since, "--format=%H%x1f%S%x1f%ae%x1f%aI%x1f%s"])?;
+
+    let mut commits = Vec::new();
+    for line in log.lines() {
+        let fields: Vec<&str> = line.split('\x1f').collect();
+        if fields.len() < 5 {
+            continue;
+        }
+        if let Some(author) = &options.author {
+            if !fields[2].eq_ignore_ascii_case(author) {
+                continue;
+            }
+        }
+        let time = match DateTime::parse_from_rfc3339(fields[3]) {
+            Ok(time) => time.with_timezone(&Local),
+            Err(_) => continue,
+        };
+        if time < options.from || time >= options.to {
+            continue;
+        }
+        commits.push(CommitTime {
+            hash: fields[0].to_string(),
+            branch: short_ref(fields[1]),
+            author: fields[2].to_string(),
+            time,
+            subject: fields[4].to_string(),
+            tracked_seconds: 0,
+            tracked_nearby: false,
+        });
+    }
+    Ok(commits)
+}
+
+fn short_ref(reference: &str) -> String {
+    reference
+        .strip_prefix("refs/heads/")
+        .or_else(|| reference.strip_prefix("refs/remotes/"))
+        .unwrap_or(reference)
+        .to_string()
+}
+
+fn overlap_seconds(activity: &Activity, start: DateTime<Local>, end: DateTime<Local>) -> u64 {
+    let activity_end = activity.end_time.unwrap_or_else(Local::now);
+    let from = activity.start_time.max(start);
+    let to = activity_end.min(end);
+    (to - from).num_seconds().max(0) as u64
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| format!("could not run git: {}", e))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(format!("git {}: {}", args.first().unwrap_or(&""), message).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::{at, finished};
+
+    fn commit(hash: &str, branch: &str, time: DateTime<Local>) -> CommitTime {
+        CommitTime {
+            hash: hash.to_string(),
+            branch: branch.to_string(),
+            author: "jen@example.com".to_string(),
+            time,
+            subject: hash.to_string(),
+            tracked_seconds: 0,
+            tracked_nearby: false,
+        }
+    }
+
+    fn activity(category: &str, repo: Option<&str>, start: DateTime<Local>, end: DateTime<Local>) -> Activity {
+        finished(start, end).category(category).repo(repo).build()
+    }
+
+    fn report(commits: Vec<CommitTime>, activities: &[Activity]) -> CommitReport {
+        let options = CommitOptions {
+            from: at(2, 0, 0),
+            to: at(2, 23, 59),
+            max_window: Duration::from_secs(3600),
+            author: None,
+        };
+        build_report("/src/crate".to_string(), commits, activities, &options)
+    }
+
+    fn estimates(report: &CommitReport) -> Vec<(&str, u64)> {
+        report.commits.iter().map(|c| (c.hash.as_str(), c.tracked_seconds / 60)).collect()
+    }
+
+    #[test]
+    fn commits_are_credited_since_the_previous_commit_on_their_branch() {
+        let report = report(
+            vec![commit("c", "main", at(2, 11, 0)), commit("a", "main", at(2, 10, 0)), commit("b", "feature", at(2, 10, 30))],
+            &[activity("Development", Some("/src/crate"), at(2, 9, 0), at(2, 11, 0))],
+        );
+        // The feature commit reaches back past the main commit before it, up to the window
+        assert_eq!(estimates(&report), [("a", 60), ("b", 60), ("c", 60)]);
+
+        let branches: Vec<(&str, usize, u64)> = report.branches.iter()
+            .map(|b| (b.branch.as_str(), b.commits, b.commit_seconds / 60))
+            .collect();
+        assert_eq!(branches, [("main", 2, 120), ("feature", 1, 60)]);
+    }
+
+    #[test]
+    fn only_time_in_the_repository_or_unattributed_development_counts() {
+        let report = report(vec![commit("a", "main", at(2, 12, 0))], &[
+            activity("Development", Some("/src/other"), at(2, 11, 0), at(2, 11, 20)),
+            activity("Development", None, at(2, 11, 20), at(2, 11, 30)),
+            activity("Communication", None, at(2, 11, 30), at(2, 11, 45)),
+            activity("Other", Some("/src/crate"), at(2, 11, 45), at(2, 12, 0)),
+        ]);
+        assert_eq!(estimates(&report), [("a", 25)]);
+        assert_eq!(report.development_seconds, 25 * 60);
+    }
+
+    #[test]
+    fn commits_with_time_tracked_just_after_them_are_not_untracked() {
+        let report = report(
+            vec![commit("quick-fix", "main", at(2, 9, 0)), commit("lonely", "main", at(2, 18, 0))],
+            &[activity("Development", Some("/src/crate"), at(2, 9, 10), at(2, 9, 40))],
+        );
+        assert_eq!(estimates(&report), [("quick-fix", 0), ("lonely", 0)]);
+        let untracked: Vec<&str> = report.untracked().map(|c| c.hash.as_str()).collect();
+        assert_eq!(untracked, ["lonely"]);
+    }
+}
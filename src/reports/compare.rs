@@ -0,0 +1,260 @@
+// Compares two periods, e.g. this week against last week: time per category and per tag, which
+// activities appeared or disappeared, and how the productive share moved. Everything comes from
+// the daily_summaries rollup, so comparing months or years stays quick.
+
+use super::{summary_totals, GroupBy, GroupTotal};
+use crate::data::DailySummary;
+use crate::goals::week_start;
+use chrono::{Datelike, Months, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+// First and last day of both periods. While `this` is still running, `vs` is cut to as many
+// days as have passed of `this`, so e.g. Monday to Wednesday is weighed against Monday to
+// Wednesday of the week before rather than all of it.
+pub fn resolve_pair(this: Period, vs: Period, today: NaiveDate) -> ((NaiveDate, NaiveDate), (NaiveDate, NaiveDate)) {
+    let this_range = this.resolve(today);
+    let (vs_from, vs_to) = vs.resolve(today);
+    if !this.is_partial(today) {
+        return (this_range, (vs_from, vs_to));
+    }
+    let elapsed = this_range.1 - this_range.0;
+    (this_range, (vs_from, vs_to.min(vs_from + elapsed)))
+}
+
+// A span of whole days relative to today, or a fixed range. Offsets count back: 0 is the current
+// day, week or month, 1 the one before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Day(u32),
+    Week(u32),
+    Month(u32),
+    Range(NaiveDate, NaiveDate),
+}
+
+impl Period {
+    // First and last day, both included. Days after today have nothing tracked yet, so a current
+    // period ends today.
+    pub fn resolve(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let (start, end) = self.bounds(today);
+        (start, end.min(today).max(start))
+    }
+
+    // Whether the period is still running, i.e. `resolve` cut it short at today
+    pub fn is_partial(&self, today: NaiveDate) -> bool {
+        let (start, end) = self.bounds(today);
+        start <= today && today <= end
+    }
+
+    fn bounds(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match *self {
+            Period::Day(back) => {
+                let day = today - chrono::Duration::days(back as i64);
+                (day, day)
+            },
+            Period::Week(back) => {
+                let start = week_start(today) - chrono::Duration::weeks(back as i64);
+                (start, start + chrono::Duration::days(6))
+            },
+            Period::Month(back) => {
+                let start = today.with_day(1).unwrap_or(today) - Months::new(back);
+                let end = (start + Months::new(1)).pred_opt().unwrap_or(start);
+                (start, end)
+            },
+            Period::Range(from, to) => (from, to),
+        }
+    }
+
+    // The period of the same kind just before this one; ranges step back by their own length
+    pub fn previous(&self) -> Period {
+        match *self {
+            Period::Day(back) => Period::Day(back + 1),
+            Period::Week(back) => Period::Week(back + 1),
+            Period::Month(back) => Period::Month(back + 1),
+            Period::Range(from, to) => {
+                let length = to - from + chrono::Duration::days(1);
+                Period::Range(from - length, to - length)
+            },
+        }
+    }
+}
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim().to_lowercase();
+        let period = match trimmed.as_str() {
+            "today" | "day" => Period::Day(0),
+            "yesterday" => Period::Day(1),
+            "week" | "this-week" => Period::Week(0),
+            "last-week" => Period::Week(1),
+            "month" | "this-month" => Period::Month(0),
+            "last-month" => Period::Month(1),
+            _ => {
+                let parse = |date: &str| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                    .map_err(|_| format!("invalid period '{}': use today, yesterday, week, last-week, month, last-month or YYYY-MM-DD..YYYY-MM-DD", s));
+                let (from, to) = match trimmed.split_once("..") {
+                    Some((from, to)) => (parse(from)?, parse(to)?),
+                    None => (parse(&trimmed)?, parse(&trimmed)?),
+                };
+                if to < from {
+                    return Err(format!("invalid period '{}': the range ends before it starts", s));
+                }
+                Period::Range(from, to)
+            },
+        };
+        Ok(period)
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::Day(0) => write!(f, "today"),
+            Period::Day(1) => write!(f, "yesterday"),
+            Period::Week(0) => write!(f, "this week"),
+            Period::Week(1) => write!(f, "last week"),
+            Period::Month(0) => write!(f, "this month"),
+            Period::Month(1) => write!(f, "last month"),
+            Period::Day(back) => write!(f, "{} days ago", back),
+            Period::Week(back) => write!(f, "{} weeks ago", back),
+            Period::Month(back) => write!(f, "{} months ago", back),
+            Period::Range(from, to) if from == to => write!(f, "{}", from),
+            Period::Range(from, to) => write!(f, "{}..{}", from, to),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Span {
+    pub label: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    // The period includes today, so its last day is not over yet
+    pub partial: bool,
+    pub tracked_seconds: u64,
+    pub productive_ratio: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Delta {
+    pub key: String,
+    // `vs` is the earlier period, `this` the one being looked at
+    pub vs_seconds: u64,
+    pub this_seconds: u64,
+    pub change_seconds: i64,
+    // None when there was nothing to compare against
+    pub change_percent: Option<f64>,
+    // Time-weighted productivity weight across both periods, so callers can tell whether more is better
+    pub mean_weight: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct Comparison {
+    pub this: Span,
+    pub vs: Span,
+    // Largest change first
+    pub categories: Vec<Delta>,
+    pub tags: Vec<Delta>,
+    // Activity names only seen in one of the periods, most time first
+    pub new_activities: Vec<GroupTotal>,
+    pub disappeared_activities: Vec<GroupTotal>,
+    // Percentage points
+    pub productive_ratio_change: f64,
+}
+
+pub fn compare(today: NaiveDate, this_label: String, this_range: (NaiveDate, NaiveDate), this: &[DailySummary], vs_label: String, vs_range: (NaiveDate, NaiveDate), vs: &[DailySummary]) -> Comparison {
+    let span = |label: String, (from, to): (NaiveDate, NaiveDate), rows: &[DailySummary]| {
+        let (tracked, productive) = rows.iter()
+            .filter(|r| r.tag.is_none())
+            .fold((0, 0), |(tracked, productive), r| (tracked + r.seconds, productive + r.productive_seconds));
+        let productive_ratio = if tracked > 0 { productive as f64 / tracked as f64 } else { 0.0 };
+        Span { label, from, to, partial: to >= today, tracked_seconds: tracked, productive_ratio }
+    };
+    let this_span = span(this_label, this_range, this);
+    let vs_span = span(vs_label, vs_range, vs);
+
+    let this_names = summary_totals(this, GroupBy::Name).unwrap_or_default();
+    let vs_names = summary_totals(vs, GroupBy::Name).unwrap_or_default();
+    let missing_from = |totals: &[GroupTotal], other: &[GroupTotal]| -> Vec<GroupTotal> {
+        totals.iter()
+            .filter(|t| t.seconds > 0 && !other.iter().any(|o| o.key == t.key && o.seconds > 0))
+            .cloned()
+            .collect()
+    };
+
+    Comparison {
+        categories: deltas(this, vs, |r| r.tag.is_none().then_some(r.category.as_str())),
+        tags: deltas(this, vs, |r| r.tag.as_deref()),
+        new_activities: missing_from(&this_names, &vs_names),
+        disappeared_activities: missing_from(&vs_names, &this_names),
+        productive_ratio_change: (this_span.productive_ratio - vs_span.productive_ratio) * 100.0,
+        this: this_span,
+        vs: vs_span,
+    }
+}
+
+// Rows count towards the key `key` gives them; rows without one are skipped
+fn deltas<'a>(this: &'a [DailySummary], vs: &'a [DailySummary], key: impl Fn(&'a DailySummary) -> Option<&'a str>) -> Vec<Delta> {
+    // Seconds in `vs`, seconds in `this`, and weight × seconds over both
+    let mut totals: HashMap<&str, (u64, u64, i64)> = HashMap::new();
+    for (rows, is_this) in [(vs, false), (this, true)] {
+        for row in rows {
+            let Some(key) = key(row) else {
+                continue;
+            };
+            let total = totals.entry(key).or_default();
+            if is_this {
+                total.1 += row.seconds;
+            } else {
+                total.0 += row.seconds;
+            }
+            total.2 += row.weighted_seconds;
+        }
+    }
+
+    let mut deltas: Vec<Delta> = totals.into_iter()
+        .filter(|(_, (vs, this, _))| vs + this > 0)
+        .map(|(key, (vs_seconds, this_seconds, weighted))| Delta {
+            key: key.to_string(),
+            vs_seconds,
+            this_seconds,
+            change_seconds: this_seconds as i64 - vs_seconds as i64,
+            change_percent: (vs_seconds > 0).then(|| (this_seconds as f64 - vs_seconds as f64) / vs_seconds as f64 * 100.0),
+            mean_weight: weighted as f64 / (vs_seconds + this_seconds) as f64,
+        })
+        .collect();
+    deltas.sort_by(|a, b| b.change_seconds.abs().cmp(&a.change_seconds.abs()).then_with(|| a.key.cmp(&b.key)));
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn current_periods_end_today_and_cut_the_one_compared_against() {
+        // A Wednesday
+        let today = date("2026-10-14");
+        assert_eq!(resolve_pair(Period::Week(0), Period::Week(1), today),
+            ((date("2026-10-12"), date("2026-10-14")), (date("2026-10-05"), date("2026-10-07"))));
+        assert_eq!(resolve_pair(Period::Month(0), Period::Month(1), today),
+            ((date("2026-10-01"), date("2026-10-14")), (date("2026-09-01"), date("2026-09-14"))));
+        assert!(Period::Week(0).is_partial(today));
+    }
+
+    #[test]
+    fn past_periods_are_compared_whole() {
+        let today = date("2026-10-14");
+        assert_eq!(resolve_pair(Period::Week(1), Period::Week(2), today),
+            ((date("2026-10-05"), date("2026-10-11")), (date("2026-09-28"), date("2026-10-04"))));
+        assert!(!Period::Month(1).is_partial(today));
+    }
+}
@@ -0,0 +1,348 @@
+// A single-file HTML report for sharing. Charts are inline SVG drawn from the same totals as the
+// terminal reports, and the styles are inline too, so the file opens offline and can be mailed
+// around as is.
+
+use super::{daily_scores, group_totals, score_trend, summarize, top_sites, weekday_hours, GroupBy, GroupTotal};
+use crate::data::duration::format_short;
+use crate::data::Activity;
+use crate::data::timezone::DayBoundary;
+use chrono::{Local, NaiveDate};
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+const PALETTE: [&str; 8] = ["#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#76b7b2", "#edc948", "#b07aa1", "#ff9da7"];
+// Categories beyond the palette are drawn together in this colour
+const OTHER_COLOR: &str = "#bab0ac";
+
+// Rows in the apps and sites tables
+const TABLE_ROWS: usize = 10;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const STYLE: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; color: #222; max-width: 960px; margin: 2em auto; padding: 0 1em; }
+h1 { margin-bottom: 0.2em; }
+h2 { margin-top: 2em; border-bottom: 1px solid #ddd; padding-bottom: 0.2em; }
+.muted { color: #777; }
+.tiles { display: flex; gap: 1em; flex-wrap: wrap; margin: 1.5em 0; }
+.tile { border: 1px solid #ddd; border-radius: 6px; padding: 0.8em 1.2em; min-width: 140px; }
+.tile b { display: block; font-size: 1.6em; }
+.row { display: flex; gap: 2em; align-items: center; flex-wrap: wrap; }
+.legend { list-style: none; padding: 0; }
+.legend li { margin: 0.3em 0; }
+.swatch { display: inline-block; width: 0.9em; height: 0.9em; border-radius: 2px; margin-right: 0.5em; vertical-align: middle; }
+table { border-collapse: collapse; width: 100%; }
+td, th { padding: 0.3em 0.6em; border-bottom: 1px solid #eee; text-align: left; }
+td.num, th.num { text-align: right; white-space: nowrap; }
+.tables { display: grid; grid-template-columns: 1fr 1fr; gap: 2em; }
+svg text { font-size: 11px; fill: #555; }
+";
+
+struct Slice {
+    label: String,
+    color: &'static str,
+    seconds: u64,
+    // Holds every category beyond the palette rather than the category named by `label`
+    folded: bool,
+}
+
+// The whole page for activities between `from` and `to`, both days included, already cut at
+// the boundary's day starts
+pub fn render(activities: &[Activity], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> String {
+    let summary = summarize(activities);
+    let slices = category_slices(activities);
+    let title = if from == to {
+        format!("Tracked time on {}", from.format("%a %Y-%m-%d"))
+    } else {
+        format!("Tracked time from {} to {}", from.format("%a %Y-%m-%d"), to.format("%a %Y-%m-%d"))
+    };
+
+    let mut page = String::new();
+    page.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    page.push_str(&format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", escape(&title), STYLE));
+    page.push_str(&format!("<h1>{}</h1>\n<p class=\"muted\">Generated {}</p>\n", escape(&title), Local::now().format("%Y-%m-%d %H:%M")));
+
+    if summary.total_seconds == 0 {
+        page.push_str("<p>Nothing tracked.</p>\n</body>\n</html>\n");
+        return page;
+    }
+
+    let share = |seconds: u64| seconds as f64 / summary.total_seconds as f64 * 100.0;
+    page.push_str("<div class=\"tiles\">\n");
+    page.push_str(&tile("Tracked", &format_short(Duration::from_secs(summary.total_seconds))));
+    page.push_str(&tile("Productive", &format!("{:.0}%", share(summary.productive_seconds))));
+    page.push_str(&tile("Unproductive", &format!("{:.0}%", share(summary.unproductive_seconds))));
+    page.push_str(&tile("Score", &format!("{:+.0}", summary.score)));
+    page.push_str(&tile("Activities", &summary.activities.to_string()));
+    page.push_str("</div>\n");
+
+    page.push_str("<h2>Categories</h2>\n<div class=\"row\">\n");
+    page.push_str(&donut(&slices, summary.total_seconds));
+    page.push_str("<ul class=\"legend\">\n");
+    for slice in &slices {
+        page.push_str(&format!(
+            "<li><span class=\"swatch\" style=\"background:{}\"></span>{} <span class=\"muted\">{} ({:.0}%)</span></li>\n",
+            slice.color, escape(&slice.label), format_short(Duration::from_secs(slice.seconds)), share(slice.seconds),
+        ));
+    }
+    page.push_str("</ul>\n</div>\n");
+
+    page.push_str("<h2>Per day</h2>\n");
+    page.push_str(&daily_bars(activities, &slices, boundary, from, to));
+
+    page.push_str("<h2>Hour of day</h2>\n<p class=\"muted\">Darker cells hold more tracked time; green leans productive, red unproductive.</p>\n");
+    page.push_str(&heatmap(activities, boundary));
+
+    page.push_str("<h2>Productivity trend</h2>\n");
+    page.push_str(&trend(activities, boundary, from, to));
+
+    page.push_str("<div class=\"tables\">\n<div>\n<h2>Top apps</h2>\n");
+    page.push_str(&table(&group_totals(activities, GroupBy::Name), summary.total_seconds));
+    page.push_str("</div>\n<div>\n<h2>Top sites</h2>\n");
+    page.push_str(&table(&top_sites(activities), summary.total_seconds));
+    page.push_str("</div>\n</div>\n</body>\n</html>\n");
+    page
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn tile(label: &str, value: &str) -> String {
+    format!("<div class=\"tile\"><span class=\"muted\">{}</span><b>{}</b></div>\n", label, escape(value))
+}
+
+// Largest categories first, with the ones beyond the palette folded into a last "Other
+// categories" slice
+fn category_slices(activities: &[Activity]) -> Vec<Slice> {
+    let totals: Vec<GroupTotal> = group_totals(activities, GroupBy::Category)
+        .into_iter()
+        .filter(|t| t.seconds > 0)
+        .collect();
+    let mut slices: Vec<Slice> = totals.iter()
+        .take(PALETTE.len())
+        .zip(PALETTE)
+        .map(|(total, color)| Slice { label: total.key.clone(), color, seconds: total.seconds, folded: false })
+        .collect();
+    let rest: u64 = totals.iter().skip(PALETTE.len()).map(|t| t.seconds).sum();
+    if rest > 0 {
+        slices.push(Slice { label: "Other categories".to_string(), color: OTHER_COLOR, seconds: rest, folded: true });
+    }
+    slices
+}
+
+// The slice drawn for `category`: its own, or the folded one when it is beyond the palette. A
+// category literally named like the folded slice still gets its own. None only for categories
+// without time, which have no slice.
+fn slice_index(slices: &[Slice], category: &str) -> Option<usize> {
+    slices.iter()
+        .position(|s| !s.folded && s.label == category)
+        .or_else(|| slices.iter().position(|s| s.folded))
+}
+
+fn donut(slices: &[Slice], total: u64) -> String {
+    let (center, radius) = (100.0, 70.0);
+    let circumference = 2.0 * PI * radius;
+    let mut svg = String::from("<svg width=\"200\" height=\"200\" viewBox=\"0 0 200 200\" role=\"img\">\n");
+    let mut offset = 0.0;
+    for slice in slices {
+        let length = slice.seconds as f64 / total as f64 * circumference;
+        svg.push_str(&format!(
+            "<circle cx=\"{c}\" cy=\"{c}\" r=\"{r}\" fill=\"none\" stroke=\"{}\" stroke-width=\"34\" stroke-dasharray=\"{:.2} {:.2}\" stroke-dashoffset=\"{:.2}\" transform=\"rotate(-90 {c} {c})\"><title>{}: {}</title></circle>\n",
+            slice.color, length, circumference - length, -offset, escape(&slice.label), format_short(Duration::from_secs(slice.seconds)),
+            c = center, r = radius,
+        ));
+        offset += length;
+    }
+    svg.push_str(&format!(
+        "<text x=\"{c}\" y=\"{c}\" text-anchor=\"middle\" dominant-baseline=\"middle\" style=\"font-size:16px\">{}</text>\n</svg>\n",
+        format_short(Duration::from_secs(total)), c = center,
+    ));
+    svg
+}
+
+// One bar per day, stacked by category in the donut's colours
+fn daily_bars(activities: &[Activity], slices: &[Slice], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> String {
+    let days: Vec<NaiveDate> = from.iter_days().take_while(|d| *d <= to).collect();
+    let mut per_day: HashMap<NaiveDate, Vec<u64>> = HashMap::new();
+    for activity in activities {
+        let seconds = activity.duration.map(|d| d.as_secs()).unwrap_or_default();
+        let Some(index) = slice_index(slices, &activity.category) else { continue };
+        per_day.entry(boundary.date_of(&activity.start_time)).or_insert_with(|| vec![0; slices.len()])[index] += seconds;
+    }
+    let busiest = per_day.values().map(|s| s.iter().sum::<u64>()).max().unwrap_or(1).max(1);
+
+    let (width, height, left, bottom) = (720.0, 240.0, 50.0, 24.0);
+    let plot_height = height - bottom - 10.0;
+    let slot = (width - left) / days.len() as f64;
+    let label_every = days.len().div_ceil(14);
+    let mut svg = format!("<svg width=\"100%\" viewBox=\"0 0 {} {}\" role=\"img\">\n", width, height);
+
+    for step in 0..=4 {
+        let seconds = busiest * step / 4;
+        let y = 10.0 + plot_height - plot_height * step as f64 / 4.0;
+        svg.push_str(&format!(
+            "<line x1=\"{}\" x2=\"{}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"#eee\"/><text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+            left, width, left - 6.0, y + 4.0, format_short(Duration::from_secs(seconds)),
+        ));
+    }
+    for (i, day) in days.iter().enumerate() {
+        let x = left + slot * i as f64 + slot * 0.15;
+        let mut y = 10.0 + plot_height;
+        if let Some(stack) = per_day.get(day) {
+            for (slice, seconds) in slices.iter().zip(stack).filter(|(_, s)| **s > 0) {
+                let bar = plot_height * *seconds as f64 / busiest as f64;
+                y -= bar;
+                svg.push_str(&format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} {}: {}</title></rect>\n",
+                    x, y, slot * 0.7, bar, slice.color, day.format("%a %d"), escape(&slice.label), format_short(Duration::from_secs(*seconds)),
+                ));
+            }
+        }
+        if i % label_every == 0 {
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+                x + slot * 0.35, height - 6.0, day.format(if days.len() > 14 { "%m-%d" } else { "%a %d" }),
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn heatmap(activities: &[Activity], boundary: &DayBoundary) -> String {
+    let cells = weekday_hours(activities, boundary);
+    let busiest = cells.iter().flatten().map(|c| c.tracked_seconds).max().unwrap_or(1).max(1);
+    let (cell_width, cell_height, left, top) = (26.0, 22.0, 40.0, 18.0);
+    let mut svg = format!(
+        "<svg width=\"100%\" viewBox=\"0 0 {} {}\" role=\"img\">\n",
+        left + cell_width * 24.0, top + cell_height * 7.0,
+    );
+    for hour in (0..24).step_by(3) {
+        svg.push_str(&format!("<text x=\"{:.1}\" y=\"12\">{:02}</text>\n", left + cell_width * hour as f64, hour));
+    }
+    for (day, hours) in cells.iter().enumerate() {
+        let y = top + cell_height * day as f64;
+        svg.push_str(&format!("<text x=\"0\" y=\"{:.1}\">{}</text>\n", y + 15.0, WEEKDAYS[day]));
+        for (hour, cell) in hours.iter().enumerate() {
+            let x = left + cell_width * hour as f64;
+            let fill = if cell.tracked_seconds == 0 {
+                "fill=\"#f3f3f3\"".to_string()
+            } else {
+                let productive = cell.productive_seconds as f64 / cell.tracked_seconds as f64;
+                let intensity = 0.2 + 0.8 * cell.tracked_seconds as f64 / busiest as f64;
+                format!("fill=\"hsl({:.0},60%,42%)\" fill-opacity=\"{:.2}\"", productive * 120.0, intensity)
+            };
+            svg.push_str(&format!(
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{}\" rx=\"3\" {}><title>{} {:02}:00 - {} tracked, {} productive</title></rect>\n",
+                x + 1.0, y + 1.0, cell_width - 2.0, cell_height - 2.0, fill, WEEKDAYS[day], hour,
+                format_short(Duration::from_secs(cell.tracked_seconds)), format_short(Duration::from_secs(cell.productive_seconds)),
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Daily score from -100 to 100, with the least-squares line through the days with tracked time
+fn trend(activities: &[Activity], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> String {
+    let scores = daily_scores(activities, boundary, from, to);
+    let (width, height, left) = (720.0, 200.0, 40.0);
+    let step = if scores.len() > 1 { (width - left - 20.0) / (scores.len() - 1) as f64 } else { 0.0 };
+    let x = |i: usize| left + 10.0 + step * i as f64;
+    let y = |score: f64| 10.0 + (100.0 - score) / 200.0 * (height - 20.0);
+
+    let mut svg = format!("<svg width=\"100%\" viewBox=\"0 0 {} {}\" role=\"img\">\n", width, height);
+    for score in [100.0, 0.0, -100.0] {
+        svg.push_str(&format!(
+            "<line x1=\"{}\" x2=\"{}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"{}\"/><text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{:+.0}</text>\n",
+            left, width, if score == 0.0 { "#bbb" } else { "#eee" }, left - 6.0, y(score) + 4.0, score, y = y(score),
+        ));
+    }
+
+    let tracked: Vec<(usize, f64)> = scores.iter().enumerate()
+        .filter(|(_, s)| s.tracked_seconds > 0)
+        .map(|(i, s)| (i, s.score))
+        .collect();
+    let points: Vec<String> = tracked.iter().map(|(i, score)| format!("{:.1},{:.1}", x(*i), y(*score))).collect();
+    svg.push_str(&format!("<polyline points=\"{}\" fill=\"none\" stroke=\"#4e79a7\" stroke-width=\"2\"/>\n", points.join(" ")));
+    for (i, score) in &tracked {
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#4e79a7\"><title>{}: {:+.0}</title></circle>\n",
+            x(*i), y(*score), scores[*i].date.format("%a %Y-%m-%d"), score,
+        ));
+    }
+
+    let slope = score_trend(&scores);
+    if let Some(slope) = slope {
+        let n = tracked.len() as f64;
+        let mean_x = tracked.iter().map(|(i, _)| *i as f64).sum::<f64>() / n;
+        let mean_y = tracked.iter().map(|(_, s)| *s).sum::<f64>() / n;
+        let at = |i: usize| (mean_y + slope * (i as f64 - mean_x)).clamp(-100.0, 100.0);
+        let last = scores.len() - 1;
+        svg.push_str(&format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#e15759\" stroke-dasharray=\"6 4\"/>\n",
+            x(0), y(at(0)), x(last), y(at(last)),
+        ));
+    }
+    svg.push_str("</svg>\n");
+    match slope {
+        Some(slope) => svg.push_str(&format!("<p class=\"muted\">Trend: {:+.1} points per day</p>\n", slope)),
+        None => svg.push_str("<p class=\"muted\">Not enough tracked days for a trend</p>\n"),
+    }
+    svg
+}
+
+fn table(totals: &[GroupTotal], total_seconds: u64) -> String {
+    if totals.is_empty() {
+        return "<p class=\"muted\">Nothing tracked</p>\n".to_string();
+    }
+    let mut html = String::from("<table>\n<tr><th>Name</th><th class=\"num\">Time</th><th class=\"num\">Share</th></tr>\n");
+    for total in totals.iter().take(TABLE_ROWS) {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{:.0}%</td></tr>\n",
+            escape(&total.key), format_short(Duration::from_secs(total.seconds)), total.seconds as f64 / total_seconds.max(1) as f64 * 100.0,
+        ));
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn activity(category: &str, minutes: u64) -> Activity {
+        let mut activity = Activity::new("App".to_string(), category.to_string(), Vec::new(), true);
+        activity.duration = Some(Duration::from_secs(minutes * 60));
+        activity
+    }
+
+    #[test]
+    fn categories_beyond_the_palette_share_the_folded_slice() {
+        // "Other" is an ordinary category here and keeps its own slice
+        let mut activities: Vec<Activity> = (0..PALETTE.len() - 1)
+            .map(|i| activity(&format!("Category {}", i), 100 + i as u64))
+            .collect();
+        activities.push(activity("Other", 90));
+        activities.push(activity("Small", 5));
+        activities.push(activity("Smaller", 3));
+
+        let slices = category_slices(&activities);
+        assert_eq!(slices.len(), PALETTE.len() + 1);
+        let folded = slices.len() - 1;
+        assert_eq!(slices[folded].label, "Other categories");
+        assert_eq!(slices[folded].seconds, 8 * 60);
+
+        let other = slice_index(&slices, "Other").unwrap();
+        assert_ne!(other, folded);
+        assert_eq!(slices[other].seconds, 90 * 60);
+        assert_eq!(slice_index(&slices, "Small"), Some(folded));
+        assert_eq!(slice_index(&slices, "Category 0"), Some(PALETTE.len() - 2));
+    }
+}
@@ -1,5 +1,515 @@
-// This module will be responsible for generating reports and statistics
-// Will be implemented in a future step
+// This module is responsible for generating reports and statistics
+
+pub mod commits;
+pub mod compare;
+pub mod html;
+
+use crate::data::timezone::{DayBoundary, DisplayZone};
+use crate::data::{Activity, DailySummary, DistractionAlert, MAX_WEIGHT};
+use crate::monitor::browser::BrowserMonitor;
+use crate::monitor::{git, title};
+use crate::shell;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, FixedOffset, Local, NaiveDate, Timelike};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct CategoryTotal {
+    pub category: String,
+    pub seconds: u64,
+    pub activities: usize,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Summary {
+    pub total_seconds: u64,
+    // Weighted above, below and at zero
+    pub productive_seconds: u64,
+    pub unproductive_seconds: u64,
+    pub neutral_seconds: u64,
+    pub productive_ratio: f64,
+    pub score: f64,
+    pub activities: usize,
+    // Largest category first
+    pub categories: Vec<CategoryTotal>,
+}
+
+// How the default report is written out
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    // A single HTML page with inline SVG charts and no external assets
+    Html,
+}
+
+// What tracked time is broken down by in `report --by`
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    Category,
+    // Activity name, i.e. the app for automatically tracked time
+    Name,
+    Title,
+    // Project open in an editor, parsed from the window title
+    Workspace,
+    File,
+    // Git working tree the time was spent in
+    Repo,
+    // Repository and branch together, since branch names repeat across repositories
+    Branch,
+    // Program running in the terminal, from the shell hooks
+    Command,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupTotal {
+    pub key: String,
+    pub seconds: u64,
+    pub activities: usize,
+}
+
+// Activities without a value for the grouping are totalled under "(none)"
+pub fn group_totals(activities: &[Activity], by: GroupBy) -> Vec<GroupTotal> {
+    let mut groups: HashMap<String, GroupTotal> = HashMap::new();
+
+    for activity in activities {
+        let key = group_key(activity, by).unwrap_or_else(|| "(none)".to_string());
+        let total = groups.entry(key.clone()).or_insert_with(|| GroupTotal { key, seconds: 0, activities: 0 });
+        total.seconds += activity.duration.map(|d| d.as_secs()).unwrap_or_default();
+        total.activities += 1;
+    }
+
+    let mut totals: Vec<GroupTotal> = groups.into_values().collect();
+    totals.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.key.cmp(&b.key)));
+    totals
+}
+
+// Same totals from the daily_summaries rollup, which is much faster over long ranges. Only
+// category and name are kept in the rollup; None for any other grouping.
+pub fn summary_totals(rows: &[DailySummary], by: GroupBy) -> Option<Vec<GroupTotal>> {
+    let key: fn(&DailySummary) -> &str = match by {
+        GroupBy::Category => |row| &row.category,
+        GroupBy::Name => |row| &row.app,
+        _ => return None,
+    };
+    let mut groups: HashMap<&str, GroupTotal> = HashMap::new();
+    for row in rows.iter().filter(|r| r.tag.is_none()) {
+        let total = groups.entry(key(row)).or_insert_with(|| GroupTotal { key: key(row).to_string(), seconds: 0, activities: 0 });
+        total.seconds += row.seconds;
+        total.activities += row.activities;
+    }
+
+    let mut totals: Vec<GroupTotal> = groups.into_values().collect();
+    totals.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.key.cmp(&b.key)));
+    Some(totals)
+}
+
+fn group_key(activity: &Activity, by: GroupBy) -> Option<String> {
+    let editor = || {
+        activity.window_title.as_deref()
+            .and_then(|t| title::parse_editor_title(&activity.name, t))
+    };
+    match by {
+        GroupBy::Category => Some(activity.category.clone()),
+        GroupBy::Name => Some(activity.name.clone()),
+        GroupBy::Title => activity.window_title.clone(),
+        GroupBy::Workspace => editor().and_then(|e| e.workspace),
+        GroupBy::File => editor().and_then(|e| e.file),
+        GroupBy::Repo => activity.git_repo.as_deref().map(git::display_repo),
+        GroupBy::Branch => activity.git_repo.as_deref().map(|repo| {
+            format!("{} ({})", git::display_repo(repo), activity.git_branch.as_deref().unwrap_or("unknown"))
+        }),
+        GroupBy::Command => activity.command.as_deref().map(shell::command_label),
+    }
+}
+
+// Browser time by site: the host of the stored URL, or for rows without one (recorded before
+// URLs were kept, or while the browser would not give it out) the window title with the
+// browser's own name dropped.
+pub fn top_sites(activities: &[Activity]) -> Vec<GroupTotal> {
+    let browsing: Vec<Activity> = activities.iter()
+        .filter(|a| BrowserMonitor::is_browser_app(&a.name))
+        .cloned()
+        .map(|mut a| {
+            let host = a.url.as_deref().and_then(BrowserMonitor::site_host);
+            a.window_title = host.or_else(|| a.window_title.as_deref().map(page_title));
+            a
+        })
+        .collect();
+    group_totals(&browsing, GroupBy::Title)
+}
+
+// "Pull requests · GitHub - Google Chrome" -> "Pull requests · GitHub"
+fn page_title(window_title: &str) -> String {
+    let trimmed = window_title.trim();
+    for separator in [" - ", " — ", " – "] {
+        if let Some((page, browser)) = trimmed.rsplit_once(separator) {
+            if BrowserMonitor::is_browser_app(browser) {
+                return page.trim().to_string();
+            }
+        }
+    }
+    trimmed.to_string()
+}
+
+#[derive(Debug, Serialize, Clone, Copy, Default)]
+pub struct HourCell {
+    pub tracked_seconds: u64,
+    // Time weighted above zero
+    pub productive_seconds: u64,
+}
+
+// Tracked time per weekday (Monday first) and hour of day, on the boundary's clock. Activities
+// crossing the hour are split between the hours they cover; running activities count up to now.
+pub fn weekday_hours(activities: &[Activity], boundary: &DayBoundary) -> [[HourCell; 24]; 7] {
+    let mut cells = [[HourCell::default(); 24]; 7];
+    for activity in activities {
+        let end = end_of(activity);
+        let mut cursor = activity.start_time;
+        while cursor < end {
+            let slice_end = next_hour(cursor, boundary).min(end);
+            let seconds = (slice_end - cursor).num_seconds().max(0) as u64;
+            let weekday = boundary.date_of(&cursor).weekday().num_days_from_monday() as usize;
+            let cell = &mut cells[weekday][boundary.zone.naive_local(&cursor).hour() as usize];
+            cell.tracked_seconds += seconds;
+            if activity.weight > 0 {
+                cell.productive_seconds += seconds;
+            }
+            cursor = slice_end;
+        }
+    }
+    cells
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct HourRanking {
+    // Hours of the day, best first
+    pub most_productive: Vec<u32>,
+    // Hours of the day with a fair amount of tracked time, least productive first
+    pub least_productive: Vec<u32>,
+}
+
+// Ranks the hours of the day with every weekday added together. Hours holding less than a tenth
+// of the busiest hour's tracked time are left out of the least productive list, so an odd
+// late-night minute doesn't come out on top.
+pub fn rank_hours(cells: &[[HourCell; 24]; 7], count: usize) -> HourRanking {
+    let mut hours = [HourCell::default(); 24];
+    for day in cells {
+        for (hour, cell) in day.iter().enumerate() {
+            hours[hour].tracked_seconds += cell.tracked_seconds;
+            hours[hour].productive_seconds += cell.productive_seconds;
+        }
+    }
+    let busiest = hours.iter().map(|h| h.tracked_seconds).max().unwrap_or_default();
+    if busiest == 0 {
+        return HourRanking::default();
+    }
+
+    let mut most: Vec<u32> = (0..24).filter(|h| hours[*h as usize].productive_seconds > 0).collect();
+    most.sort_by(|a, b| hours[*b as usize].productive_seconds.cmp(&hours[*a as usize].productive_seconds).then(a.cmp(b)));
+    most.truncate(count);
+
+    let share = |hour: u32| {
+        let cell = hours[hour as usize];
+        cell.productive_seconds as f64 / cell.tracked_seconds as f64
+    };
+    let mut least: Vec<u32> = (0..24)
+        .filter(|h| hours[*h as usize].tracked_seconds * 10 >= busiest && !most.contains(h))
+        .collect();
+    least.sort_by(|a, b| share(*a).total_cmp(&share(*b)).then(a.cmp(b)));
+    least.truncate(count);
+
+    HourRanking { most_productive: most, least_productive: least }
+}
+
+fn next_hour(time: DateTime<Local>, boundary: &DayBoundary) -> DateTime<Local> {
+    let local = boundary.zone.naive_local(&time);
+    let into_hour = ChronoDuration::minutes(local.minute() as i64)
+        + ChronoDuration::seconds(local.second() as i64)
+        + ChronoDuration::nanoseconds(local.nanosecond() as i64);
+    time - into_hour + ChronoDuration::hours(1)
+}
+
+// When an activity ended, or now while it is running
+fn end_of(activity: &Activity) -> DateTime<Local> {
+    match activity.duration {
+        Some(duration) => activity.start_time + ChronoDuration::from_std(duration).unwrap_or_default(),
+        None => Local::now(),
+    }
+}
+
+// The parts of an activity within each reporting day from `from` to `to`, oldest first
+fn day_pieces(activity: &Activity, boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, Activity)> {
+    let end = end_of(activity);
+    let mut pieces = Vec::new();
+    let mut date = boundary.date_of(&activity.start_time).max(from);
+    while date <= to {
+        let Some(next) = date.succ_opt() else {
+            break;
+        };
+        let (day_start, day_end) = (boundary.start_of(date), boundary.start_of(next));
+        if day_start >= end {
+            break;
+        }
+        let start = activity.start_time.max(day_start);
+        let stop = end.min(day_end);
+        if stop > start || start == activity.start_time {
+            let mut piece = activity.clone();
+            piece.start_time = start;
+            piece.end_time = Some(stop);
+            piece.duration = (stop - start).to_std().ok();
+            pieces.push((date, piece));
+        }
+        date = next;
+    }
+    pieces
+}
+
+// Activities cut where one reporting day ends and the next begins, keeping only the days from
+// `from` to `to`. Running activities are cut off at the current time.
+pub fn split_by_day(activities: &[Activity], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> Vec<Activity> {
+    activities.iter()
+        .flat_map(|activity| day_pieces(activity, boundary, from, to))
+        .map(|(_, piece)| piece)
+        .collect()
+}
+
+// Midnight on the wall clock the activity was recorded on, at the offset it started with: the
+// days the daily_summaries rollup counts it on
+fn recorded_days(activity: &Activity) -> DayBoundary {
+    let zone = activity.zone.as_deref()
+        .and_then(|zone| zone.parse::<DisplayZone>().ok())
+        .unwrap_or(DisplayZone::Local);
+    let offset = (zone.naive_local(&activity.start_time) - activity.start_time.naive_utc()).num_seconds() as i32;
+    DayBoundary {
+        zone: FixedOffset::east_opt(offset).map_or(zone, DisplayZone::Fixed),
+        start_hour: 0,
+    }
+}
+
+// Like `split_by_day`, but on the days each activity was recorded in, so totals match the
+// rollup's for the same range. Load the activities a couple of days either side of it, as
+// recorded days can be a day off the local ones.
+pub fn split_by_recorded_day(activities: &[Activity], from: NaiveDate, to: NaiveDate) -> Vec<Activity> {
+    activities.iter()
+        .flat_map(|activity| day_pieces(activity, &recorded_days(activity), from, to))
+        .map(|(_, piece)| piece)
+        .collect()
+}
+
+// The rows the daily_summaries rollup would hold for these activities, with days on the given
+// boundary instead of the midnight the activities were recorded in. Activities count once, on
+// the day they started; their time goes to every day they cover.
+pub fn day_summaries(activities: &[Activity], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> Vec<DailySummary> {
+    let mut rows: HashMap<(NaiveDate, String, String, Option<String>), DailySummary> = HashMap::new();
+    for activity in activities {
+        for (date, piece) in day_pieces(activity, boundary, from, to) {
+            let seconds = piece.duration.map(|d| d.as_secs()).unwrap_or_default();
+            let started = piece.start_time == activity.start_time;
+            let mut tags: Vec<Option<String>> = vec![None];
+            for tag in &activity.tags {
+                if !tags.contains(&Some(tag.clone())) {
+                    tags.push(Some(tag.clone()));
+                }
+            }
+            for tag in tags {
+                let key = (date, activity.category.clone(), activity.name.clone(), tag.clone());
+                let row = rows.entry(key).or_insert_with(|| DailySummary {
+                    date,
+                    category: activity.category.clone(),
+                    app: activity.name.clone(),
+                    tag,
+                    seconds: 0,
+                    activities: 0,
+                    weighted_seconds: 0,
+                    productive_seconds: 0,
+                    unproductive_seconds: 0,
+                });
+                row.seconds += seconds;
+                row.activities += started as usize;
+                row.weighted_seconds += activity.weight as i64 * seconds as i64;
+                if activity.weight > 0 {
+                    row.productive_seconds += seconds;
+                } else if activity.weight < 0 {
+                    row.unproductive_seconds += seconds;
+                }
+            }
+        }
+    }
+    let mut rows: Vec<DailySummary> = rows.into_values().collect();
+    rows.sort_by_key(|r| r.date);
+    rows
+}
+
+pub fn summarize(activities: &[Activity]) -> Summary {
+    let mut summary = Summary::default();
+    let mut categories: HashMap<&str, CategoryTotal> = HashMap::new();
+
+    for activity in activities {
+        let seconds = activity.duration.map(|d| d.as_secs()).unwrap_or_default();
+        summary.total_seconds += seconds;
+        summary.activities += 1;
+        match activity.weight {
+            w if w > 0 => summary.productive_seconds += seconds,
+            w if w < 0 => summary.unproductive_seconds += seconds,
+            _ => summary.neutral_seconds += seconds,
+        }
+
+        let total = categories.entry(&activity.category).or_insert_with(|| CategoryTotal {
+            category: activity.category.clone(),
+            ..Default::default()
+        });
+        total.seconds += seconds;
+        total.activities += 1;
+    }
+
+    if summary.total_seconds > 0 {
+        summary.productive_ratio = summary.productive_seconds as f64 / summary.total_seconds as f64;
+    }
+    summary.score = score(activities);
+    summary.categories = categories.into_values().collect();
+    summary.categories.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.category.cmp(&b.category)));
+    summary
+}
+
+// Same summary from the daily_summaries rollup. An activity spanning several days counts once
+// on each of them, as after `split_by_day`.
+pub fn summarize_rows(rows: &[DailySummary]) -> Summary {
+    let mut summary = Summary::default();
+    let mut weighted_seconds = 0;
+    for row in rows.iter().filter(|r| r.tag.is_none()) {
+        summary.total_seconds += row.seconds;
+        summary.productive_seconds += row.productive_seconds;
+        summary.unproductive_seconds += row.unproductive_seconds;
+        summary.activities += row.activities;
+        weighted_seconds += row.weighted_seconds;
+    }
+    summary.neutral_seconds = summary.total_seconds.saturating_sub(summary.productive_seconds + summary.unproductive_seconds);
+
+    if summary.total_seconds > 0 {
+        summary.productive_ratio = summary.productive_seconds as f64 / summary.total_seconds as f64;
+    }
+    summary.score = weighted_score(weighted_seconds, summary.total_seconds);
+    summary.categories = summary_totals(rows, GroupBy::Category).unwrap_or_default()
+        .into_iter()
+        .map(|t| CategoryTotal { category: t.key, seconds: t.seconds, activities: t.activities })
+        .collect();
+    summary
+}
+
+// Time-weighted average productivity weight, scaled to -100 (all distraction) .. 100 (all deep work)
+pub fn score<'a>(activities: impl IntoIterator<Item = &'a Activity>) -> f64 {
+    let (weighted, total) = activities.into_iter().fold((0i64, 0u64), |(weighted, total), activity| {
+        let seconds = activity.duration.map(|d| d.as_secs()).unwrap_or_default();
+        (weighted + activity.weight as i64 * seconds as i64, total + seconds)
+    });
+    weighted_score(weighted, total)
+}
+
+// Sum of weight × seconds over the seconds they cover, on the score's scale
+pub fn weighted_score(weighted_seconds: i64, seconds: u64) -> f64 {
+    if seconds == 0 {
+        return 0.0;
+    }
+    weighted_seconds as f64 / (seconds as f64 * MAX_WEIGHT as f64) * 100.0
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct DailyScore {
+    pub date: NaiveDate,
+    pub score: f64,
+    pub tracked_seconds: u64,
+}
+
+// One entry per day from `from` to `to`, including days with nothing tracked.
+// Activities count on the reporting day they started; split them with `split_by_day` first.
+pub fn daily_scores(activities: &[Activity], boundary: &DayBoundary, from: NaiveDate, to: NaiveDate) -> Vec<DailyScore> {
+    let mut by_day: HashMap<NaiveDate, Vec<&Activity>> = HashMap::new();
+    for activity in activities {
+        by_day.entry(boundary.date_of(&activity.start_time)).or_default().push(activity);
+    }
+
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|date| {
+            let day = by_day.get(&date).map(|a| a.as_slice()).unwrap_or_default();
+            DailyScore {
+                date,
+                score: score(day.iter().copied()),
+                tracked_seconds: day.iter().filter_map(|a| a.duration).map(|d| d.as_secs()).sum(),
+            }
+        })
+        .collect()
+}
+
+// daily_scores from the rollup
+pub fn summary_scores(rows: &[DailySummary], from: NaiveDate, to: NaiveDate) -> Vec<DailyScore> {
+    let mut by_day: HashMap<NaiveDate, (i64, u64)> = HashMap::new();
+    for row in rows.iter().filter(|r| r.tag.is_none()) {
+        let day = by_day.entry(row.date).or_default();
+        day.0 += row.weighted_seconds;
+        day.1 += row.seconds;
+    }
+
+    from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|date| {
+            let (weighted, seconds) = by_day.get(&date).copied().unwrap_or_default();
+            DailyScore { date, score: weighted_score(weighted, seconds), tracked_seconds: seconds }
+        })
+        .collect()
+}
+
+// Least-squares slope of the score over the days with tracked time, in points per day.
+// None with fewer than two such days.
+pub fn score_trend(scores: &[DailyScore]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = scores.iter()
+        .enumerate()
+        .filter(|(_, s)| s.tracked_seconds > 0)
+        .map(|(i, s)| (i as f64, s.score))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    Some(covariance / variance)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AlertCount {
+    pub target: String,
+    pub count: usize,
+    // Apps in front when the alerts fired, most frequent first
+    pub apps: Vec<String>,
+}
+
+// How often each alert rule fired, most often first
+pub fn alert_counts(alerts: &[DistractionAlert]) -> Vec<AlertCount> {
+    let mut targets: HashMap<&str, (usize, HashMap<&str, usize>)> = HashMap::new();
+    for alert in alerts {
+        let (count, apps) = targets.entry(&alert.target).or_default();
+        *count += 1;
+        *apps.entry(&alert.app).or_default() += 1;
+    }
+
+    let mut counts: Vec<AlertCount> = targets.into_iter()
+        .map(|(target, (count, apps))| {
+            let mut apps: Vec<(&str, usize)> = apps.into_iter().collect();
+            apps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            AlertCount {
+                target: target.to_string(),
+                count,
+                apps: apps.into_iter().map(|(app, _)| app.to_string()).collect(),
+            }
+        })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    counts
+}
 
 pub fn generate_daily_report() {
     println!("Daily report functionality will be implemented here");
@@ -9,3 +519,110 @@ pub fn generate_weekly_report() {
     println!("Weekly report functionality will be implemented here");
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::{at, finished};
+
+    fn activity(weight: i8, start: DateTime<Local>, end: DateTime<Local>) -> Activity {
+        finished(start, end).weight(weight).build()
+    }
+
+    // (weekday, hour, tracked minutes, productive minutes) of every cell with time in it
+    fn filled(cells: &[[HourCell; 24]; 7]) -> Vec<(usize, usize, u64, u64)> {
+        let mut filled = Vec::new();
+        for (weekday, hours) in cells.iter().enumerate() {
+            for (hour, cell) in hours.iter().enumerate() {
+                if cell.tracked_seconds > 0 {
+                    filled.push((weekday, hour, cell.tracked_seconds / 60, cell.productive_seconds / 60));
+                }
+            }
+        }
+        filled
+    }
+
+    #[test]
+    fn spans_are_split_at_hour_boundaries() {
+        let utc = DayBoundary { zone: DisplayZone::Utc, start_hour: 0 };
+        // Monday 2 March
+        let cells = weekday_hours(&[
+            activity(2, at(2, 9, 20), at(2, 12, 10)),
+            activity(0, at(2, 12, 10), at(2, 12, 40)),
+        ], &utc);
+        assert_eq!(filled(&cells), [(0, 9, 40, 40), (0, 10, 60, 60), (0, 11, 60, 60), (0, 12, 40, 10)]);
+
+        // Hours begin at half past in a zone offset by a half hour
+        let india = DayBoundary { zone: "+05:30".parse().unwrap(), start_hour: 0 };
+        let cells = weekday_hours(&[activity(1, at(2, 9, 0), at(2, 10, 0))], &india);
+        assert_eq!(filled(&cells), [(0, 14, 30, 30), (0, 15, 30, 30)]);
+    }
+
+    #[test]
+    fn time_past_midnight_counts_towards_the_day_before_a_late_day_start() {
+        // Tuesday 3 March 23:30 to Wednesday 01:15
+        let late_night = [activity(1, at(3, 23, 30), at(4, 1, 15))];
+
+        let midnight = DayBoundary { zone: DisplayZone::Utc, start_hour: 0 };
+        assert_eq!(filled(&weekday_hours(&late_night, &midnight)), [(1, 23, 30, 30), (2, 0, 60, 60), (2, 1, 15, 15)]);
+
+        let four_am = DayBoundary { zone: DisplayZone::Utc, start_hour: 4 };
+        assert_eq!(filled(&weekday_hours(&late_night, &four_am)), [(1, 0, 60, 60), (1, 1, 15, 15), (1, 23, 30, 30)]);
+    }
+
+    #[test]
+    fn summaries_from_the_rollup_match_those_from_activities() {
+        let activities = [
+            activity(2, at(2, 9, 0), at(2, 10, 0)),
+            activity(-1, at(2, 10, 0), at(2, 10, 30)),
+            activity(0, at(3, 9, 0), at(3, 9, 15)),
+        ];
+        let row = |day: u32, category: &str, tag: Option<&str>, minutes: u64, weight: i64| DailySummary {
+            date: NaiveDate::from_ymd_opt(2026, 3, day).unwrap(),
+            category: category.to_string(),
+            app: "Code".to_string(),
+            tag: tag.map(str::to_string),
+            seconds: minutes * 60,
+            activities: 1,
+            weighted_seconds: weight * minutes as i64 * 60,
+            productive_seconds: if weight > 0 { minutes * 60 } else { 0 },
+            unproductive_seconds: if weight < 0 { minutes * 60 } else { 0 },
+        };
+        let rows = [
+            row(2, "Development", None, 60, 2),
+            row(2, "Development", Some("rust"), 60, 2),
+            row(2, "Development", None, 30, -1),
+            row(3, "Development", None, 15, 0),
+        ];
+
+        let from_rows = summarize_rows(&rows);
+        let from_activities = summarize(&activities);
+        assert_eq!(from_rows.total_seconds, 105 * 60);
+        assert_eq!(
+            (from_rows.productive_seconds, from_rows.unproductive_seconds, from_rows.neutral_seconds, from_rows.activities),
+            (from_activities.productive_seconds, from_activities.unproductive_seconds, from_activities.neutral_seconds, from_activities.activities),
+        );
+        assert_eq!(from_rows.score, from_activities.score);
+        assert_eq!(from_rows.categories.len(), 1);
+        assert_eq!(from_rows.categories[0].seconds, from_activities.categories[0].seconds);
+    }
+
+    #[test]
+    fn hours_with_little_time_are_not_ranked_least_productive() {
+        let mut cells = [[HourCell::default(); 24]; 7];
+        let mut set = |weekday: usize, hour: usize, tracked: u64, productive: u64| {
+            cells[weekday][hour] = HourCell { tracked_seconds: tracked * 60, productive_seconds: productive * 60 };
+        };
+        set(0, 9, 40, 40);
+        set(1, 9, 20, 20);
+        set(0, 10, 60, 30);
+        set(0, 14, 60, 10);
+        set(2, 16, 30, 0);
+        // Under a tenth of the busiest hour
+        set(4, 23, 5, 0);
+
+        let ranking = rank_hours(&cells, 2);
+        assert_eq!(ranking.most_productive, [9, 10]);
+        assert_eq!(ranking.least_productive, [16, 14]);
+        assert_eq!(rank_hours(&[[HourCell::default(); 24]; 7], 2).most_productive, Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,154 @@
+// User categorization rules, read from rules.json in the data directory. The first rule whose
+// patterns all match wins; anything unmatched falls back to the built-in app heuristics.
+//
+//     [
+//         { "title": "(?i)jira|confluence", "category": "Planning" },
+//         { "app": "Code", "title": "personal-site", "category": "Side project", "productive": false },
+//         { "command": "^ssh prod", "category": "Ops", "weight": 1 },
+//         { "url": "reddit\\.com", "category": "Social Media", "weight": -2 }
+//     ]
+//
+// Patterns are regular expressions matched anywhere in the app name, window title, URL or the
+// command running in a terminal (reported by the shell hooks). `weight` is the productivity
+// weight from -2 to +2; the older `productive` flag stands for +1 or 0.
+
+use crate::data::{weight_from_flag, MAX_WEIGHT, MIN_WEIGHT};
+use crate::db;
+use regex::Regex;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRule {
+    app: Option<String>,
+    title: Option<String>,
+    url: Option<String>,
+    command: Option<String>,
+    category: String,
+    productive: Option<bool>,
+    weight: Option<i8>,
+}
+
+#[derive(Debug)]
+pub struct Rule {
+    app: Option<Regex>,
+    title: Option<Regex>,
+    url: Option<Regex>,
+    command: Option<Regex>,
+    pub category: String,
+    pub productive: Option<bool>,
+    pub weight: Option<i8>,
+}
+
+#[derive(Debug, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+pub fn rules_path() -> PathBuf {
+    db::data_dir().join("rules.json")
+}
+
+impl RuleSet {
+    // No file means no rules
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = rules_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(&path)?;
+        Self::parse(&contents, &path)
+    }
+
+    fn parse(contents: &str, path: &Path) -> Result<Self, Box<dyn Error>> {
+        let raw: Vec<RawRule> = serde_json::from_str(contents)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        let mut rules = Vec::with_capacity(raw.len());
+        for (i, rule) in raw.into_iter().enumerate() {
+            let compile = |pattern: Option<String>| -> Result<Option<Regex>, String> {
+                pattern
+                    .map(|p| Regex::new(&p).map_err(|e| format!("{}: rule {}: {}", path.display(), i + 1, e)))
+                    .transpose()
+            };
+            // A rule without patterns would match every sample
+            if rule.app.is_none() && rule.title.is_none() && rule.url.is_none() && rule.command.is_none() {
+                return Err(format!("{}: rule {}: needs at least one of app, title, url or command", path.display(), i + 1).into());
+            }
+            if let Some(weight) = rule.weight.filter(|w| !(MIN_WEIGHT..=MAX_WEIGHT).contains(w)) {
+                return Err(format!("{}: rule {}: weight {} is outside {}..{}", path.display(), i + 1, weight, MIN_WEIGHT, MAX_WEIGHT).into());
+            }
+            rules.push(Rule {
+                app: compile(rule.app)?,
+                title: compile(rule.title)?,
+                url: compile(rule.url)?,
+                command: compile(rule.command)?,
+                category: rule.category,
+                productive: rule.productive,
+                weight: rule.weight,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    // Falls back to no rules, with a warning, so a typo in the file never stops tracking
+    pub fn load_or_warn() -> Self {
+        Self::load().unwrap_or_else(|e| {
+            eprintln!("Ignoring categorization rules: {}", e);
+            Self::default()
+        })
+    }
+
+    pub fn find(&self, app_name: &str, window_title: Option<&str>, browser_url: Option<&str>, command: Option<&str>) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(app_name, window_title, browser_url, command))
+    }
+}
+
+impl Rule {
+    // None leaves the weight to the built-in heuristics
+    pub fn weight(&self) -> Option<i8> {
+        self.weight.or_else(|| self.productive.map(weight_from_flag))
+    }
+
+    // A pattern on a field the sample lacks never matches
+    fn matches(&self, app_name: &str, window_title: Option<&str>, browser_url: Option<&str>, command: Option<&str>) -> bool {
+        let check = |pattern: &Option<Regex>, value: Option<&str>| match pattern {
+            Some(pattern) => value.map(|v| pattern.is_match(v)).unwrap_or(false),
+            None => true,
+        };
+        check(&self.app, Some(app_name)) && check(&self.title, window_title) && check(&self.url, browser_url)
+            && check(&self.command, command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(rules: &str) -> Result<RuleSet, String> {
+        RuleSet::parse(rules, Path::new("rules.json")).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = parse(r#"[
+            { "app": "Code", "title": "personal-site", "category": "Side project", "productive": false },
+            { "title": "(?i)jira", "category": "Planning", "weight": 1 }
+        ]"#).unwrap();
+        assert_eq!(rules.find("Code", Some("index.html - personal-site"), None, None).map(|r| r.weight()), Some(Some(0)));
+        assert_eq!(rules.find("Firefox", Some("JIRA board"), None, None).map(|r| r.category.as_str()), Some("Planning"));
+        assert!(rules.find("Firefox", None, None, None).is_none());
+    }
+
+    #[test]
+    fn rules_with_typos_or_no_patterns_are_rejected() {
+        let typo = parse(r#"[{ "titel": "jira", "category": "Planning" }]"#).unwrap_err();
+        assert!(typo.contains("unknown field `titel`"), "{}", typo);
+        assert_eq!(
+            parse(r#"[{ "category": "Planning", "weight": 1 }]"#).unwrap_err(),
+            "rules.json: rule 1: needs at least one of app, title, url or command",
+        );
+    }
+}
@@ -0,0 +1,478 @@
+use crate::data::timezone::{DayBoundary, DisplayZone};
+use crate::data::{weight_from_flag, Activity, ActivityChange, ActivityFilter, MAX_WEIGHT, MIN_WEIGHT};
+use crate::db;
+use crate::ipc;
+use crate::reports::{self, Summary};
+use crate::tracker::Tracker;
+use axum::extract::{Query, Request, State};
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+// How often the database is checked for rows written or changed by other processes, such as the daemon
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub enum Bind {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+// Pushed to `GET /events` subscribers as server-sent events
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActivityEvent {
+    Started { activity: Activity },
+    Stopped { activity: Activity },
+    // An activity written by another process, finished or still running
+    Recorded { activity: Activity },
+    // An activity changed by another process, e.g. stopped, edited or retagged
+    Updated { activity: Activity },
+    Deleted { id: i64 },
+}
+
+impl ActivityEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            ActivityEvent::Started { .. } => "started",
+            ActivityEvent::Stopped { .. } => "stopped",
+            ActivityEvent::Recorded { .. } => "recorded",
+            ActivityEvent::Updated { .. } => "updated",
+            ActivityEvent::Deleted { .. } => "deleted",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    tracker: Arc<Mutex<Tracker>>,
+    events: broadcast::Sender<ActivityEvent>,
+    token: Arc<String>,
+    // Highest activity id already announced to subscribers
+    last_seen_id: Arc<AtomicI64>,
+    // Latest change to the activities table the poller has looked at
+    last_change: Arc<AtomicI64>,
+    // Change number of each activity this server announced itself, for the poller to skip
+    announced: Arc<Mutex<HashMap<i64, i64>>>,
+}
+
+pub async fn serve(bind: Bind, token: String) -> Result<(), Box<dyn Error>> {
+    let tracker = Tracker::new()?;
+    let last_seen_id = tracker.database().last_activity_id()?;
+    let last_change = tracker.database().last_activity_change()?;
+    let (events, _) = broadcast::channel(64);
+
+    let state = AppState {
+        tracker: Arc::new(Mutex::new(tracker)),
+        events,
+        token: Arc::new(token),
+        last_seen_id: Arc::new(AtomicI64::new(last_seen_id)),
+        last_change: Arc::new(AtomicI64::new(last_change)),
+        announced: Arc::new(Mutex::new(HashMap::new())),
+    };
+    tokio::spawn(poll_changes(state.clone()));
+
+    let app = Router::new()
+        .route("/activities", get(list_activities))
+        .route("/current", get(current_activity))
+        .route("/start", post(start_activity))
+        .route("/stop", post(stop_activity))
+        .route("/reports/summary", get(summary))
+        .route("/events", get(events_stream))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token))
+        .with_state(state);
+
+    match bind {
+        Bind::Tcp(addr) => {
+            // The API can start and stop tracking, so it is never exposed beyond this machine
+            if !addr.ip().is_loopback() {
+                return Err(format!("refusing to listen on non-loopback address {}", addr).into());
+            }
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            println!("Listening on http://{}", addr);
+            axum::serve(listener, app).await?;
+        },
+        Bind::Unix(path) => {
+            let listener = bind_unix(&path)?;
+            println!("Listening on unix:{}", path.display());
+            axum::serve(listener, app).await?;
+        }
+    }
+    Ok(())
+}
+
+// Binds a socket at `path`, replacing only a socket nobody answers on any more. The socket is
+// created inside a private directory and moved into place once only its owner can use it, so
+// there is no moment where another user could connect.
+pub(crate) fn bind_unix(path: &std::path::Path) -> Result<tokio::net::UnixListener, Box<dyn Error>> {
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt};
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if !meta.file_type().is_socket() => {
+            return Err(format!("refusing to replace {}: it is not a socket", path.display()).into());
+        },
+        Ok(_) if std::os::unix::net::UnixStream::connect(path).is_ok() => {
+            return Err(format!("{} is in use by another server", path.display()).into());
+        },
+        // Left behind by a server that did not shut down cleanly
+        Ok(_) => std::fs::remove_file(path)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e.into()),
+    }
+
+    let name = path.file_name().ok_or_else(|| format!("{} is not a file path", path.display()))?;
+    let staging = path.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join("socket");
+    let bound = tokio::net::UnixListener::bind(&staged)
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|listener| {
+            restrict_permissions(&staged)?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&staging)?;
+    bound
+}
+
+// The token lives next to the database and is created on first use
+pub fn load_or_create_token() -> Result<String, Box<dyn Error>> {
+    let path = db::data_dir().join("api_token");
+    if let Ok(token) = std::fs::read_to_string(&path) {
+        let token = token.trim().to_string();
+        if !token.is_empty() {
+            return Ok(token);
+        }
+    }
+
+    let mut bytes = [0u8; 24];
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    // Created owner-only from the start, so the token is never readable by others; an
+    // empty leftover file is replaced
+    use std::os::unix::fs::OpenOptionsExt;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(&path)?;
+    file.write_all(token.as_bytes())?;
+    Ok(token)
+}
+
+fn restrict_permissions(path: &std::path::Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+// Accepts "Authorization: Bearer <token>", or "?token=<token>" for EventSource clients
+// that cannot set headers
+async fn require_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    if is_authorized(request.headers(), request.uri(), &state.token) {
+        next.run(request).await
+    } else {
+        ApiError(StatusCode::UNAUTHORIZED, "missing or invalid token".to_string()).into_response()
+    }
+}
+
+// A bearer token in the header is checked in preference to one in the query
+fn is_authorized(headers: &HeaderMap, uri: &Uri, token: &str) -> bool {
+    let from_header = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim());
+    let from_query = uri
+        .query()
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("token=")));
+
+    from_header.or(from_query)
+        .map(|t| constant_time_eq(t.as_bytes(), token.as_bytes()))
+        .unwrap_or(false)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(e: rusqlite::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct RangeQuery {
+    // Inclusive calendar days in the local time zone
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    category: Option<String>,
+    // Comma separated, matches any
+    tags: Option<String>,
+    limit: Option<usize>,
+}
+
+impl RangeQuery {
+    fn filter(&self) -> ActivityFilter {
+        ActivityFilter {
+            from: self.from.map(|d| DisplayZone::Local.start_of_day(d)),
+            to: self.to.and_then(|d| d.succ_opt()).map(|d| DisplayZone::Local.start_of_day(d)),
+            category: self.category.clone(),
+            tags: self.tags.as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+async fn list_activities(State(state): State<AppState>, Query(query): Query<RangeQuery>) -> Result<Json<Vec<Activity>>, ApiError> {
+    let tracker = state.tracker.lock().unwrap();
+    let mut activities = tracker.database().get_activities(&query.filter())?;
+
+    // Most recent activities are the interesting ones when the list is cut short
+    if let Some(limit) = query.limit {
+        let skip = activities.len().saturating_sub(limit);
+        activities.drain(..skip);
+    }
+    Ok(Json(activities))
+}
+
+async fn current_activity(State(state): State<AppState>) -> Result<Json<Option<Activity>>, ApiError> {
+    let mut tracker = state.tracker.lock().unwrap();
+    tracker.refresh()?;
+    Ok(Json(tracker.get_current_activity().cloned()))
+}
+
+#[derive(Deserialize)]
+struct StartRequest {
+    name: String,
+    category: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default = "default_productive")]
+    productive: bool,
+    // -2 to +2, overriding `productive`
+    #[serde(default)]
+    weight: Option<i8>,
+}
+
+fn default_productive() -> bool {
+    true
+}
+
+async fn start_activity(State(state): State<AppState>, Json(request): Json<StartRequest>) -> Result<Json<Activity>, ApiError> {
+    // Checked before anything changes, so a bad request leaves the running activity alone
+    if let Some(weight) = request.weight.filter(|w| !(MIN_WEIGHT..=MAX_WEIGHT).contains(w)) {
+        return Err(ApiError(StatusCode::BAD_REQUEST, format!("weight {} is outside {}..{}", weight, MIN_WEIGHT, MAX_WEIGHT)));
+    }
+    let weight = request.weight.unwrap_or_else(|| weight_from_flag(request.productive));
+
+    // Starting a new activity implicitly stops the running one
+    let (stopped, started) = through_daemon(move || {
+        let stopped = ipc::stop_activity()?;
+        Ok((stopped, ipc::start_activity(request.name, request.category, request.tags, weight)?))
+    }).await?;
+    if let Some(stopped) = stopped {
+        announce_stopped(&state, stopped);
+    }
+
+    let activity = started
+        .ok_or_else(|| ApiError(StatusCode::INTERNAL_SERVER_ERROR, "activity did not start".to_string()))?;
+    mark_announced(&state, &activity);
+    let _ = state.events.send(ActivityEvent::Started { activity: activity.clone() });
+    Ok(Json(activity))
+}
+
+async fn stop_activity(State(state): State<AppState>) -> Result<Json<Option<Activity>>, ApiError> {
+    let stopped = through_daemon(ipc::stop_activity).await?;
+    if let Some(activity) = &stopped {
+        announce_stopped(&state, activity.clone());
+    }
+    Ok(Json(stopped))
+}
+
+// Starts and stops go through the daemon, like the CLI's, so automatic tracking steps aside
+// while a manual activity runs. Without a daemon they are written to the database directly.
+async fn through_daemon<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, Box<dyn Error>> + Send + 'static,
+) -> Result<T, ApiError> {
+    tokio::task::spawn_blocking(move || f().map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| ApiError(StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+fn announce_stopped(state: &AppState, activity: Activity) {
+    mark_announced(state, &activity);
+    let _ = state.events.send(ActivityEvent::Stopped { activity });
+}
+
+// The poller must not announce this write a second time
+fn mark_announced(state: &AppState, activity: &Activity) {
+    let Some(id) = activity.id else {
+        return;
+    };
+    state.last_seen_id.fetch_max(id, Ordering::SeqCst);
+    let change = state.tracker.lock().unwrap().database().activity_change(id);
+    if let Ok(Some(seq)) = change {
+        state.announced.lock().unwrap().insert(id, seq);
+    }
+}
+
+// Defaults to today when no range is given, and to everything since the first activity when
+// only `to` is. Time crossing into or out of the range only counts for the part inside it.
+async fn summary(State(state): State<AppState>, Query(query): Query<RangeQuery>) -> Result<Json<Summary>, ApiError> {
+    let today = Local::now().date_naive();
+    let tracker = state.tracker.lock().unwrap();
+    let db = tracker.database();
+    let (from, to) = match (query.from, query.to) {
+        (None, None) => (today, today),
+        (Some(from), to) => (from, to.unwrap_or(today)),
+        (None, Some(to)) => (db.first_activity_start()?.map_or(to, |start| start.date_naive().min(to)), to),
+    };
+    if to < from {
+        return Err(ApiError(StatusCode::BAD_REQUEST, "'to' must not be before 'from'".to_string()));
+    }
+
+    // The rollup has no rows for a match on any of several tags
+    let filter = query.filter();
+    if filter.tags.is_empty() {
+        let rows = db.get_daily_summaries(from, to, query.category.as_deref())?;
+        return Ok(Json(reports::summarize_rows(&rows)));
+    }
+    let boundary = DayBoundary::default();
+    let activities = db.get_activities(&ActivityFilter {
+        from: Some(boundary.start_of(from)),
+        to: Some(boundary.start_of(to.succ_opt().unwrap_or(to))),
+        overlapping: true,
+        ..filter
+    })?;
+    Ok(Json(reports::summarize(&reports::split_by_day(&activities, &boundary, from, to))))
+}
+
+async fn events_stream(State(state): State<AppState>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe())
+        // A lagging subscriber only misses events, the stream stays open
+        .filter_map(|event| event.ok())
+        .map(|event| {
+            let sse = Event::default().event(event.name());
+            Ok(sse.json_data(&event).unwrap_or_else(|_| Event::default().comment("unserializable event")))
+        });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+// Announces activities written, changed or deleted by the daemon, the CLI or the dashboard
+// while the server is running. A row changed several times between two polls is announced
+// once, as it is after the latest change.
+async fn poll_changes(state: AppState) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let after = state.last_change.load(Ordering::SeqCst);
+        let changes = {
+            let tracker = state.tracker.lock().unwrap();
+            tracker.database().get_activity_changes(after)
+        };
+
+        match changes {
+            Ok(changes) => {
+                for change in changes {
+                    state.last_change.fetch_max(change.seq, Ordering::SeqCst);
+                    if let Some(event) = change_event(&state, change) {
+                        let _ = state.events.send(event);
+                    }
+                }
+            },
+            Err(e) => eprintln!("Failed to poll for activity changes: {}", e),
+        }
+    }
+}
+
+// None for a change this server made and announced itself
+fn change_event(state: &AppState, change: ActivityChange) -> Option<ActivityEvent> {
+    let announced = state.announced.lock().unwrap().remove(&change.id);
+    if announced == Some(change.seq) {
+        return None;
+    }
+    let Some(activity) = change.activity else {
+        return Some(ActivityEvent::Deleted { id: change.id });
+    };
+    let seen_before = state.last_seen_id.fetch_max(change.id, Ordering::SeqCst);
+    if change.id > seen_before {
+        Some(ActivityEvent::Recorded { activity })
+    } else {
+        Some(ActivityEvent::Updated { activity })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorized(header: Option<&str>, uri: &str) -> bool {
+        let mut headers = HeaderMap::new();
+        if let Some(value) = header {
+            headers.insert(header::AUTHORIZATION, value.parse().unwrap());
+        }
+        is_authorized(&headers, &uri.parse().unwrap(), "s3cret")
+    }
+
+    #[test]
+    fn accepts_the_token_as_a_bearer_header_or_query_parameter() {
+        assert!(authorized(Some("Bearer s3cret"), "/activities"));
+        assert!(authorized(Some("Bearer  s3cret "), "/activities"));
+        assert!(authorized(None, "/events?since=1&token=s3cret"));
+    }
+
+    #[test]
+    fn rejects_missing_wrong_or_partial_tokens() {
+        assert!(!authorized(None, "/activities"));
+        assert!(!authorized(Some("s3cret"), "/activities"));
+        assert!(!authorized(Some("Basic s3cret"), "/activities"));
+        assert!(!authorized(Some("Bearer s3cre"), "/activities"));
+        assert!(!authorized(Some("Bearer s3cret!"), "/activities"));
+        assert!(!authorized(None, "/events?token="));
+        assert!(!authorized(None, "/events?xtoken=s3cret"));
+        // A wrong header is not rescued by a right query parameter
+        assert!(!authorized(Some("Bearer nope"), "/events?token=s3cret"));
+    }
+
+    #[test]
+    fn compares_tokens_of_any_length() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+}
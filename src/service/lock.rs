@@ -0,0 +1,55 @@
+use crate::db;
+use fs2::FileExt;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+// Held for as long as the daemon runs. The lock, not the file, is what counts: a pidfile
+// left behind by a crash is simply locked again by the next daemon.
+pub struct InstanceLock {
+    _file: File,
+}
+
+pub fn pidfile_path() -> PathBuf {
+    db::data_dir().join("daemon.pid")
+}
+
+impl InstanceLock {
+    pub fn acquire() -> Result<Self, Box<dyn Error>> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(pidfile_path())?;
+
+        if file.try_lock_exclusive().is_err() {
+            let pid = read_pid(&mut file).map(|p| format!(" (pid {})", p)).unwrap_or_default();
+            return Err(format!("another daemon is already running{}", pid).into());
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+        Ok(Self { _file: file })
+    }
+}
+
+// Pid of the daemon holding the lock, if one is running
+pub fn running_pid() -> Option<u32> {
+    let mut file = File::open(pidfile_path()).ok()?;
+    if file.try_lock_shared().is_ok() {
+        let _ = FileExt::unlock(&file);
+        return None;
+    }
+    read_pid(&mut file)
+}
+
+fn read_pid(file: &mut File) -> Option<u32> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    contents.trim().parse().ok()
+}
@@ -0,0 +1,229 @@
+pub mod lock;
+pub mod notify;
+
+use crate::db;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub use self::lock::InstanceLock;
+
+const SYSTEMD_UNIT: &str = "productivity_tracker.service";
+const LAUNCHD_LABEL: &str = "com.productivity_tracker.daemon";
+
+// Service managers the daemon can be registered with, one per platform
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Manager {
+    Systemd,
+    Launchd,
+}
+
+#[derive(Debug)]
+pub struct ServiceStatus {
+    pub manager: Manager,
+    pub unit_path: PathBuf,
+    pub installed: bool,
+    // What the service manager reports, e.g. "active" or "failed"
+    pub state: Option<String>,
+    // Pid of whichever daemon holds the instance lock, service or not
+    pub daemon_pid: Option<u32>,
+}
+
+impl Manager {
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Manager::Launchd
+        } else {
+            Manager::Systemd
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Manager::Systemd => "systemd",
+            Manager::Launchd => "launchd",
+        }
+    }
+
+    pub fn unit_path(&self) -> PathBuf {
+        let home = dirs::home_dir().unwrap_or_default();
+        match self {
+            Manager::Systemd => dirs::config_dir()
+                .unwrap_or_else(|| home.join(".config"))
+                .join("systemd/user")
+                .join(SYSTEMD_UNIT),
+            Manager::Launchd => home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)),
+        }
+    }
+
+    // The unit file or plist that runs `<this binary> daemon --interval <interval>`
+    pub fn render(&self, interval: u64) -> Result<String, Box<dyn Error>> {
+        let exe = std::env::current_exe()?;
+        let exe = exe.to_string_lossy();
+        Ok(match self {
+            Manager::Systemd => systemd_unit(&exe, interval),
+            Manager::Launchd => launchd_plist(&exe, interval),
+        })
+    }
+}
+
+// Writes the unit and asks the service manager to start it now and at every login
+pub fn install(interval: u64) -> Result<PathBuf, Box<dyn Error>> {
+    let manager = Manager::current();
+    let path = manager.unit_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, manager.render(interval)?)?;
+
+    match manager {
+        Manager::Systemd => {
+            run("systemctl", &["--user", "daemon-reload"])?;
+            run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])?;
+        },
+        Manager::Launchd => {
+            // Reloading picks up a changed plist when the agent was already installed
+            let _ = run("launchctl", &["unload", &path.to_string_lossy()]);
+            run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+        },
+    }
+    Ok(path)
+}
+
+// Stops the service and removes the unit; returns false when nothing was installed
+pub fn uninstall() -> Result<bool, Box<dyn Error>> {
+    let manager = Manager::current();
+    let path = manager.unit_path();
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    match manager {
+        Manager::Systemd => {
+            run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT])?;
+            std::fs::remove_file(&path)?;
+            run("systemctl", &["--user", "daemon-reload"])?;
+        },
+        Manager::Launchd => {
+            run("launchctl", &["unload", "-w", &path.to_string_lossy()])?;
+            std::fs::remove_file(&path)?;
+        },
+    }
+    Ok(true)
+}
+
+pub fn status() -> ServiceStatus {
+    let manager = Manager::current();
+    let unit_path = manager.unit_path();
+    let installed = unit_path.exists();
+
+    let state = match manager {
+        Manager::Systemd => output("systemctl", &["--user", "is-active", SYSTEMD_UNIT]),
+        // `launchctl list <label>` fails when the agent is not loaded
+        Manager::Launchd => Command::new("launchctl")
+            .args(["list", LAUNCHD_LABEL])
+            .output()
+            .ok()
+            .map(|o| if o.status.success() { "loaded".to_string() } else { "not loaded".to_string() }),
+    };
+
+    ServiceStatus {
+        manager,
+        unit_path,
+        installed,
+        state,
+        daemon_pid: lock::running_pid(),
+    }
+}
+
+fn systemd_unit(exe: &str, interval: u64) -> String {
+    // The monitor pings the watchdog once per sample, so allow several missed samples
+    let watchdog = (interval * 6).max(30);
+    format!(
+        "[Unit]
+Description=Productivity tracker daemon
+After=graphical-session.target
+
+[Service]
+Type=notify
+NotifyAccess=main
+ExecStart=\"{exe}\" daemon --interval {interval}
+Restart=on-failure
+RestartSec=5
+WatchdogSec={watchdog}
+TimeoutStopSec=20
+
+[Install]
+WantedBy=default.target
+",
+        exe = exe.replace('\\', "\\\\").replace('"', "\\\""),
+        interval = interval,
+        watchdog = watchdog,
+    )
+}
+
+fn launchd_plist(exe: &str, interval: u64) -> String {
+    let log = db::data_dir().join("daemon.log");
+    let log = xml_escape(&log.to_string_lossy());
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>daemon</string>
+        <string>--interval</string>
+        <string>{interval}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ProcessType</key>
+    <string>Interactive</string>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        exe = xml_escape(exe),
+        interval = interval,
+        log = log,
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn run(program: &str, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| format!("could not run {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("`{} {}` failed with {}", program, args.join(" "), status).into());
+    }
+    Ok(())
+}
+
+// Trimmed stdout, even when the command exits non-zero as `systemctl is-active` does
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(text).filter(|t| !t.is_empty())
+}
@@ -0,0 +1,50 @@
+use std::os::unix::net::UnixDatagram;
+
+// Minimal sd_notify(3): a datagram to $NOTIFY_SOCKET, ignored when not started by systemd.
+// Failures are not worth stopping the daemon for; systemd notices a silent watchdog by itself.
+pub fn notify(state: &str) {
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) => path,
+        None => return,
+    };
+    let socket = match UnixDatagram::unbound() {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+
+    let path = path.to_string_lossy().into_owned();
+    let result = match path.strip_prefix('@') {
+        Some(name) => send_abstract(&socket, name, state),
+        None => socket.send_to(state.as_bytes(), &path).map(|_| ()),
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to notify the service manager: {}", e);
+    }
+}
+
+// Sent once the daemon is set up and sampling is about to begin
+pub fn ready() {
+    notify(&format!("READY=1\nMAINPID={}", std::process::id()));
+}
+
+// Sent on every monitor tick; a loop that hangs stops sending and gets restarted
+pub fn watchdog() {
+    notify("WATCHDOG=1");
+}
+
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+#[cfg(target_os = "linux")]
+fn send_abstract(socket: &UnixDatagram, name: &str, state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name.as_bytes())?;
+    socket.send_to_addr(state.as_bytes(), &addr).map(|_| ())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn send_abstract(_socket: &UnixDatagram, _name: &str, _state: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "abstract sockets are Linux only"))
+}
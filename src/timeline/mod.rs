@@ -0,0 +1,402 @@
+// A single day laid out in time order for the `timeline` command: either one bar per hour with a
+// cell per minute, or a list of contiguous blocks. Activities shorter than a minute are collapsed
+// into "short switches" so a burst of app switching reads as one block, and holes between
+// activities show up as pauses (from the gaps table) or as idle / untracked time.
+
+use crate::data::duration::format_short;
+use crate::data::{Activity, Gap};
+use chrono::{DateTime, Duration as ChronoDuration, Local, Timelike};
+use colored::*;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Blocks and holes shorter than this are not shown on their own
+const MIN_BLOCK: ChronoDuration = ChronoDuration::seconds(60);
+
+// Tag the daemon puts on everything it records; anything without it was tracked by hand
+const AUTOMATIC_TAG: &str = "automatic";
+
+const PALETTE: [Color; 12] = [
+    Color::Blue,
+    Color::Green,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Red,
+    Color::BrightBlue,
+    Color::BrightGreen,
+    Color::BrightYellow,
+    Color::BrightMagenta,
+    Color::BrightCyan,
+    Color::BrightRed,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockKind {
+    Activity {
+        name: String,
+        category: String,
+        manual: bool,
+    },
+    // Consecutive activities shorter than a minute each
+    Switches {
+        count: usize,
+    },
+    // Tracking deliberately paused or snoozed
+    Gap {
+        reason: String,
+    },
+    // Nothing recorded: the computer was idle or the daemon was not running
+    Untracked,
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelineBlock {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub kind: BlockKind,
+}
+
+impl TimelineBlock {
+    pub fn length(&self) -> Duration {
+        (self.end - self.start).to_std().unwrap_or_default()
+    }
+}
+
+// Blocks between `from` and `to`, oldest first and without overlaps. Running activities end now.
+pub fn build(activities: &[Activity], gaps: &[Gap], from: DateTime<Local>, to: DateTime<Local>) -> Vec<TimelineBlock> {
+    let to = to.min(Local::now());
+    let mut recorded: Vec<TimelineBlock> = activities.iter()
+        .map(|a| TimelineBlock {
+            start: a.start_time.max(from),
+            end: a.duration
+                .and_then(|d| ChronoDuration::from_std(d).ok())
+                .map(|d| a.start_time + d)
+                .unwrap_or(to)
+                .min(to),
+            kind: BlockKind::Activity {
+                name: a.name.clone(),
+                category: a.category.clone(),
+                manual: !a.tags.iter().any(|t| t == AUTOMATIC_TAG),
+            },
+        })
+        .filter(|b| b.end > b.start)
+        .collect();
+    recorded.sort_by_key(|b| b.start);
+
+    // Overlapping entries (e.g. imported next to tracked time) give way to the one that began first
+    let mut activity_blocks: Vec<TimelineBlock> = Vec::with_capacity(recorded.len());
+    for mut block in recorded {
+        if let Some(previous) = activity_blocks.last_mut() {
+            block.start = block.start.max(previous.end);
+            if block.end <= block.start {
+                continue;
+            }
+            // Consecutive samples of the same thing read as one block
+            if previous.kind == block.kind && block.start - previous.end < MIN_BLOCK {
+                previous.end = block.end;
+                continue;
+            }
+        }
+        activity_blocks.push(block);
+    }
+
+    let mut collapsed: Vec<TimelineBlock> = Vec::with_capacity(activity_blocks.len());
+    for block in activity_blocks {
+        if block.end - block.start >= MIN_BLOCK {
+            collapsed.push(block);
+            continue;
+        }
+        match collapsed.last_mut() {
+            Some(TimelineBlock { end, kind: BlockKind::Switches { count }, .. }) if block.start - *end < MIN_BLOCK => {
+                *end = block.end;
+                *count += 1;
+            },
+            _ => collapsed.push(TimelineBlock {
+                start: block.start,
+                end: block.end,
+                kind: BlockKind::Switches { count: 1 },
+            }),
+        }
+    }
+
+    // Holes are filled from the first to the last thing recorded, pauses included
+    let gaps: Vec<(DateTime<Local>, DateTime<Local>, &str)> = gaps.iter()
+        .map(|g| (g.start_time.max(from), g.end_time.min(to), g.reason.as_str()))
+        .filter(|(start, end, _)| end > start)
+        .collect();
+    let first = collapsed.first().map(|b| b.start).into_iter().chain(gaps.iter().map(|g| g.0)).min();
+    let last = collapsed.last().map(|b| b.end).into_iter().chain(gaps.iter().map(|g| g.1)).max();
+    let (Some(first), Some(last)) = (first, last) else {
+        return Vec::new();
+    };
+
+    let mut blocks = Vec::new();
+    let mut cursor = first;
+    let mut next = collapsed.into_iter().peekable();
+    loop {
+        let hole_end = next.peek().map(|b| b.start).unwrap_or(last);
+        if hole_end > cursor {
+            fill_hole(&mut blocks, &gaps, cursor, hole_end);
+        }
+        match next.next() {
+            Some(block) => {
+                cursor = block.end;
+                blocks.push(block);
+            },
+            None => break,
+        }
+    }
+    blocks
+}
+
+// Splits the time between two blocks into pauses and untracked stretches
+fn fill_hole(blocks: &mut Vec<TimelineBlock>, gaps: &[(DateTime<Local>, DateTime<Local>, &str)], start: DateTime<Local>, end: DateTime<Local>) {
+    let mut cursor = start;
+    for (gap_start, gap_end, reason) in gaps {
+        let (gap_start, gap_end) = ((*gap_start).max(cursor), (*gap_end).min(end));
+        if gap_end <= gap_start {
+            continue;
+        }
+        push_hole(blocks, cursor, gap_start, BlockKind::Untracked);
+        push_hole(blocks, gap_start, gap_end, BlockKind::Gap { reason: reason.to_string() });
+        cursor = gap_end;
+    }
+    push_hole(blocks, cursor, end, BlockKind::Untracked);
+}
+
+fn push_hole(blocks: &mut Vec<TimelineBlock>, start: DateTime<Local>, end: DateTime<Local>, kind: BlockKind) {
+    if end - start >= MIN_BLOCK {
+        blocks.push(TimelineBlock { start, end, kind });
+    }
+}
+
+// Colours go to categories by time spent, so the main ones are the easiest to tell apart
+pub fn category_colors(blocks: &[TimelineBlock]) -> Vec<(String, Color)> {
+    let mut totals: HashMap<&str, Duration> = HashMap::new();
+    for block in blocks {
+        if let BlockKind::Activity { category, .. } = &block.kind {
+            *totals.entry(category.as_str()).or_default() += block.length();
+        }
+    }
+    let mut categories: Vec<(&str, Duration)> = totals.into_iter().collect();
+    categories.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    categories.into_iter()
+        .enumerate()
+        .map(|(i, (category, _))| (category.to_string(), PALETTE[i % PALETTE.len()]))
+        .collect()
+}
+
+fn color_of(colors: &[(String, Color)], category: &str) -> Color {
+    colors.iter()
+        .find(|(name, _)| name == category)
+        .map(|(_, color)| *color)
+        .unwrap_or(Color::White)
+}
+
+fn glyph(block: &TimelineBlock, colors: &[(String, Color)]) -> ColoredString {
+    match &block.kind {
+        BlockKind::Activity { category, manual: true, .. } => "▓".color(color_of(colors, category)),
+        BlockKind::Activity { category, manual: false, .. } => "█".color(color_of(colors, category)),
+        BlockKind::Switches { .. } => "┊".normal(),
+        BlockKind::Gap { .. } => "░".dimmed(),
+        BlockKind::Untracked => "·".dimmed(),
+    }
+}
+
+// One line per hour, one cell per minute. A cell shows whatever covers most of its minute.
+pub fn print_hours(blocks: &[TimelineBlock]) {
+    let colors = category_colors(blocks);
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+        return;
+    };
+    let mut hour = first.start.with_minute(0).and_then(|t| t.with_second(0)).and_then(|t| t.with_nanosecond(0)).unwrap_or(first.start);
+
+    println!("{}", "      :00       :10       :20       :30       :40       :50".dimmed());
+    while hour < last.end {
+        let mut line = String::new();
+        let mut covered: HashMap<&str, Duration> = HashMap::new();
+        for minute in 0..60 {
+            let cell_start = hour + ChronoDuration::minutes(minute);
+            let cell_end = cell_start + ChronoDuration::minutes(1);
+            let cell = blocks.iter()
+                .filter(|b| b.start < cell_end && b.end > cell_start)
+                .max_by_key(|b| b.end.min(cell_end) - b.start.max(cell_start));
+            match cell {
+                Some(block) => {
+                    line.push_str(&glyph(block, &colors).to_string());
+                    if let BlockKind::Activity { category, .. } = &block.kind {
+                        *covered.entry(category.as_str()).or_default() += Duration::from_secs(60);
+                    }
+                },
+                None => line.push(' '),
+            }
+        }
+        let main = covered.into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+            .map(|(category, time)| format!("{} {}", category, format_short(time)))
+            .unwrap_or_default();
+        println!("{}  {}  {}", hour.format("%H:%M"), line, main.dimmed());
+        hour += ChronoDuration::hours(1);
+    }
+
+    println!();
+    let legend: Vec<String> = colors.iter()
+        .map(|(category, color)| format!("{} {}", "█".color(*color), category))
+        .collect();
+    println!("{}", legend.join("  "));
+    println!("{}", "█ automatic  ▓ manual  ┊ short switches  ░ paused  · idle / untracked".dimmed());
+}
+
+// One line per contiguous block
+pub fn print_blocks(blocks: &[TimelineBlock]) {
+    let colors = category_colors(blocks);
+    for block in blocks {
+        let span = format!("{}–{}", block.start.format("%H:%M"), block.end.format("%H:%M"));
+        let length = format!("{:>7}", format_short(block.length()));
+        // A cell per started ten minutes, padded by hand since the colour codes throw off `{:<}`
+        let cells = block.length().as_secs().div_ceil(600).clamp(1, 12) as usize;
+        let bar = format!("{}{}", glyph(block, &colors).to_string().repeat(cells), " ".repeat(12 - cells));
+        let description = match &block.kind {
+            BlockKind::Activity { name, category, manual } => {
+                let description = format!("{} {}", name.bold(), format!("({})", category).color(color_of(&colors, category)));
+                if *manual { format!("{} {}", description, "manual".cyan()) } else { description }
+            },
+            BlockKind::Switches { count: 1 } => "1 short activity".dimmed().to_string(),
+            BlockKind::Switches { count } => format!("{} short switches", count).dimmed().to_string(),
+            BlockKind::Gap { reason } => reason.dimmed().to_string(),
+            BlockKind::Untracked => "idle / untracked".dimmed().to_string(),
+        };
+        println!("{}  {}  {} {}", span, length, bar, description);
+    }
+}
+
+// Total time per kind of block, for the line under either view
+pub fn print_totals(blocks: &[TimelineBlock]) {
+    let (mut automatic, mut manual, mut switches, mut paused, mut untracked) = (Duration::ZERO, Duration::ZERO, Duration::ZERO, Duration::ZERO, Duration::ZERO);
+    for block in blocks {
+        let total = match &block.kind {
+            BlockKind::Activity { manual: false, .. } => &mut automatic,
+            BlockKind::Activity { manual: true, .. } => &mut manual,
+            BlockKind::Switches { .. } => &mut switches,
+            BlockKind::Gap { .. } => &mut paused,
+            BlockKind::Untracked => &mut untracked,
+        };
+        *total += block.length();
+    }
+    println!(
+        "Tracked {} ({} automatic, {} manual, {} in short switches) · paused {} · idle / untracked {}",
+        format_short(automatic + manual + switches).bold(),
+        format_short(automatic),
+        format_short(manual),
+        format_short(switches),
+        format_short(paused),
+        format_short(untracked),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::test_support::{self, finished};
+    use chrono::Utc;
+
+    // On Monday 2 March, to the second
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        test_support::at(2, hour, minute) + ChronoDuration::seconds(second as i64)
+    }
+
+    fn activity(name: &str, automatic: bool, start: DateTime<Local>, end: DateTime<Local>) -> Activity {
+        let tags: &[&str] = if automatic { &[AUTOMATIC_TAG] } else { &[] };
+        finished(start, end).name(name).tags(tags).build()
+    }
+
+    fn gap(reason: &str, start: DateTime<Local>, end: DateTime<Local>) -> Gap {
+        Gap { id: None, start_time: start, end_time: end, reason: reason.to_string() }
+    }
+
+    fn layout(activities: &[Activity], gaps: &[Gap]) -> Vec<(String, String, String)> {
+        let time = |t: DateTime<Local>| t.with_timezone(&Utc).format("%H:%M:%S").to_string();
+        build(activities, gaps, at(0, 0, 0), at(23, 59, 59)).into_iter()
+            .map(|b| {
+                let label = match b.kind {
+                    BlockKind::Activity { name, manual, .. } => if manual { format!("{} (manual)", name) } else { name },
+                    BlockKind::Switches { count } => format!("{} switches", count),
+                    BlockKind::Gap { reason } => format!("gap: {}", reason),
+                    BlockKind::Untracked => "untracked".to_string(),
+                };
+                (time(b.start), time(b.end), label)
+            })
+            .collect()
+    }
+
+    fn block(start: &str, end: &str, label: &str) -> (String, String, String) {
+        (start.to_string(), end.to_string(), label.to_string())
+    }
+
+    #[test]
+    fn overlapping_activities_give_way_to_the_one_that_began_first() {
+        let blocks = layout(&[
+            activity("Code", true, at(9, 30, 0), at(10, 30, 0)),
+            activity("Standup", false, at(9, 0, 0), at(10, 0, 0)),
+            activity("Slack", true, at(9, 10, 0), at(9, 20, 0)),
+        ], &[]);
+        assert_eq!(blocks, [
+            block("09:00:00", "10:00:00", "Standup (manual)"),
+            block("10:00:00", "10:30:00", "Code"),
+        ]);
+    }
+
+    #[test]
+    fn consecutive_samples_of_one_activity_become_one_block() {
+        let blocks = layout(&[
+            activity("Code", true, at(9, 0, 0), at(9, 5, 0)),
+            activity("Code", true, at(9, 5, 30), at(9, 10, 0)),
+            activity("Code", true, at(9, 10, 0), at(9, 20, 0)),
+            activity("Terminal", true, at(9, 20, 0), at(9, 30, 0)),
+            activity("Code", true, at(9, 30, 0), at(9, 40, 0)),
+        ], &[]);
+        assert_eq!(blocks, [
+            block("09:00:00", "09:20:00", "Code"),
+            block("09:20:00", "09:30:00", "Terminal"),
+            block("09:30:00", "09:40:00", "Code"),
+        ]);
+    }
+
+    #[test]
+    fn sub_minute_activities_collapse_into_switches() {
+        let blocks = layout(&[
+            activity("Code", true, at(9, 0, 0), at(9, 10, 0)),
+            activity("Slack", true, at(9, 10, 0), at(9, 10, 20)),
+            activity("Mail", true, at(9, 10, 20), at(9, 10, 50)),
+            activity("Safari", true, at(9, 10, 50), at(9, 11, 5)),
+            activity("Code", true, at(9, 11, 5), at(9, 20, 0)),
+            activity("Slack", true, at(9, 30, 0), at(9, 30, 10)),
+        ], &[]);
+        assert_eq!(blocks, [
+            block("09:00:00", "09:10:00", "Code"),
+            block("09:10:00", "09:11:05", "3 switches"),
+            block("09:11:05", "09:20:00", "Code"),
+            block("09:20:00", "09:30:00", "untracked"),
+            block("09:30:00", "09:30:10", "1 switches"),
+        ]);
+    }
+
+    #[test]
+    fn holes_split_into_pauses_and_untracked_time() {
+        let blocks = layout(&[
+            activity("Code", true, at(9, 0, 0), at(9, 10, 0)),
+            activity("Code", true, at(10, 20, 0), at(10, 30, 0)),
+            // Too short a hole to show
+            activity("Terminal", true, at(10, 30, 40), at(10, 40, 0)),
+        ], &[gap("paused", at(9, 30, 0), at(10, 0, 0))]);
+        assert_eq!(blocks, [
+            block("09:00:00", "09:10:00", "Code"),
+            block("09:10:00", "09:30:00", "untracked"),
+            block("09:30:00", "10:00:00", "gap: paused"),
+            block("10:00:00", "10:20:00", "untracked"),
+            block("10:20:00", "10:30:00", "Code"),
+            block("10:30:40", "10:40:00", "Terminal"),
+        ]);
+    }
+}
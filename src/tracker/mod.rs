@@ -9,28 +9,47 @@ pub struct Tracker {
 
 impl Tracker {
     pub fn new() -> Result<Self> {
-        let db = Database::new()?;
+        Self::with_database(Database::new()?)
+    }
+    
+    pub fn with_database(db: Database) -> Result<Self> {
+        // Pick up an activity started by an earlier invocation
+        let current_activity = db.get_open_activity()?;
         Ok(Self {
             db,
-            current_activity: None,
+            current_activity,
         })
     }
     
-    pub fn start_activity(&mut self, name: String, category: String, tags: Vec<String>, is_productive: bool) -> Result<()> {
+    pub fn start_activity(&mut self, name: String, category: String, tags: Vec<String>, weight: i8) -> Result<()> {
         if self.current_activity.is_some() {
             self.stop_activity()?;
         }
         
-        let activity = Activity::new(name, category, tags, is_productive);
+        // Saved right away without an end time so other processes can see and stop it
+        let mut activity = Activity::new(name, category, tags, weight > 0);
+        activity.set_weight(weight);
+        activity.id = Some(self.db.save_activity(&activity)?);
         self.current_activity = Some(activity);
         Ok(())
     }
     
-    pub fn stop_activity(&mut self) -> Result<()> {
+    // Returns the finished activity as it was saved, if one was running
+    pub fn stop_activity(&mut self) -> Result<Option<Activity>> {
         if let Some(mut activity) = self.current_activity.take() {
             activity.stop();
-            self.db.save_activity(&activity)?;
+            match activity.id {
+                Some(_) => self.db.update_activity(&activity)?,
+                None => activity.id = Some(self.db.save_activity(&activity)?),
+            }
+            return Ok(Some(activity));
         }
+        Ok(None)
+    }
+    
+    // Another process may have started or stopped an activity since this tracker was created
+    pub fn refresh(&mut self) -> Result<()> {
+        self.current_activity = self.db.get_open_activity()?;
         Ok(())
     }
     
@@ -38,6 +57,10 @@ impl Tracker {
         self.current_activity.as_ref()
     }
     
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+    
     // Additional tracker methods will be added here
 }
 